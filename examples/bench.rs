@@ -0,0 +1,95 @@
+//! Measures how many frames the emulator runs per second, without a window
+//!
+//! Runs the given ROM, or the boot ROM with no cartridge inserted when there isn't one:
+//!
+//! ```text
+//! cargo run --release --example bench -- [ROM_PATH] [--frames N]
+//! ```
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use gameboy_emulator::{
+    error::EmulatorError, read_cartridge, Emulator, InputState, FRAMES_PER_SECOND,
+};
+
+#[derive(Debug, Parser)]
+#[command(about = "Measures emulation speed in frames per second")]
+struct Args {
+    #[arg(value_name = "ROM_PATH", help = "The ROM to run. Optional.")]
+    cartridge_rom_path: Option<PathBuf>,
+    #[arg(
+        short = 'f',
+        long = "frames",
+        default_value_t = 600,
+        help = "How many frames to run"
+    )]
+    frames: u32,
+}
+
+struct BenchResult {
+    frames: u32,
+    elapsed: Duration,
+}
+
+impl BenchResult {
+    fn frames_per_second(&self) -> f64 {
+        self.frames as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+fn run(emulator: &mut Emulator, frames: u32) -> Result<BenchResult, EmulatorError> {
+    let start = Instant::now();
+
+    for _ in 0..frames {
+        emulator.run_frame(InputState::empty())?;
+    }
+
+    Ok(BenchResult {
+        frames,
+        elapsed: start.elapsed(),
+    })
+}
+
+fn main() -> Result<(), EmulatorError> {
+    let args = Args::parse();
+
+    let mut builder = Emulator::builder().lock_on_invalid_opcode(true);
+
+    if let Some(path) = &args.cartridge_rom_path {
+        builder = builder.with_cartridge(read_cartridge(path)?);
+    }
+
+    let mut emulator = builder.build();
+    let result = run(&mut emulator, args.frames)?;
+    let fps = result.frames_per_second();
+
+    println!(
+        "{} frames in {:.3} s: {:.1} FPS ({:.1}x real time)",
+        result.frames,
+        result.elapsed.as_secs_f64(),
+        fps,
+        fps / FRAMES_PER_SECOND
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use gameboy_emulator::Emulator;
+
+    use super::run;
+
+    #[test]
+    fn bench_completes() {
+        let mut emulator = Emulator::builder().build();
+        let result = run(&mut emulator, 10).unwrap();
+
+        assert_eq!(result.frames, 10);
+        assert!(result.frames_per_second() > 0.0);
+    }
+}
@@ -0,0 +1,78 @@
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A set of addresses stored as one bit per address across the whole 64 KiB address space, so
+/// that membership can be checked in constant time on every instruction
+#[derive(Clone)]
+pub struct AddressSet {
+    bits: [u64; 0x10000 / WORD_BITS],
+}
+
+impl AddressSet {
+    pub fn new() -> Self {
+        Self {
+            bits: [0; 0x10000 / WORD_BITS],
+        }
+    }
+
+    fn position(address: u16) -> (usize, u64) {
+        let address = address as usize;
+        (address / WORD_BITS, 1 << (address % WORD_BITS))
+    }
+
+    /// Returns true if the address was not already in the set
+    pub fn insert(&mut self, address: u16) -> bool {
+        let (word, mask) = Self::position(address);
+        let inserted = self.bits[word] & mask == 0;
+        self.bits[word] |= mask;
+        inserted
+    }
+
+    pub fn remove(&mut self, address: u16) {
+        let (word, mask) = Self::position(address);
+        self.bits[word] &= !mask;
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, address: u16) -> bool {
+        let (word, mask) = Self::position(address);
+        self.bits[word] & mask != 0
+    }
+
+    pub fn clear(&mut self) {
+        self.bits = [0; 0x10000 / WORD_BITS];
+    }
+}
+
+impl Default for AddressSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AddressSet;
+
+    #[test]
+    fn many_addresses_resolve() {
+        let mut set = AddressSet::new();
+
+        for address in (0..=0xFFFFu16).step_by(3) {
+            assert!(set.insert(address));
+        }
+        assert!(!set.insert(0x0000));
+
+        for address in 0..=0xFFFFu16 {
+            assert_eq!(set.contains(address), address % 3 == 0, "{:04x}", address);
+        }
+
+        set.remove(0xFFFF);
+        set.remove(0x0001);
+        assert!(!set.contains(0xFFFF));
+        assert!(set.contains(0xFFFC));
+        assert!(set.contains(0x0000));
+
+        set.clear();
+        assert!((0..=0xFFFFu16).all(|address| !set.contains(address)));
+    }
+}
@@ -0,0 +1,72 @@
+use super::scale::display_size;
+
+/// Where a bezel image and the display inside it are drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BezelLayout {
+    /// The on-screen size of the bezel
+    pub size: [f32; 2],
+    /// The position of the display's top left corner, relative to the top left of the bezel
+    pub display_offset: [f32; 2],
+}
+
+/// Lays out a bezel image of the given size in pixels around the display, which is centered in it
+///
+/// Bezel images are drawn with one image pixel per Game Boy pixel, so they grow with the display.
+/// A bezel smaller than the display is stretched to at least the display's size.
+pub fn bezel_layout(image_size: [f32; 2], scale: u32) -> BezelLayout {
+    let display = display_size(scale);
+    let size = [
+        (image_size[0] * scale as f32).max(display[0]),
+        (image_size[1] * scale as f32).max(display[1]),
+    ];
+
+    BezelLayout {
+        size,
+        display_offset: [
+            ((size[0] - display[0]) / 2.0).floor(),
+            ((size[1] - display[1]) / 2.0).floor(),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bezel_layout, BezelLayout};
+
+    #[test]
+    fn display_centered_in_bezel() {
+        assert_eq!(
+            bezel_layout([200.0, 180.0], 1),
+            BezelLayout {
+                size: [200.0, 180.0],
+                display_offset: [20.0, 18.0],
+            }
+        );
+
+        assert_eq!(
+            bezel_layout([200.0, 180.0], 3),
+            BezelLayout {
+                size: [600.0, 540.0],
+                display_offset: [60.0, 54.0],
+            }
+        );
+    }
+
+    #[test]
+    fn odd_margins_round_down() {
+        let layout = bezel_layout([161.0, 147.0], 1);
+
+        assert_eq!(layout.display_offset, [0.0, 1.0]);
+    }
+
+    #[test]
+    fn small_bezel_covers_display() {
+        assert_eq!(
+            bezel_layout([100.0, 100.0], 2),
+            BezelLayout {
+                size: [320.0, 288.0],
+                display_offset: [0.0, 0.0],
+            }
+        );
+    }
+}
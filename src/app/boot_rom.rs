@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+
+use gameboy_emulator::{
+    boot::{BootRom, DEFAULT_BOOT_ROM, OPEN_SOURCE_BOOT_ROM},
+    error::EmulatorError,
+    read_boot_rom,
+};
+
+/// Which boot ROM the emulator starts with
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum BootRomChoice {
+    /// The bundled DMG boot ROM
+    #[default]
+    Default,
+    /// The bundled open-source replacement
+    OpenSource,
+    /// No boot ROM, starting the cartridge at 0x0100 as the DMG boot ROM would leave it
+    Skip,
+    /// A boot ROM read from a file
+    File(PathBuf),
+}
+
+impl BootRomChoice {
+    pub const BUNDLED: [BootRomChoice; 3] = [
+        BootRomChoice::Default,
+        BootRomChoice::OpenSource,
+        BootRomChoice::Skip,
+    ];
+
+    /// The value used for this setting in the settings file, which is the path for a file
+    pub fn name(&self) -> String {
+        match self {
+            Self::Default => "default".to_string(),
+            Self::OpenSource => "open_source".to_string(),
+            Self::Skip => "skip".to_string(),
+            Self::File(path) => path.display().to_string(),
+        }
+    }
+
+    /// Parses a value written by [`name`](Self::name), anything other than a bundled boot ROM's
+    /// name is taken as a path
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.is_empty() {
+            return None;
+        }
+
+        let choice = Self::BUNDLED
+            .into_iter()
+            .find(|choice| choice.name() == name)
+            .unwrap_or_else(|| Self::File(PathBuf::from(name)));
+
+        Some(choice)
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::Default => "Default".to_string(),
+            Self::OpenSource => "Open Source".to_string(),
+            Self::Skip => "None (Skip)".to_string(),
+            Self::File(path) => file_label(path),
+        }
+    }
+
+    /// Reads the boot ROM, which is `None` when the boot ROM is skipped
+    pub fn read(&self) -> Result<Option<BootRom>, EmulatorError> {
+        match self {
+            Self::Default => Ok(Some(DEFAULT_BOOT_ROM)),
+            Self::OpenSource => Ok(Some(OPEN_SOURCE_BOOT_ROM)),
+            Self::Skip => Ok(None),
+            Self::File(path) => read_boot_rom(path).map(Some),
+        }
+    }
+}
+
+/// The file's name, or the whole path if it doesn't have one
+pub fn file_label(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::BootRomChoice;
+
+    #[test]
+    fn names_round_trip() {
+        let file = BootRomChoice::File(PathBuf::from("/home/user/boot/dmg_boot.bin"));
+
+        for choice in BootRomChoice::BUNDLED.into_iter().chain([file]) {
+            assert_eq!(BootRomChoice::from_name(&choice.name()), Some(choice));
+        }
+
+        assert_eq!(BootRomChoice::from_name(""), None);
+    }
+
+    #[test]
+    fn skip_has_no_boot_rom() {
+        assert!(BootRomChoice::Skip.read().unwrap().is_none());
+        assert!(BootRomChoice::Default.read().unwrap().is_some());
+        assert!(BootRomChoice::File(PathBuf::from("does-not-exist.bin"))
+            .read()
+            .is_err());
+    }
+}
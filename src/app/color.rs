@@ -0,0 +1,135 @@
+use eframe::egui::Color32;
+
+/// The gamma curve applied by LCD color correction
+const LCD_GAMMA: f32 = 1.4;
+/// The DMG's LCD never gets fully dark or fully light, so corrected levels are kept in this range
+const LCD_BLACK_LEVEL: f32 = 0.06;
+const LCD_WHITE_LEVEL: f32 = 0.94;
+
+/// How the emulator's output colors are adjusted before they are displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorCorrection {
+    /// The palette colors are shown as they are
+    Raw,
+    /// A gamma curve and reduced contrast, closer to how a real LCD looks
+    LcdCorrected,
+}
+
+impl ColorCorrection {
+    pub const ALL: [ColorCorrection; 2] = [ColorCorrection::Raw, ColorCorrection::LcdCorrected];
+
+    /// The name used for this setting in the settings file
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Raw => "raw",
+            Self::LcdCorrected => "lcd",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Raw => "Raw",
+            Self::LcdCorrected => "LCD-Corrected",
+        }
+    }
+
+    /// The value each color channel is mapped to
+    fn channel_table(self) -> [u8; 256] {
+        let mut table = [0; 256];
+
+        for (value, corrected) in table.iter_mut().enumerate() {
+            *corrected = match self {
+                Self::Raw => value as u8,
+                Self::LcdCorrected => {
+                    let linear = (value as f32 / 255.0).powf(LCD_GAMMA);
+                    let level = LCD_BLACK_LEVEL + linear * (LCD_WHITE_LEVEL - LCD_BLACK_LEVEL);
+                    (level * 255.0).round() as u8
+                }
+            };
+        }
+
+        table
+    }
+}
+
+/// Applies a [`ColorCorrection`] to finished frames, so emulation is unaffected
+pub struct ColorCorrector {
+    mode: ColorCorrection,
+    table: [u8; 256],
+    corrected: Vec<Color32>,
+}
+
+impl ColorCorrector {
+    pub fn new(mode: ColorCorrection) -> Self {
+        Self {
+            mode,
+            table: mode.channel_table(),
+            corrected: Vec::new(),
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: ColorCorrection) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.table = mode.channel_table();
+        }
+    }
+
+    /// Returns the frame with every color corrected
+    pub fn correct(&mut self, frame: &[Color32]) -> &[Color32] {
+        let table = &self.table;
+
+        self.corrected.clear();
+        self.corrected.extend(frame.iter().map(|color| {
+            Color32::from_rgba_premultiplied(
+                table[color.r() as usize],
+                table[color.g() as usize],
+                table[color.b() as usize],
+                color.a(),
+            )
+        }));
+
+        &self.corrected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gameboy_emulator::ppu::{DARKEST_COLOR, LIGHTEST_COLOR, OFF_COLOR};
+
+    use super::{ColorCorrection, ColorCorrector};
+
+    const PALETTE: [eframe::egui::Color32; 3] = [LIGHTEST_COLOR, DARKEST_COLOR, OFF_COLOR];
+
+    #[test]
+    fn raw_is_identity() {
+        let mut corrector = ColorCorrector::new(ColorCorrection::Raw);
+
+        assert_eq!(corrector.correct(&PALETTE), PALETTE.as_slice());
+    }
+
+    #[test]
+    fn lcd_correction_changes_colors() {
+        let mut corrector = ColorCorrector::new(ColorCorrection::LcdCorrected);
+        let corrected = corrector.correct(&PALETTE).to_vec();
+
+        for (original, corrected) in PALETTE.iter().zip(&corrected) {
+            assert_ne!(original, corrected);
+            assert_eq!(original.a(), corrected.a());
+        }
+
+        corrector.set_mode(ColorCorrection::Raw);
+        assert_eq!(corrector.correct(&PALETTE), PALETTE.as_slice());
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for mode in ColorCorrection::ALL {
+            assert_eq!(ColorCorrection::from_name(mode.name()), Some(mode));
+        }
+    }
+}
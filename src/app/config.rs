@@ -0,0 +1,596 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, ErrorKind},
+    path::PathBuf,
+};
+
+use gameboy_emulator::AccuracyMode;
+
+use super::{
+    boot_rom::BootRomChoice,
+    color::ColorCorrection,
+    frame_skip::MAX_FRAME_SKIP,
+    input::{DEFAULT_TURBO_RATE, MAX_TURBO_RATE},
+    recents::Recents,
+    scale::{snap_scale, DEFAULT_SCALE},
+    scanlines::MAX_SCANLINE_INTENSITY,
+};
+
+const CONFIG_DIR_NAME: &str = "gameboy-emulator";
+const CONFIG_FILE_NAME: &str = "settings.cfg";
+
+/// The version of the settings format, written to the file so that later versions can migrate
+/// older files
+///
+/// Files without a version were written before it was added, and have the same keys as version 1.
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Settings which replace the global ones while a particular game is loaded
+///
+/// Each is stored as a `game = CHECKSUM key=value...` line, where the checksum is the cartridge
+/// header's global checksum in hexadecimal, for example `game = 8A2B color_correction=lcd`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GameOverride {
+    pub color_correction: Option<ColorCorrection>,
+    pub lcd_ghosting: Option<bool>,
+    pub accuracy: Option<AccuracyMode>,
+}
+
+/// The settings a game runs with, once its override has been applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameSettings {
+    pub color_correction: ColorCorrection,
+    pub lcd_ghosting: bool,
+    pub accuracy: AccuracyMode,
+}
+
+impl GameOverride {
+    /// The global settings with this override's changes made, where `default_accuracy` is the
+    /// accuracy the emulator was started with
+    pub fn apply(&self, settings: &Settings, default_accuracy: AccuracyMode) -> GameSettings {
+        GameSettings {
+            color_correction: self.color_correction.unwrap_or(settings.color_correction),
+            lcd_ghosting: self.lcd_ghosting.unwrap_or(settings.lcd_ghosting),
+            accuracy: self.accuracy.unwrap_or(default_accuracy),
+        }
+    }
+
+    /// Parses the value of a `game` line, returning the checksum it applies to
+    fn parse(value: &str) -> Option<(u16, Self)> {
+        let mut words = value.split_whitespace();
+        let checksum = u16::from_str_radix(words.next()?, 16).ok()?;
+        let mut game_override = Self::default();
+
+        for word in words {
+            let Some((key, value)) = word.split_once('=') else {
+                continue;
+            };
+
+            match key {
+                "color_correction" => {
+                    game_override.color_correction = ColorCorrection::from_name(value);
+                }
+                "lcd_ghosting" => game_override.lcd_ghosting = value.parse().ok(),
+                "accuracy" => game_override.accuracy = accuracy_from_name(value),
+                _ => {}
+            }
+        }
+
+        Some((checksum, game_override))
+    }
+
+    fn serialize(&self, checksum: u16) -> String {
+        let mut text = format!("{:04X}", checksum);
+
+        if let Some(color_correction) = self.color_correction {
+            text += &format!(" color_correction={}", color_correction.name());
+        }
+        if let Some(lcd_ghosting) = self.lcd_ghosting {
+            text += &format!(" lcd_ghosting={}", lcd_ghosting);
+        }
+        if let Some(accuracy) = self.accuracy {
+            text += &format!(" accuracy={}", accuracy_name(accuracy));
+        }
+
+        text
+    }
+}
+
+fn accuracy_name(accuracy: AccuracyMode) -> &'static str {
+    match accuracy {
+        AccuracyMode::Fast => "fast",
+        AccuracyMode::Accurate => "accurate",
+    }
+}
+
+fn accuracy_from_name(name: &str) -> Option<AccuracyMode> {
+    [AccuracyMode::Fast, AccuracyMode::Accurate]
+        .into_iter()
+        .find(|accuracy| accuracy_name(*accuracy) == name)
+}
+
+/// User settings which persist between runs of the emulator
+///
+/// They are stored as `key = value` lines, unknown keys and malformed values are ignored. Keys
+/// which hold a list, like `recent`, are repeated once per item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// The version of the format the settings were read from
+    pub version: u32,
+    pub scale: u32,
+    /// Blends each frame with the previous one, like the DMG's slow LCD
+    pub lcd_ghosting: bool,
+    /// Smooths the display by upscaling each frame with Scale2x
+    pub scale2x: bool,
+    /// How strongly every other row is darkened, as a percentage, 0 turns scanlines off
+    pub scanlines: u32,
+    /// How many frames go undrawn after each one that is drawn, to save time on slow machines
+    pub frame_skip: u32,
+    pub color_correction: ColorCorrection,
+    /// Rapidly presses and releases A while it is held
+    pub turbo_a: bool,
+    pub turbo_b: bool,
+    /// How many times per second turbo buttons are pressed
+    pub turbo_rate: u32,
+    /// Lets opposing d-pad directions be held together, rather than cancelling out
+    pub allow_opposing_directions: bool,
+    /// Draws the on-screen buttons and d-pad, hiding them leaves just the display and shell
+    pub show_controls: bool,
+    /// Shows the frame rate and emulation speed over the display
+    pub show_speed: bool,
+    /// An image drawn around the display, such as a console shell
+    pub bezel_path: Option<PathBuf>,
+    /// The boot ROM the emulator starts with, applied on startup and whenever a ROM is opened
+    pub boot_rom: BootRomChoice,
+    pub recents: Recents,
+    /// Boot ROM files which have been chosen before, most recently chosen first
+    pub recent_boot_roms: Recents,
+    /// Overrides for particular games, by their cartridge header's global checksum
+    pub game_overrides: BTreeMap<u16, GameOverride>,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            scale: DEFAULT_SCALE,
+            lcd_ghosting: false,
+            scale2x: false,
+            scanlines: 0,
+            frame_skip: 0,
+            color_correction: ColorCorrection::Raw,
+            turbo_a: false,
+            turbo_b: false,
+            turbo_rate: DEFAULT_TURBO_RATE,
+            allow_opposing_directions: false,
+            show_controls: true,
+            show_speed: false,
+            bezel_path: None,
+            boot_rom: BootRomChoice::Default,
+            recents: Recents::new(),
+            recent_boot_roms: Recents::new(),
+            game_overrides: BTreeMap::new(),
+        }
+    }
+
+    /// The override for the game with this global checksum, which overrides nothing if there
+    /// isn't one
+    pub fn game_override(&self, global_checksum: u16) -> GameOverride {
+        self.game_overrides
+            .get(&global_checksum)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Shows the on-screen controls if they were hidden, or hides them, returning whether they
+    /// are now shown
+    pub fn toggle_controls(&mut self) -> bool {
+        self.show_controls = !self.show_controls;
+        self.show_controls
+    }
+
+    /// Loads the settings from the user's config directory, falling back to the defaults
+    pub fn load() -> Self {
+        let Some(path) = config_path() else {
+            return Self::new();
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                let settings = Self::parse(&text);
+
+                if settings.version > SETTINGS_VERSION {
+                    eprintln!(
+                        "Settings in {} are from a newer version, some may be ignored",
+                        path.display()
+                    );
+                }
+
+                settings
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Self::new(),
+            Err(e) => {
+                eprintln!("Failed to read settings from {}: {}", path.display(), e);
+                Self::new()
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = config_path().ok_or_else(|| {
+            io::Error::new(ErrorKind::NotFound, "No config directory could be found")
+        })?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, self.serialize())
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut settings = Self::new();
+        settings.version = 0;
+        let mut recent_paths = Vec::new();
+        let mut recent_boot_rom_paths = Vec::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            match key.trim() {
+                "version" => {
+                    if let Ok(version) = value.trim().parse() {
+                        settings.version = version;
+                    }
+                }
+                "scale" => {
+                    if let Ok(scale) = value.trim().parse::<f32>() {
+                        settings.scale = snap_scale(scale);
+                    }
+                }
+                "lcd_ghosting" => {
+                    if let Ok(lcd_ghosting) = value.trim().parse() {
+                        settings.lcd_ghosting = lcd_ghosting;
+                    }
+                }
+                "scale2x" => {
+                    if let Ok(scale2x) = value.trim().parse() {
+                        settings.scale2x = scale2x;
+                    }
+                }
+                "scanlines" => {
+                    if let Ok(scanlines) = value.trim().parse::<u32>() {
+                        settings.scanlines = scanlines.min(MAX_SCANLINE_INTENSITY);
+                    }
+                }
+                "frame_skip" => {
+                    if let Ok(frame_skip) = value.trim().parse::<u32>() {
+                        settings.frame_skip = frame_skip.min(MAX_FRAME_SKIP);
+                    }
+                }
+                "color_correction" => {
+                    if let Some(color_correction) = ColorCorrection::from_name(value.trim()) {
+                        settings.color_correction = color_correction;
+                    }
+                }
+                "turbo_a" => {
+                    if let Ok(turbo_a) = value.trim().parse() {
+                        settings.turbo_a = turbo_a;
+                    }
+                }
+                "turbo_b" => {
+                    if let Ok(turbo_b) = value.trim().parse() {
+                        settings.turbo_b = turbo_b;
+                    }
+                }
+                "allow_opposing_directions" => {
+                    if let Ok(allow) = value.trim().parse() {
+                        settings.allow_opposing_directions = allow;
+                    }
+                }
+                "show_controls" => {
+                    if let Ok(show_controls) = value.trim().parse() {
+                        settings.show_controls = show_controls;
+                    }
+                }
+                "show_speed" => {
+                    if let Ok(show_speed) = value.trim().parse() {
+                        settings.show_speed = show_speed;
+                    }
+                }
+                "bezel_path" => {
+                    let path = value.trim();
+
+                    if !path.is_empty() {
+                        settings.bezel_path = Some(PathBuf::from(path));
+                    }
+                }
+                "boot_rom" => {
+                    if let Some(boot_rom) = BootRomChoice::from_name(value.trim()) {
+                        settings.boot_rom = boot_rom;
+                    }
+                }
+                "game" => {
+                    if let Some((checksum, game_override)) = GameOverride::parse(value) {
+                        settings.game_overrides.insert(checksum, game_override);
+                    }
+                }
+                "recent" => {
+                    let path = value.trim();
+
+                    if !path.is_empty() {
+                        recent_paths.push(PathBuf::from(path));
+                    }
+                }
+                "recent_boot_rom" => {
+                    let path = value.trim();
+
+                    if !path.is_empty() {
+                        recent_boot_rom_paths.push(PathBuf::from(path));
+                    }
+                }
+                "turbo_rate" => {
+                    if let Ok(turbo_rate) = value.trim().parse::<u32>() {
+                        settings.turbo_rate = turbo_rate.clamp(1, MAX_TURBO_RATE);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        settings.recents = Recents::from_paths(recent_paths);
+        settings.recent_boot_roms = Recents::from_paths(recent_boot_rom_paths);
+
+        settings
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut text = String::new();
+
+        text += &format!("version = {}\n", SETTINGS_VERSION);
+        text += &format!("scale = {}\n", self.scale);
+        text += &format!("lcd_ghosting = {}\n", self.lcd_ghosting);
+        text += &format!("scale2x = {}\n", self.scale2x);
+        text += &format!("scanlines = {}\n", self.scanlines);
+        text += &format!("frame_skip = {}\n", self.frame_skip);
+        text += &format!("color_correction = {}\n", self.color_correction.name());
+        text += &format!("turbo_a = {}\n", self.turbo_a);
+        text += &format!("turbo_b = {}\n", self.turbo_b);
+        text += &format!("turbo_rate = {}\n", self.turbo_rate);
+        text += &format!(
+            "allow_opposing_directions = {}\n",
+            self.allow_opposing_directions
+        );
+        text += &format!("show_controls = {}\n", self.show_controls);
+        text += &format!("show_speed = {}\n", self.show_speed);
+
+        if let Some(bezel_path) = &self.bezel_path {
+            text += &format!("bezel_path = {}\n", bezel_path.display());
+        }
+
+        text += &format!("boot_rom = {}\n", self.boot_rom.name());
+
+        for path in self.recents.paths() {
+            text += &format!("recent = {}\n", path.display());
+        }
+
+        for path in self.recent_boot_roms.paths() {
+            text += &format!("recent_boot_rom = {}\n", path.display());
+        }
+
+        for (checksum, game_override) in &self.game_overrides {
+            text += &format!("game = {}\n", game_override.serialize(*checksum));
+        }
+
+        text
+    }
+}
+
+/// The location of the settings file, inside the platform's config directory
+pub fn config_path() -> Option<PathBuf> {
+    let config_dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    }?;
+
+    Some(config_dir.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::scale::{DEFAULT_SCALE, MAX_SCALE};
+
+    use std::{collections::BTreeMap, path::Path};
+
+    use gameboy_emulator::AccuracyMode;
+
+    use crate::app::{boot_rom::BootRomChoice, recents::Recents};
+
+    use super::{ColorCorrection, GameOverride, GameSettings, Settings, SETTINGS_VERSION};
+
+    #[test]
+    fn round_trip() {
+        let mut recents = Recents::new();
+        recents.add_or_promote(Path::new("/home/user/roms/tetris.gb"));
+        recents.add_or_promote(Path::new("/home/user/roms/pokemon red.gb"));
+
+        let mut game_overrides = BTreeMap::new();
+        game_overrides.insert(
+            0x8A2B,
+            GameOverride {
+                color_correction: Some(ColorCorrection::Raw),
+                lcd_ghosting: Some(false),
+                accuracy: Some(AccuracyMode::Accurate),
+            },
+        );
+        game_overrides.insert(
+            0x0016,
+            GameOverride {
+                lcd_ghosting: Some(true),
+                ..Default::default()
+            },
+        );
+
+        let settings = Settings {
+            version: SETTINGS_VERSION,
+            scale: 5,
+            lcd_ghosting: true,
+            scale2x: true,
+            scanlines: 40,
+            frame_skip: 2,
+            color_correction: ColorCorrection::LcdCorrected,
+            turbo_a: true,
+            turbo_b: false,
+            turbo_rate: 20,
+            allow_opposing_directions: true,
+            show_controls: false,
+            show_speed: true,
+            bezel_path: Some("/home/user/bezels/dmg.png".into()),
+            boot_rom: BootRomChoice::Skip,
+            recents,
+            recent_boot_roms: Recents::new(),
+            game_overrides,
+        };
+
+        assert_eq!(Settings::parse(&settings.serialize()), settings);
+    }
+
+    #[test]
+    fn malformed_values_fall_back() {
+        let settings = Settings::parse(
+            "# comment\nscale = big\nlcd_ghosting = maybe\ncolor_correction = sepia\nbezel_path =\nunknown = 1\nnot a setting\n",
+        );
+
+        assert_eq!(settings.scale, DEFAULT_SCALE);
+        assert!(!settings.lcd_ghosting);
+        assert_eq!(settings.color_correction, ColorCorrection::Raw);
+        assert_eq!(settings.bezel_path, None);
+    }
+
+    #[test]
+    fn unversioned_file_is_version_0() {
+        let settings = Settings::parse("scale = 4\n");
+
+        assert_eq!(settings.version, 0);
+        assert_eq!(settings.scale, 4);
+        assert!(Settings::new().serialize().starts_with("version = 1\n"));
+    }
+
+    #[test]
+    fn toggling_controls_flips_visibility() {
+        let mut settings = Settings::new();
+        assert!(settings.show_controls);
+
+        assert!(!settings.toggle_controls());
+        assert!(!settings.show_controls);
+        assert!(!Settings::parse(&settings.serialize()).show_controls);
+
+        assert!(settings.toggle_controls());
+        assert!(settings.show_controls);
+    }
+
+    #[test]
+    fn game_override_applies_to_matching_cartridge() {
+        let settings = Settings::parse(
+            "color_correction = raw\ngame = 8A2B color_correction=lcd accuracy=fast\ngame = oops\n",
+        );
+        assert_eq!(settings.game_overrides.len(), 1);
+
+        let game_override = settings.game_override(0x8A2B);
+        assert_eq!(
+            game_override.color_correction,
+            Some(ColorCorrection::LcdCorrected)
+        );
+        assert_eq!(game_override.accuracy, Some(AccuracyMode::Fast));
+        assert_eq!(game_override.lcd_ghosting, None);
+
+        assert_eq!(settings.game_override(0x1234), GameOverride::default());
+    }
+
+    #[test]
+    fn game_override_changes_game_settings() {
+        let mut settings = Settings::parse("game = 8A2B color_correction=lcd accuracy=accurate\n");
+        settings.lcd_ghosting = true;
+
+        let default_accuracy = AccuracyMode::Fast;
+        let matching = settings.game_override(0x8A2B);
+        let matching = matching.apply(&settings, default_accuracy);
+        let other = settings.game_override(0x1234);
+        let other = other.apply(&settings, default_accuracy);
+
+        assert_eq!(
+            matching,
+            GameSettings {
+                color_correction: ColorCorrection::LcdCorrected,
+                lcd_ghosting: true,
+                accuracy: AccuracyMode::Accurate,
+            }
+        );
+        assert_eq!(
+            other,
+            GameSettings {
+                color_correction: ColorCorrection::Raw,
+                lcd_ghosting: true,
+                accuracy: AccuracyMode::Fast,
+            }
+        );
+    }
+
+    #[test]
+    fn turbo_rate_is_clamped() {
+        assert_eq!(Settings::parse("turbo_rate = 0").turbo_rate, 1);
+        assert_eq!(Settings::parse("turbo_rate = 1000").turbo_rate, 30);
+    }
+
+    #[test]
+    fn scanline_intensity_is_clamped() {
+        assert_eq!(Settings::parse("scanlines = 250").scanlines, 100);
+        assert_eq!(Settings::parse("scanlines = -5").scanlines, 0);
+    }
+
+    #[test]
+    fn frame_skip_is_clamped() {
+        assert_eq!(Settings::parse("frame_skip = 3").frame_skip, 3);
+        assert_eq!(Settings::parse("frame_skip = 60").frame_skip, 9);
+    }
+
+    #[test]
+    fn scale_is_snapped() {
+        assert_eq!(Settings::parse("scale = 100").scale, MAX_SCALE);
+        assert_eq!(Settings::parse("scale=3.2").scale, 3);
+    }
+
+    #[test]
+    fn boot_rom_path_round_trip() {
+        let path = Path::new("/home/user/boot/dmg boot.bin");
+
+        let mut settings = Settings::new();
+        settings.boot_rom = BootRomChoice::File(path.to_path_buf());
+        settings.recent_boot_roms.add_or_promote(path);
+        settings
+            .recent_boot_roms
+            .add_or_promote(Path::new("/home/user/boot/mgb_boot.bin"));
+
+        let parsed = Settings::parse(&settings.serialize());
+        assert_eq!(parsed.boot_rom, BootRomChoice::File(path.to_path_buf()));
+        assert_eq!(parsed.recent_boot_roms, settings.recent_boot_roms);
+
+        assert_eq!(Settings::new().boot_rom, BootRomChoice::Default);
+        assert_eq!(
+            Settings::parse("boot_rom = ").boot_rom,
+            BootRomChoice::Default
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use std::path::Path;
+
+/// The file extensions of Game Boy and Game Boy Color ROMs
+const ROM_EXTENSIONS: [&str; 2] = ["gb", "gbc"];
+
+/// Whether the file looks like a ROM from its extension, in any case
+pub fn is_rom_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ROM_EXTENSIONS
+                .iter()
+                .any(|rom_extension| extension.eq_ignore_ascii_case(rom_extension))
+        })
+}
+
+/// The first ROM among files dropped on the window, ignoring everything else
+pub fn first_rom<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Option<&'a Path> {
+    paths.into_iter().find(|path| is_rom_file(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{first_rom, is_rom_file};
+
+    #[test]
+    fn accepts_only_rom_extensions() {
+        assert!(is_rom_file(Path::new("roms/tetris.gb")));
+        assert!(is_rom_file(Path::new("Pokemon Crystal.GBC")));
+
+        assert!(!is_rom_file(Path::new("tetris.gba")));
+        assert!(!is_rom_file(Path::new("tetris.sav")));
+        assert!(!is_rom_file(Path::new("gb")));
+        assert!(!is_rom_file(Path::new("roms.gb/readme")));
+    }
+
+    #[test]
+    fn first_rom_skips_other_files() {
+        let paths = [
+            Path::new("notes.txt"),
+            Path::new("game.gbc"),
+            Path::new("other.gb"),
+        ];
+
+        assert_eq!(first_rom(paths), Some(Path::new("game.gbc")));
+        assert_eq!(first_rom([Path::new("boot.bin")]), None);
+    }
+}
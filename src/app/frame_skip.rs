@@ -0,0 +1,35 @@
+pub const MAX_FRAME_SKIP: u32 = 9;
+
+/// Whether the given frame should be shown, when `frame_skip` frames are skipped after each one
+/// that is
+///
+/// Every frame is still emulated, skipping only saves the work of drawing it.
+pub fn should_display_frame(frame: u64, frame_skip: u32) -> bool {
+    frame.is_multiple_of(frame_skip as u64 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::should_display_frame;
+
+    fn pattern(frame_skip: u32, frames: u64) -> Vec<bool> {
+        (0..frames)
+            .map(|frame| should_display_frame(frame, frame_skip))
+            .collect()
+    }
+
+    #[test]
+    fn no_skip_displays_every_frame() {
+        assert_eq!(pattern(0, 4), vec![true; 4]);
+    }
+
+    #[test]
+    fn skipped_frames_are_not_displayed() {
+        assert_eq!(pattern(1, 6), vec![true, false, true, false, true, false]);
+        assert_eq!(
+            pattern(2, 7),
+            vec![true, false, false, true, false, false, true]
+        );
+        assert_eq!(pattern(3, 8), [true, false, false, false].repeat(2));
+    }
+}
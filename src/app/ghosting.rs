@@ -0,0 +1,93 @@
+use eframe::egui::Color32;
+
+/// Blends each frame with the one before it, imitating the slow pixel response of the DMG's LCD
+///
+/// Some games flicker objects on alternate frames to make them look transparent, which only
+/// works with this blur. It is applied to finished frames, so emulation is unaffected.
+pub struct FrameBlender {
+    previous: Vec<Color32>,
+    blended: Vec<Color32>,
+}
+
+impl FrameBlender {
+    pub fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+            blended: Vec::new(),
+        }
+    }
+
+    /// Returns the average of this frame and the last one passed in
+    pub fn blend(&mut self, frame: &[Color32]) -> &[Color32] {
+        if self.previous.len() != frame.len() {
+            self.previous = frame.to_vec();
+        }
+
+        self.blended.clear();
+        self.blended.extend(
+            self.previous
+                .iter()
+                .zip(frame)
+                .map(|(previous, current)| blend_colors(*previous, *current)),
+        );
+
+        self.previous.copy_from_slice(frame);
+
+        &self.blended
+    }
+
+    /// Keeps this frame to blend the next one with, for frames which aren't shown
+    pub fn remember(&mut self, frame: &[Color32]) {
+        self.previous.clear();
+        self.previous.extend_from_slice(frame);
+    }
+}
+
+fn blend_colors(a: Color32, b: Color32) -> Color32 {
+    let average = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+
+    Color32::from_rgba_premultiplied(
+        average(a.r(), b.r()),
+        average(a.g(), b.g()),
+        average(a.b(), b.b()),
+        average(a.a(), b.a()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use gameboy_emulator::ppu::{DARKEST_COLOR, LIGHTEST_COLOR, TOTAL_PIXELS};
+
+    use super::FrameBlender;
+
+    #[test]
+    fn solid_frames_blend_to_midpoint() {
+        let mut blender = FrameBlender::new();
+        let light = vec![LIGHTEST_COLOR; TOTAL_PIXELS];
+        let dark = vec![DARKEST_COLOR; TOTAL_PIXELS];
+
+        // Nothing to blend with yet
+        assert_eq!(blender.blend(&light), light.as_slice());
+
+        let blended = blender.blend(&dark);
+        assert_eq!(blended.len(), TOTAL_PIXELS);
+        assert!(blended
+            .iter()
+            .all(|pixel| pixel.to_array() == [116, 136, 120, 255]));
+
+        assert_eq!(blender.blend(&dark), dark.as_slice());
+    }
+
+    #[test]
+    fn remembered_frame_is_blended_with() {
+        let mut blender = FrameBlender::new();
+        let light = vec![LIGHTEST_COLOR; TOTAL_PIXELS];
+        let dark = vec![DARKEST_COLOR; TOTAL_PIXELS];
+
+        blender.blend(&dark);
+        blender.remember(&light);
+
+        let blended = blender.blend(&light);
+        assert_eq!(blended, light.as_slice());
+    }
+}
@@ -0,0 +1,54 @@
+use gameboy_emulator::FRAMES_PER_SECOND;
+
+pub const DEFAULT_TURBO_RATE: u32 = 15;
+pub const MAX_TURBO_RATE: u32 = 30;
+
+/// Whether a held turbo button should be pressed on the given frame, when it is being pressed
+/// and released `rate_hz` times per second
+///
+/// The rate is limited by the frame rate, so anything above 30 Hz alternates every frame. A rate
+/// of 0 disables auto-fire, leaving the button pressed.
+pub fn should_autofire(frame: u64, rate_hz: u32) -> bool {
+    if rate_hz == 0 {
+        return true;
+    }
+
+    let frames_per_press = ((FRAMES_PER_SECOND / rate_hz as f64).round() as u64).max(2);
+
+    (frame % frames_per_press) < (frames_per_press / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::should_autofire;
+
+    fn pattern(rate_hz: u32, frames: u64) -> Vec<bool> {
+        (0..frames)
+            .map(|frame| should_autofire(frame, rate_hz))
+            .collect()
+    }
+
+    #[test]
+    fn autofire_patterns() {
+        assert_eq!(pattern(30, 6), vec![true, false, true, false, true, false]);
+        assert_eq!(
+            pattern(15, 8),
+            vec![true, true, false, false, true, true, false, false]
+        );
+        assert_eq!(pattern(10, 12), [[true; 3], [false; 3]].concat().repeat(2));
+        // Faster than the frame rate allows
+        assert_eq!(pattern(60, 4), vec![true, false, true, false]);
+        // Disabled
+        assert!(pattern(0, 10).into_iter().all(|pressed| pressed));
+    }
+
+    #[test]
+    fn autofire_rate_over_a_second() {
+        // Roughly 60 frames in a second
+        let presses = (0..60u64)
+            .filter(|frame| should_autofire(*frame, 10) && !should_autofire(frame + 1, 10))
+            .count();
+
+        assert_eq!(presses, 10);
+    }
+}
@@ -0,0 +1,114 @@
+use gameboy_emulator::ppu::{
+    BACKGROUND_MAP_SIZE_PIXELS, DISPLAY_HEIGHT_PIXELS, DISPLAY_WIDTH_PIXELS,
+};
+
+/// How far right the window register places the window's left edge, WX = 7 being the left of
+/// the display
+const WINDOW_X_OFFSET: usize = 7;
+
+/// An area of a 256x256 tile map, in pixels from its top left corner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// The area of the background map shown on the display at the given scroll (SCX, SCY)
+///
+/// The display wraps around the edges of the map, so this is split into up to four rectangles.
+pub fn viewport_rects(scroll_x: u8, scroll_y: u8) -> Vec<MapRect> {
+    let columns = wrapped_spans(scroll_x as usize, DISPLAY_WIDTH_PIXELS);
+    let rows = wrapped_spans(scroll_y as usize, DISPLAY_HEIGHT_PIXELS);
+
+    rows.iter()
+        .flat_map(|&(y, height)| {
+            columns.iter().map(move |&(x, width)| MapRect {
+                x,
+                y,
+                width,
+                height,
+            })
+        })
+        .collect()
+}
+
+/// The area of the window map shown on the display with the window at the given position
+/// (WX, WY), or `None` if the window is entirely off the display
+///
+/// The window is drawn from the top left of its map, unless WX puts its left edge off the display.
+pub fn window_rect(window_x: u8, window_y: u8) -> Option<MapRect> {
+    let left = (window_x as usize).saturating_sub(WINDOW_X_OFFSET);
+    let top = window_y as usize;
+
+    if left >= DISPLAY_WIDTH_PIXELS || top >= DISPLAY_HEIGHT_PIXELS {
+        return None;
+    }
+
+    Some(MapRect {
+        x: WINDOW_X_OFFSET.saturating_sub(window_x as usize),
+        y: 0,
+        width: DISPLAY_WIDTH_PIXELS - left,
+        height: DISPLAY_HEIGHT_PIXELS - top,
+    })
+}
+
+/// Splits a span of `length` starting at `start` into the parts before and after it wraps around
+/// the end of the map, as `(start, length)` pairs
+fn wrapped_spans(start: usize, length: usize) -> Vec<(usize, usize)> {
+    let before_edge = BACKGROUND_MAP_SIZE_PIXELS - start;
+
+    if length <= before_edge {
+        vec![(start, length)]
+    } else {
+        vec![(start, before_edge), (0, length - before_edge)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{viewport_rects, window_rect, MapRect};
+
+    fn rect(x: usize, y: usize, width: usize, height: usize) -> MapRect {
+        MapRect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn unwrapped_viewport_is_one_rect() {
+        assert_eq!(viewport_rects(0, 0), vec![rect(0, 0, 160, 144)]);
+        assert_eq!(viewport_rects(96, 112), vec![rect(96, 112, 160, 144)]);
+    }
+
+    #[test]
+    fn viewport_wraps_across_map_edges() {
+        assert_eq!(
+            viewport_rects(200, 0),
+            vec![rect(200, 0, 56, 144), rect(0, 0, 104, 144)]
+        );
+        assert_eq!(
+            viewport_rects(200, 180),
+            vec![
+                rect(200, 180, 56, 76),
+                rect(0, 180, 104, 76),
+                rect(200, 0, 56, 68),
+                rect(0, 0, 104, 68),
+            ]
+        );
+    }
+
+    #[test]
+    fn window_rect_shrinks_with_position() {
+        assert_eq!(window_rect(7, 0), Some(rect(0, 0, 160, 144)));
+        assert_eq!(window_rect(87, 100), Some(rect(0, 0, 80, 44)));
+        // With its left edge off the display, the start of the window map is hidden
+        assert_eq!(window_rect(0, 0), Some(rect(7, 0, 160, 144)));
+        assert_eq!(window_rect(167, 0), None);
+        assert_eq!(window_rect(7, 144), None);
+    }
+}
@@ -0,0 +1,1477 @@
+pub mod bezel;
+pub mod boot_rom;
+pub mod color;
+pub mod config;
+pub mod dropped_files;
+pub mod frame_skip;
+pub mod ghosting;
+pub mod input;
+pub mod map_viewer;
+pub mod recents;
+pub mod scale;
+pub mod scanlines;
+pub mod screenshot;
+pub mod speed;
+pub mod upscale;
+
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use bezel::bezel_layout;
+use boot_rom::{file_label, BootRomChoice};
+use color::{ColorCorrection, ColorCorrector};
+use config::{GameOverride, GameSettings, Settings};
+use dropped_files::first_rom;
+use eframe::{
+    egui::{
+        self, load::SizedTexture, text::LayoutJob, Color32, ColorImage, CornerRadius, FontId,
+        Label, Margin, Pos2, Rect, Sense, Shadow, Shape, TextFormat, Ui, Vec2, Widget,
+    },
+    epaint::{
+        text::{FontInsert, InsertFontFamily},
+        RectShape,
+    },
+};
+use frame_skip::{should_display_frame, MAX_FRAME_SKIP};
+use gameboy_emulator::{
+    io::{interrupts::Interrupt, lcd::TileMapArea},
+    ppu::{BACKGROUND_MAP_SIZE_PIXELS, DARKEST_COLOR, DISPLAY_SIZE_PIXELS, OFF_COLOR},
+    read_cartridge, AccuracyMode, CpuStatus, DPadButtonState, DPadState, Emulator, InputState,
+    CYCLES_PER_FRAME,
+};
+use ghosting::FrameBlender;
+use input::{should_autofire, MAX_TURBO_RATE};
+use map_viewer::{viewport_rects, window_rect};
+use scale::{display_size, fit_scale, pixels_per_mm, MAX_SCALE, MIN_SCALE};
+use scanlines::{apply_scanlines, MAX_SCANLINE_INTENSITY};
+use screenshot::{file_stem, screenshot_path};
+use speed::SpeedMeter;
+use upscale::upscale2x;
+
+const GAMEBOY_HEIGHT: f32 = 148.0; // mm
+const GAMEBOY_WIDTH: f32 = 90.0; // mm
+const BUTTON_DIAMETER: f32 = 10.0; // mm
+const DPAD_WIDTH: f32 = 20.0; // mm
+/// The pixels per millimetre that the fixed pixel sizes in the layout were designed for
+const BASE_PIXELS_PER_MM: f32 = 6.0;
+const MENU_BAR_HEIGHT: f32 = 24.0;
+const OUTER_MARGIN: f32 = 10.0;
+
+const GAMEBOY_COLOR: Color32 = Color32::from_rgb(193, 189, 186);
+const DISPLAY_FRAME_COLOR: Color32 = Color32::from_rgb(98, 95, 114);
+const FONT_COLOR: Color32 = Color32::from_rgb(67, 67, 142);
+const AB_BUTTON_COLOR: Color32 = Color32::from_rgb(151, 38, 94);
+const AB_BUTTON_CLICKED_COLOR: Color32 = Color32::from_rgb(131, 28, 79);
+const START_BUTTON_COLOR: Color32 = Color32::from_rgb(134, 127, 131);
+const START_BUTTON_CLICKED_COLOR: Color32 = Color32::from_rgb(124, 117, 121);
+const DPAD_BUTTON_COLOR: Color32 = Color32::from_rgb(96, 96, 96);
+const DPAD_BUTTON_CLICKED_COLOR: Color32 = Color32::from_rgb(86, 86, 86);
+const DROP_SHADOW: Shadow = Shadow {
+    offset: [4, 4],
+    blur: 2,
+    spread: 0,
+    color: Color32::GRAY,
+};
+
+/// The size of the window needed to show the Game Boy at the given display scale
+pub fn window_size(scale: u32) -> [f32; 2] {
+    let pixels_per_mm = pixels_per_mm(scale);
+
+    [
+        GAMEBOY_WIDTH * pixels_per_mm,
+        GAMEBOY_HEIGHT * pixels_per_mm + MENU_BAR_HEIGHT,
+    ]
+}
+
+pub struct EmuApp {
+    emulator: Emulator,
+    /// Whether a ROM has been opened, rather than running with an empty cartridge
+    rom_loaded: bool,
+    display_texture: egui::TextureHandle,
+    breakpoint_reached: bool,
+    input_state: InputState,
+    dpad: DPad,
+    settings: Settings,
+    /// The settings saved for the loaded game, which take the place of the global ones
+    game_override: GameOverride,
+    /// The accuracy the emulator was started with, used for games without an override
+    default_accuracy: AccuracyMode,
+    color_corrector: ColorCorrector,
+    frame_blender: FrameBlender,
+    /// Frames shown since starting, which times turbo buttons
+    frame_count: u64,
+    speed_meter: SpeedMeter,
+    fullscreen: bool,
+    serial_output: String,
+    show_serial_output: bool,
+    show_cartridge_info: bool,
+    show_map_viewer: bool,
+    show_interrupts: bool,
+    /// Whether the map viewer shows the window's tile map, rather than the background's
+    map_viewer_window: bool,
+    map_texture: Option<egui::TextureHandle>,
+    /// Where the Game Boy was drawn last frame, which screenshots are cropped to
+    gameboy_rect: Option<Rect>,
+}
+
+impl eframe::App for EmuApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let (toggle_fullscreen, toggle_controls) = ctx.input(|input| {
+            let arrow_up = input.key_down(egui::Key::ArrowUp);
+            let arrow_down = input.key_down(egui::Key::ArrowDown);
+            let arrow_left = input.key_down(egui::Key::ArrowLeft);
+            let arrow_right = input.key_down(egui::Key::ArrowRight);
+
+            let a_button = input.key_down(egui::Key::X);
+            let b_button = input.key_down(egui::Key::Z);
+
+            let start_button = input.key_down(egui::Key::Enter);
+            let select_button = input.key_down(egui::Key::Backspace);
+
+            let turbo_pressed = should_autofire(self.frame_count, self.settings.turbo_rate);
+
+            self.input_state = self
+                .input_state
+                .with_a(a_button && (turbo_pressed || !self.settings.turbo_a))
+                .with_b(b_button && (turbo_pressed || !self.settings.turbo_b))
+                .with_select(select_button)
+                .with_start(start_button);
+            self.dpad.keyboard_input_state =
+                DPadButtonState::new(arrow_up, arrow_down, arrow_left, arrow_right);
+
+            (
+                input.key_pressed(egui::Key::F11),
+                input.key_pressed(egui::Key::F10),
+            )
+        });
+
+        if toggle_fullscreen {
+            self.fullscreen = !self.fullscreen;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(self.fullscreen));
+        }
+
+        if toggle_controls {
+            self.settings.toggle_controls();
+            self.save_settings();
+        }
+
+        self.save_screenshots(ctx);
+        self.open_dropped_rom(ctx);
+
+        if self.fullscreen || !self.settings.show_controls {
+            // The on-screen D-pad isn't shown, so only the keyboard controls it
+            self.dpad.state = DPadState::from_buttons(self.dpad.keyboard_input_state);
+            self.dpad.buttons = self.dpad.keyboard_input_state;
+        }
+
+        if self.fullscreen {
+            self.show_fullscreen_display(ctx);
+        } else {
+            self.show_menu_bar(ctx);
+            self.show_gameboy(ctx, self.breakpoint_reached);
+        }
+
+        self.show_serial_output(ctx);
+        self.show_cartridge_info(ctx);
+        self.show_map_viewer(ctx);
+        self.show_interrupts(ctx);
+
+        self.input_state = self.input_state.with_dpad_buttons(self.dpad.buttons);
+
+        self.breakpoint_reached = false;
+
+        let mut cycles_done = 0;
+
+        while cycles_done < CYCLES_PER_FRAME {
+            if let Some(_) = self.emulator.breakpoint_reached() {
+                self.breakpoint_reached = true;
+                break;
+            } else {
+                let outcome = self.emulator.step(self.input_state).unwrap();
+                cycles_done += outcome.cycles;
+
+                if outcome.new_frame {
+                    self.frame_count += 1;
+                    self.speed_meter.frame(self.emulator.clock().now());
+
+                    if !should_display_frame(self.frame_count, self.settings.frame_skip) {
+                        // The next frame shown is blended with this one, not the last one shown
+                        let next_is_shown =
+                            should_display_frame(self.frame_count + 1, self.settings.frame_skip);
+
+                        if self.lcd_ghosting() && next_is_shown {
+                            let pixels = self.color_corrector.correct(self.emulator.get_pixels());
+                            self.frame_blender.remember(pixels);
+                        }
+
+                        continue;
+                    }
+
+                    let mut pixels = self.color_corrector.correct(self.emulator.get_pixels());
+
+                    if self.lcd_ghosting() {
+                        pixels = self.frame_blender.blend(pixels);
+                    }
+
+                    let mut image = if self.settings.scale2x {
+                        let [width, height] = *DISPLAY_SIZE_PIXELS;
+
+                        egui::ColorImage {
+                            size: [width * 2, height * 2],
+                            pixels: upscale2x(pixels, width, height),
+                        }
+                    } else {
+                        egui::ColorImage {
+                            size: *DISPLAY_SIZE_PIXELS,
+                            pixels: pixels.to_vec(),
+                        }
+                    };
+
+                    if self.settings.scanlines > 0 {
+                        apply_scanlines(
+                            &mut image.pixels,
+                            image.size[0],
+                            self.settings.scanlines as f32 / MAX_SCANLINE_INTENSITY as f32,
+                        );
+                    }
+
+                    self.display_texture
+                        .set(image, egui::TextureOptions::NEAREST);
+                }
+            }
+        }
+
+        self.serial_output
+            .push_str(&self.emulator.take_serial_output());
+
+        ctx.request_repaint();
+    }
+}
+
+impl EmuApp {
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        mut emulator: Emulator,
+        settings: Settings,
+        rom_loaded: bool,
+    ) -> Self {
+        emulator.set_allow_opposing_directions(settings.allow_opposing_directions);
+
+        let display_image = ColorImage::new(*DISPLAY_SIZE_PIXELS, OFF_COLOR);
+
+        cc.egui_ctx.add_font(FontInsert::new(
+            "Corporate",
+            egui::FontData::from_static(include_bytes!("../fonts/av05-logotype.ttf")),
+            vec![InsertFontFamily {
+                family: egui::FontFamily::Name("Corporate".into()),
+                priority: egui::epaint::text::FontPriority::Lowest,
+            }],
+        ));
+
+        cc.egui_ctx.style_mut(|style| {
+            style.interaction.selectable_labels = false;
+        });
+
+        let mut app = Self {
+            default_accuracy: emulator.accuracy(),
+            emulator,
+            rom_loaded,
+            display_texture: cc.egui_ctx.load_texture(
+                "display",
+                display_image,
+                egui::TextureOptions::NEAREST,
+            ),
+            breakpoint_reached: false,
+            input_state: InputState::empty(),
+            dpad: DPad::new(),
+            color_corrector: ColorCorrector::new(settings.color_correction),
+            settings,
+            game_override: GameOverride::default(),
+            frame_blender: FrameBlender::new(),
+            frame_count: 0,
+            speed_meter: SpeedMeter::new(),
+            fullscreen: false,
+            serial_output: String::new(),
+            show_serial_output: false,
+            show_cartridge_info: false,
+            show_map_viewer: false,
+            show_interrupts: false,
+            map_viewer_window: false,
+            map_texture: None,
+            gameboy_rect: None,
+        };
+
+        app.apply_game_override();
+        app
+    }
+
+    /// Looks up the override for the loaded cartridge, and applies the settings it changes
+    fn apply_game_override(&mut self) {
+        let checksum = self.emulator.cartridge_header().read_global_checksum();
+        self.game_override = self.settings.game_override(checksum);
+
+        let game_settings = self.game_settings();
+        self.color_corrector
+            .set_mode(game_settings.color_correction);
+        self.emulator.set_accuracy(game_settings.accuracy);
+    }
+
+    /// The settings with the loaded cartridge's override applied
+    fn game_settings(&self) -> GameSettings {
+        self.game_override
+            .apply(&self.settings, self.default_accuracy)
+    }
+
+    fn color_correction(&self) -> ColorCorrection {
+        self.game_settings().color_correction
+    }
+
+    fn lcd_ghosting(&self) -> bool {
+        self.game_settings().lcd_ghosting
+    }
+
+    fn pixels_per_mm(&self) -> f32 {
+        pixels_per_mm(self.settings.scale)
+    }
+
+    /// Scales a size in pixels from the layout's base scale to the current one
+    fn scaled(&self, pixels: f32) -> f32 {
+        pixels * self.pixels_per_mm() / BASE_PIXELS_PER_MM
+    }
+
+    fn set_scale(&mut self, ctx: &egui::Context, scale: u32) {
+        if scale == self.settings.scale {
+            return;
+        }
+
+        self.settings.scale = scale;
+        ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(window_size(scale).into()));
+
+        self.save_settings();
+    }
+
+    fn save_settings(&self) {
+        if let Err(e) = self.settings.save() {
+            eprintln!("Failed to save settings: {}", e);
+        }
+    }
+
+    fn show_menu_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("menu_bar")
+            .exact_height(MENU_BAR_HEIGHT)
+            .show(ctx, |ui| {
+                egui::menu::bar(ui, |ui| {
+                    ui.menu_button("File", |ui| {
+                        ui.menu_button("Recent ROMs", |ui| self.show_recents_menu(ui));
+                        ui.menu_button("Boot ROM", |ui| self.show_boot_rom_menu(ui));
+
+                        ui.separator();
+
+                        if ui.button("Save Screenshot").clicked() {
+                            ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot(
+                                egui::UserData::default(),
+                            ));
+                            ui.close_menu();
+                        }
+                    });
+
+                    ui.menu_button("View", |ui| {
+                        ui.menu_button("Scale", |ui| {
+                            for scale in MIN_SCALE..=MAX_SCALE {
+                                let selected = scale == self.settings.scale;
+
+                                if ui.radio(selected, format!("{}x", scale)).clicked() {
+                                    self.set_scale(ctx, scale);
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+
+                        if ui
+                            .checkbox(&mut self.settings.lcd_ghosting, "LCD Ghosting")
+                            .changed()
+                        {
+                            self.save_settings();
+                        }
+
+                        if ui
+                            .checkbox(&mut self.settings.scale2x, "Smooth Upscaling (Scale2x)")
+                            .changed()
+                        {
+                            self.save_settings();
+                        }
+
+                        if ui
+                            .add(
+                                egui::Slider::new(
+                                    &mut self.settings.scanlines,
+                                    0..=MAX_SCANLINE_INTENSITY,
+                                )
+                                .text("Scanlines (%)"),
+                            )
+                            .changed()
+                        {
+                            self.save_settings();
+                        }
+
+                        if ui
+                            .add(
+                                egui::Slider::new(
+                                    &mut self.settings.frame_skip,
+                                    0..=MAX_FRAME_SKIP,
+                                )
+                                .text("Frame Skip"),
+                            )
+                            .changed()
+                        {
+                            self.save_settings();
+                        }
+
+                        if ui
+                            .checkbox(&mut self.settings.show_controls, "Show Controls (F10)")
+                            .changed()
+                        {
+                            self.save_settings();
+                        }
+
+                        if ui
+                            .checkbox(&mut self.settings.show_speed, "Show FPS and Speed")
+                            .changed()
+                        {
+                            self.save_settings();
+                        }
+
+                        ui.menu_button("Color Correction", |ui| {
+                            for mode in ColorCorrection::ALL {
+                                let selected = mode == self.settings.color_correction;
+
+                                if ui.radio(selected, mode.label()).clicked() {
+                                    self.settings.color_correction = mode;
+                                    self.color_corrector.set_mode(self.color_correction());
+                                    self.save_settings();
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+
+                    ui.menu_button("Input", |ui| {
+                        let mut changed = false;
+
+                        changed |= ui.checkbox(&mut self.settings.turbo_a, "Turbo A").changed();
+                        changed |= ui.checkbox(&mut self.settings.turbo_b, "Turbo B").changed();
+                        changed |= ui
+                            .add(
+                                egui::Slider::new(
+                                    &mut self.settings.turbo_rate,
+                                    1..=MAX_TURBO_RATE,
+                                )
+                                .text("Turbo Rate (Hz)"),
+                            )
+                            .changed();
+
+                        if ui
+                            .checkbox(
+                                &mut self.settings.allow_opposing_directions,
+                                "Allow Opposing Directions",
+                            )
+                            .changed()
+                        {
+                            self.emulator.set_allow_opposing_directions(
+                                self.settings.allow_opposing_directions,
+                            );
+                            changed = true;
+                        }
+
+                        if changed {
+                            self.save_settings();
+                        }
+                    });
+
+                    ui.menu_button("Debug", |ui| {
+                        ui.checkbox(&mut self.show_serial_output, "Serial Output");
+                        ui.checkbox(&mut self.show_cartridge_info, "Cartridge Info");
+                        ui.checkbox(&mut self.show_map_viewer, "Tile Map Viewer");
+                        ui.checkbox(&mut self.show_interrupts, "Interrupts");
+
+                        ui.separator();
+
+                        if ui.button("Dump Work RAM").clicked() {
+                            self.dump_wram();
+                            ui.close_menu();
+                        }
+                    });
+                });
+            });
+    }
+
+    /// Lists the recent ROMs to open, which can each be removed by right clicking them
+    fn show_recents_menu(&mut self, ui: &mut Ui) {
+        let mut opened = None;
+        let mut removed = None;
+
+        if self.settings.recents.paths().is_empty() {
+            ui.label("No recent ROMs");
+        }
+
+        for path in self.settings.recents.paths() {
+            let button = ui
+                .button(file_label(path))
+                .on_hover_text(path.display().to_string());
+
+            if button.clicked() {
+                opened = Some(path.clone());
+                ui.close_menu();
+            }
+
+            button.context_menu(|ui| {
+                if ui.button("Remove from Recents").clicked() {
+                    removed = Some(path.clone());
+                    ui.close_menu();
+                }
+            });
+        }
+
+        if let Some(path) = opened {
+            self.open_rom(&path);
+        }
+
+        ui.separator();
+
+        let mut changed = false;
+
+        if let Some(path) = removed {
+            self.settings.recents.remove(&path);
+            changed = true;
+        }
+
+        if ui.button("Clear Recent").clicked() {
+            self.settings.recents.clear();
+            changed = true;
+            ui.close_menu();
+        }
+
+        if changed {
+            self.save_settings();
+        }
+    }
+
+    /// Lists the bundled boot ROMs and the recently chosen boot ROM files, which can each be
+    /// removed by right clicking them
+    fn show_boot_rom_menu(&mut self, ui: &mut Ui) {
+        let mut chosen = None;
+        let mut removed = None;
+
+        let files = self.settings.recent_boot_roms.paths().iter().cloned();
+
+        for (i, choice) in BootRomChoice::BUNDLED
+            .into_iter()
+            .chain(files.map(BootRomChoice::File))
+            .enumerate()
+        {
+            if i == BootRomChoice::BUNDLED.len() {
+                ui.separator();
+            }
+
+            let selected = choice == self.settings.boot_rom;
+            let mut radio = ui.radio(selected, choice.label());
+
+            if let BootRomChoice::File(path) = &choice {
+                radio = radio.on_hover_text(path.display().to_string());
+
+                radio.context_menu(|ui| {
+                    if ui.button("Remove from Recents").clicked() {
+                        removed = Some(path.clone());
+                        ui.close_menu();
+                    }
+                });
+            }
+
+            if radio.clicked() {
+                chosen = Some(choice);
+                ui.close_menu();
+            }
+        }
+
+        ui.separator();
+        ui.label("Applies when a ROM is next opened");
+
+        if let Some(path) = removed {
+            self.settings.recent_boot_roms.remove(&path);
+            self.save_settings();
+        }
+
+        if let Some(choice) = chosen {
+            self.set_boot_rom(choice);
+        }
+    }
+
+    /// Uses the boot ROM from the next time a ROM is opened, and remembers it for future runs
+    fn set_boot_rom(&mut self, choice: BootRomChoice) {
+        match choice.read() {
+            Ok(boot_rom) => {
+                if let Some(boot_rom) = boot_rom {
+                    self.emulator.set_boot_rom(boot_rom);
+                }
+                self.emulator.set_skip_boot(boot_rom.is_none());
+
+                if let BootRomChoice::File(path) = &choice {
+                    self.settings.recent_boot_roms.add_or_promote(path);
+                }
+                self.settings.boot_rom = choice;
+                self.save_settings();
+            }
+            Err(e) => eprintln!("Failed to read boot ROM {}: {}", choice.name(), e),
+        }
+    }
+
+    /// Swaps the running cartridge for the one at `path`, keeping the emulator's configuration
+    fn open_rom(&mut self, path: &Path) {
+        match read_cartridge(path) {
+            Ok(cartridge) => {
+                // The logo is only forged for the empty cartridge shown when starting without a ROM
+                self.emulator.set_bypass_logo_check(false);
+                self.emulator.load_cartridge(cartridge);
+                self.rom_loaded = true;
+                self.apply_game_override();
+                self.breakpoint_reached = false;
+                self.settings.recents.add_or_promote(path);
+                self.save_settings();
+            }
+            Err(e) => eprintln!("Failed to open {}: {}", path.display(), e),
+        }
+    }
+
+    /// Opens a ROM dragged onto the window, like picking it from the recent ROMs
+    fn open_dropped_rom(&mut self, ctx: &egui::Context) {
+        let dropped: Vec<PathBuf> = ctx.input(|input| {
+            input
+                .raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.clone())
+                .collect()
+        });
+
+        if dropped.is_empty() {
+            return;
+        }
+
+        match first_rom(dropped.iter().map(PathBuf::as_path)) {
+            Some(path) => self.open_rom(path),
+            None => eprintln!("Ignoring dropped files, none of them are .gb or .gbc ROMs"),
+        }
+    }
+
+    /// Writes work RAM to a file in the working directory, named after the cartridge
+    fn dump_wram(&self) {
+        let title = file_stem(self.emulator.cartridge_header().title());
+        let path = format!("{}-wram.bin", title);
+
+        match std::fs::write(&path, self.emulator.dump_wram()) {
+            Ok(()) => println!("Wrote work RAM to {}", path),
+            Err(e) => eprintln!("Failed to write work RAM to {}: {}", path, e),
+        }
+    }
+
+    /// Saves any screenshots of the window that have arrived, cropped to the Game Boy
+    fn save_screenshots(&self, ctx: &egui::Context) {
+        let screenshots: Vec<_> = ctx.input(|input| {
+            input
+                .raw
+                .events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                    _ => None,
+                })
+                .collect()
+        });
+
+        for screenshot in screenshots {
+            let photo = match self.gameboy_rect {
+                Some(rect) => screenshot.region(&rect, Some(ctx.pixels_per_point())),
+                None => (*screenshot).clone(),
+            };
+
+            let title = self.emulator.cartridge_header().title();
+            let path = screenshot_path(Path::new("."), title, SystemTime::now());
+
+            match image::save_buffer_with_format(
+                &path,
+                photo.as_raw(),
+                photo.width() as u32,
+                photo.height() as u32,
+                image::ExtendedColorType::Rgba8,
+                image::ImageFormat::Png,
+            ) {
+                Ok(()) => println!("Saved screenshot to {}", path.display()),
+                Err(e) => eprintln!("Failed to save screenshot to {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    fn show_cartridge_info(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Cartridge Info")
+            .open(&mut self.show_cartridge_info)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let header = self.emulator.cartridge_header();
+
+                egui::Grid::new("cartridge_info")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        let row = |ui: &mut Ui, name: &str, value: String| {
+                            ui.label(name);
+                            ui.label(value);
+                            ui.end_row();
+                        };
+
+                        row(ui, "Title", header.title().to_string());
+                        row(
+                            ui,
+                            "Manufacturer Code",
+                            header.manufacturer_code().code().clone(),
+                        );
+                        row(ui, "Licensee", header.licensee().to_string());
+                        row(
+                            ui,
+                            "Cartridge Type",
+                            format!("{:?}", header.cartridge_type()),
+                        );
+                        row(ui, "ROM Size", format!("{:?}", header.rom_size()));
+                        row(ui, "RAM Size", format!("{:?}", header.ram_size()));
+                        row(ui, "CGB Flag", format!("{:?}", header.cgb_flag()));
+                        row(ui, "CGB Mode", self.emulator.cgb_mode().to_string());
+                        row(ui, "SGB Flag", format!("{:?}", header.sgb_flag()));
+                        row(
+                            ui,
+                            "Destination",
+                            format!("{:?}", header.destination_code()),
+                        );
+                        row(ui, "Version", header.version_number().to_string());
+
+                        ui.label("Logo");
+                        ui.colored_label(
+                            checksum_color(header.logo_valid()),
+                            if header.logo_valid() {
+                                "Valid"
+                            } else {
+                                "Invalid"
+                            },
+                        );
+                        ui.end_row();
+
+                        ui.label("Header Checksum");
+                        ui.colored_label(
+                            checksum_color(header.header_checksum_valid()),
+                            format!(
+                                "{:#04X} (computed {:#04X})",
+                                header.read_header_checksum(),
+                                header.computed_header_checksum()
+                            ),
+                        );
+                        ui.end_row();
+
+                        ui.label("Global Checksum");
+                        ui.colored_label(
+                            checksum_color(header.global_checksum_valid()),
+                            format!(
+                                "{:#06X} (computed {:#06X})",
+                                header.read_global_checksum(),
+                                header.computed_global_checksum()
+                            ),
+                        );
+                        ui.end_row();
+                    });
+            });
+    }
+
+    /// Shows everything sent over the serial port, which is how many test ROMs report results
+    fn show_serial_output(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Serial Output")
+            .open(&mut self.show_serial_output)
+            .default_size([300.0, 200.0])
+            .show(ctx, |ui| {
+                if ui.button("Clear").clicked() {
+                    self.serial_output.clear();
+                }
+
+                egui::ScrollArea::vertical()
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        ui.monospace(&self.serial_output);
+                    });
+            });
+    }
+
+    /// Shows the whole background or window tile map, outlining the part that is on the display
+    fn show_map_viewer(&mut self, ctx: &egui::Context) {
+        const MAP_SCALE: f32 = 2.0;
+
+        let mut open = self.show_map_viewer;
+
+        egui::Window::new("Tile Map Viewer")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.map_viewer_window, false, "Background");
+                    ui.radio_value(&mut self.map_viewer_window, true, "Window");
+                });
+
+                let lcd = self.emulator.lcd();
+
+                let (map_area, rects) = if self.map_viewer_window {
+                    (
+                        lcd.control().window_tile_map_area(),
+                        window_rect(lcd.read_window_x(), lcd.read_window_y())
+                            .into_iter()
+                            .collect(),
+                    )
+                } else {
+                    (
+                        lcd.control().bg_tile_map_area(),
+                        viewport_rects(lcd.read_scroll_x(), lcd.read_scroll_y()),
+                    )
+                };
+
+                let (map, map_address) = match map_area {
+                    TileMapArea::Lower => (0, 0x9800),
+                    TileMapArea::Upper => (1, 0x9C00),
+                };
+
+                ui.label(format!(
+                    "Map at {:04X}   SCX {} SCY {}   WX {} WY {}",
+                    map_address,
+                    lcd.read_scroll_x(),
+                    lcd.read_scroll_y(),
+                    lcd.read_window_x(),
+                    lcd.read_window_y(),
+                ));
+
+                let image = self.emulator.render_background_map(map);
+                let texture = match &mut self.map_texture {
+                    Some(texture) => {
+                        texture.set(image, egui::TextureOptions::NEAREST);
+                        texture
+                    }
+                    None => self.map_texture.insert(ctx.load_texture(
+                        "tile_map",
+                        image,
+                        egui::TextureOptions::NEAREST,
+                    )),
+                };
+
+                let size = Vec2::splat(BACKGROUND_MAP_SIZE_PIXELS as f32 * MAP_SCALE);
+                let response = ui.image(SizedTexture::new(texture.id(), size));
+
+                let outlines = rects
+                    .iter()
+                    .map(|rect| {
+                        let min =
+                            response.rect.min + Vec2::new(rect.x as f32, rect.y as f32) * MAP_SCALE;
+                        let size = Vec2::new(rect.width as f32, rect.height as f32) * MAP_SCALE;
+
+                        Shape::rect_stroke(
+                            Rect::from_min_size(min, size),
+                            CornerRadius::ZERO,
+                            egui::Stroke::new(2.0, Color32::RED),
+                            egui::StrokeKind::Inside,
+                        )
+                    })
+                    .collect();
+
+                ui.painter_at(response.rect).add(Shape::Vec(outlines));
+            });
+
+        self.show_map_viewer = open;
+    }
+
+    /// Shows the IE and IF registers bit by bit, IME, and the interrupt that will be serviced next
+    fn show_interrupts(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Interrupts")
+            .open(&mut self.show_interrupts)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let state = self.emulator.interrupt_state();
+                let pending = state.pending();
+
+                egui::Grid::new("interrupts")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Interrupt");
+                        ui.label(format!("IE {:02X}", state.enabled.bits()));
+                        ui.label(format!("IF {:02X}", state.requested.bits()));
+                        ui.label("Pending");
+                        ui.end_row();
+
+                        let bit = |ui: &mut Ui, set: bool| {
+                            ui.colored_label(checksum_color(set), if set { "1" } else { "0" });
+                        };
+
+                        for interrupt in Interrupt::ALL {
+                            ui.label(format!("{:?}", interrupt));
+                            bit(ui, state.enabled.contains(interrupt));
+                            bit(ui, state.requested.contains(interrupt));
+                            bit(ui, pending.contains(interrupt));
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+
+                ui.label(format!(
+                    "IME: {}",
+                    if state.master_enable {
+                        "Enabled"
+                    } else {
+                        "Disabled"
+                    }
+                ));
+
+                ui.label(match (state.next_interrupt(), pending.highest_priority()) {
+                    (Some(interrupt), _) => format!("Next: {:?}", interrupt),
+                    (None, Some(interrupt)) => {
+                        format!("Next: none ({:?} waiting on IME)", interrupt)
+                    }
+                    (None, None) => "Next: none".to_string(),
+                });
+            });
+    }
+
+    fn show_gameboy(&mut self, ctx: &egui::Context, breakpoint_reached: bool) {
+        let outer_margin = self.scaled(OUTER_MARGIN);
+
+        let gameboy_outline = egui::containers::Frame {
+            outer_margin: egui::Margin::same(outer_margin as i8),
+            inner_margin: egui::Margin::ZERO,
+            corner_radius: CornerRadius::same(outer_margin as u8),
+            shadow: eframe::epaint::Shadow::NONE,
+            fill: GAMEBOY_COLOR,
+            stroke: egui::Stroke::new(2.0, Color32::GRAY),
+        };
+
+        egui::CentralPanel::default()
+            .frame(gameboy_outline)
+            .show(ctx, |ui| {
+                // Everything is laid out relative to the top left of the Game Boy's outline
+                let origin = ui.max_rect().min - Vec2::splat(outer_margin);
+                self.gameboy_rect = Some(ui.max_rect());
+
+                ui.vertical_centered(|ui| {
+                    if breakpoint_reached {
+                        ui.label("Breakpoint reached.");
+                    }
+
+                    if let CpuStatus::Locked { address, .. } = self.emulator.cpu_status() {
+                        ui.label(format!("CPU locked (invalid opcode at {:#06X})", address));
+                    }
+
+                    ui.add_space(self.scaled(20.0));
+
+                    self.show_display(ui);
+
+                    if self.settings.show_controls {
+                        self.show_buttons(ui, origin);
+                    }
+                });
+            });
+    }
+
+    fn show_display(&mut self, ui: &mut Ui) {
+        if let Some(bezel) = self.bezel_texture(ui.ctx()) {
+            let layout = bezel_layout(bezel.size.into(), self.settings.scale);
+            let (rect, _) = ui.allocate_exact_size(layout.size.into(), Sense::hover());
+
+            self.show_display_in_bezel(ui, rect.min, bezel, self.settings.scale);
+            return;
+        }
+
+        let display_image = egui::Image::new(SizedTexture::new(
+            &self.display_texture,
+            display_size(self.settings.scale),
+        ));
+
+        let margin = Margin::same(self.scaled(20.0) as i8);
+
+        egui::Frame::default()
+            .outer_margin(margin)
+            .inner_margin(margin)
+            .shadow(DROP_SHADOW)
+            .fill(DISPLAY_FRAME_COLOR)
+            .corner_radius(CornerRadius {
+                nw: self.scaled(15.0) as u8,
+                ne: self.scaled(15.0) as u8,
+                sw: self.scaled(15.0) as u8,
+                se: self.scaled(50.0) as u8,
+            })
+            .show(ui, |ui| {
+                let response = ui.add(display_image);
+                self.show_no_rom_message(ui, response.rect);
+                self.show_speed(ui, response.rect);
+            });
+    }
+
+    /// Shows just the display, at the largest integer scale that fits, letterboxed by the body color
+    fn show_fullscreen_display(&mut self, ctx: &egui::Context) {
+        self.gameboy_rect = None;
+        let background = egui::containers::Frame::NONE.fill(GAMEBOY_COLOR);
+        let bezel = self.bezel_texture(ctx);
+
+        egui::CentralPanel::default()
+            .frame(background)
+            .show(ctx, |ui| {
+                let available = ui.max_rect();
+                let scale = fit_scale(available.size().into());
+
+                if let Some(bezel) = bezel {
+                    let layout = bezel_layout(bezel.size.into(), scale);
+                    let rect = Rect::from_center_size(available.center(), layout.size.into());
+
+                    self.show_display_in_bezel(ui, rect.min, bezel, scale);
+                    return;
+                }
+
+                let display_image = egui::Image::new(SizedTexture::new(
+                    &self.display_texture,
+                    display_size(scale),
+                ));
+
+                let response = ui.put(
+                    Rect::from_center_size(available.center(), display_size(scale).into()),
+                    display_image,
+                );
+                self.show_no_rom_message(ui, response.rect);
+                self.show_speed(ui, response.rect);
+            });
+    }
+
+    /// The user's bezel image, once it has finished loading
+    fn bezel_texture(&self, ctx: &egui::Context) -> Option<SizedTexture> {
+        let path = self.settings.bezel_path.as_ref()?;
+        let uri = format!("file://{}", path.display());
+
+        match ctx.try_load_texture(
+            &uri,
+            egui::TextureOptions::NEAREST,
+            egui::SizeHint::default(),
+        ) {
+            Ok(egui::load::TexturePoll::Ready { texture }) => Some(texture),
+            _ => None,
+        }
+    }
+
+    /// Draws the bezel with its top left corner at the given position, and the display over it
+    fn show_display_in_bezel(&self, ui: &mut Ui, origin: Pos2, bezel: SizedTexture, scale: u32) {
+        let layout = bezel_layout(bezel.size.into(), scale);
+        let bezel_rect = Rect::from_min_size(origin, layout.size.into());
+        let display_rect = Rect::from_min_size(
+            origin + Vec2::from(layout.display_offset),
+            display_size(scale).into(),
+        );
+
+        ui.put(
+            bezel_rect,
+            egui::Image::new(SizedTexture::new(bezel.id, layout.size)),
+        );
+        ui.put(
+            display_rect,
+            egui::Image::new(SizedTexture::new(
+                &self.display_texture,
+                display_size(scale),
+            )),
+        );
+        self.show_no_rom_message(ui, display_rect);
+        self.show_speed(ui, display_rect);
+    }
+
+    /// Tells the user how to open a ROM, over the bottom of the display, while none is loaded
+    fn show_no_rom_message(&self, ui: &Ui, display_rect: Rect) {
+        if self.rom_loaded {
+            return;
+        }
+
+        // Below where the boot ROM draws the logo
+        let pos = display_rect.center_top() + Vec2::new(0.0, display_rect.height() * 0.8);
+
+        ui.painter().text(
+            pos,
+            egui::Align2::CENTER_CENTER,
+            "No ROM loaded\nDrop a ROM here or open one\nfrom File > Recent ROMs",
+            FontId::proportional(display_rect.width() / 20.0),
+            DARKEST_COLOR,
+        );
+    }
+
+    /// Shows the frame rate and emulation speed in the top left corner of the display, if enabled
+    fn show_speed(&self, ui: &Ui, display_rect: Rect) {
+        if !self.settings.show_speed {
+            return;
+        }
+
+        let text = format!(
+            "{:.1} FPS\n{:.0}%",
+            self.speed_meter.fps(),
+            self.speed_meter.speed_percent()
+        );
+        let font = FontId::monospace(display_rect.width() / 24.0);
+        let padding = Vec2::splat(font.size / 4.0);
+
+        let painter = ui.painter();
+        let galley = painter.layout_no_wrap(text, font, Color32::WHITE);
+        let background = Rect::from_min_size(display_rect.min, galley.size() + padding * 2.0);
+
+        painter.rect_filled(background, 0.0, Color32::from_black_alpha(160));
+        painter.galley(background.min + padding, galley, Color32::WHITE);
+    }
+
+    /// The position of a point given as fractions of the Game Boy's width and height
+    fn gameboy_pos(&self, origin: Pos2, x: f32, y: f32) -> Pos2 {
+        let width = GAMEBOY_WIDTH * self.pixels_per_mm();
+        let height = GAMEBOY_HEIGHT * self.pixels_per_mm();
+
+        origin + Vec2::new(width * x, height * y)
+    }
+
+    fn show_buttons(&mut self, ui: &mut Ui, origin: Pos2) {
+        let a_pos = self.gameboy_pos(origin, 0.88, 0.65);
+        let b_pos = self.gameboy_pos(origin, 0.72, 0.70);
+
+        let a_clicked = self.show_ab_button(ui, "A", a_pos, self.input_state.a_pressed);
+        let b_clicked = self.show_ab_button(ui, "B", b_pos, self.input_state.b_pressed);
+
+        if !self.input_state.a_pressed {
+            self.input_state.a_pressed = a_clicked;
+        }
+
+        if !self.input_state.b_pressed {
+            self.input_state.b_pressed = b_clicked;
+        }
+
+        let start_pos = self.gameboy_pos(origin, 0.62, 0.83);
+        let select_pos = self.gameboy_pos(origin, 0.42, 0.83);
+
+        let start_clicked =
+            self.show_start_button(ui, "START", start_pos, self.input_state.start_pressed);
+        let select_clicked =
+            self.show_start_button(ui, "SELECT", select_pos, self.input_state.select_pressed);
+
+        if !self.input_state.start_pressed {
+            self.input_state.start_pressed = start_clicked;
+        }
+
+        if !self.input_state.select_pressed {
+            self.input_state.select_pressed = select_clicked;
+        }
+
+        let dpad_pos = self.gameboy_pos(origin, 0.19, 0.67);
+
+        self.show_dpad(ui, dpad_pos);
+    }
+
+    fn label_font(&self) -> FontId {
+        FontId::new(
+            self.scaled(24.0),
+            egui::FontFamily::Name("Corporate".into()),
+        )
+    }
+
+    fn show_ab_button(
+        &mut self,
+        ui: &mut Ui,
+        text: &str,
+        pos: Pos2,
+        activation_override: bool,
+    ) -> bool {
+        let font_id = self.label_font();
+        let button_diameter = BUTTON_DIAMETER * self.pixels_per_mm();
+        let button_size = Vec2::new(button_diameter, button_diameter);
+
+        let clicked = ui
+            .put(
+                Rect::from_center_size(pos, button_size),
+                ABButton::new(activation_override),
+            )
+            .dragged();
+
+        let text_pos = Pos2::new(pos.x, pos.y + self.scaled(50.0));
+
+        let b_text_center = Rect::from_center_size(text_pos, button_size);
+
+        let mut b_button = LayoutJob::default();
+        b_button.append(text, 0.0, TextFormat::simple(font_id, FONT_COLOR));
+
+        ui.put(b_text_center, Label::new(b_button));
+
+        clicked
+    }
+
+    fn show_start_button(
+        &mut self,
+        ui: &mut Ui,
+        text: &str,
+        pos: Pos2,
+        activation_override: bool,
+    ) -> bool {
+        let button_diameter = BUTTON_DIAMETER * self.pixels_per_mm();
+        let button_size = Vec2::new(button_diameter, button_diameter * 0.3);
+
+        let clicked = ui
+            .put(
+                Rect::from_center_size(pos, button_size),
+                StartButton::new(activation_override),
+            )
+            .dragged();
+
+        let text_pos = Pos2::new(pos.x, pos.y + self.scaled(30.0));
+        let text_center = Rect::from_center_size(text_pos, button_size * 2.0);
+
+        let mut button = LayoutJob::default();
+        let font_id = self.label_font();
+        button.append(text, 0.0, TextFormat::simple(font_id, FONT_COLOR));
+
+        ui.put(text_center, Label::new(button));
+
+        clicked
+    }
+
+    fn show_dpad(&mut self, ui: &mut Ui, pos: Pos2) {
+        let dpad_width = DPAD_WIDTH * self.pixels_per_mm();
+        let dpad_size = Vec2::new(dpad_width, dpad_width);
+
+        ui.put(Rect::from_center_size(pos, dpad_size), &mut self.dpad);
+    }
+}
+
+fn checksum_color(valid: bool) -> Color32 {
+    if valid {
+        Color32::GREEN
+    } else {
+        Color32::RED
+    }
+}
+
+struct ABButton {
+    activated: bool,
+}
+
+impl ABButton {
+    fn new(activated: bool) -> Self {
+        Self { activated }
+    }
+}
+
+impl Widget for ABButton {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let outer_rect_bounds = ui.available_rect_before_wrap();
+        let response = ui.allocate_rect(outer_rect_bounds, Sense::drag());
+        let interacted = response.dragged();
+
+        let fill_color = if interacted | self.activated {
+            AB_BUTTON_CLICKED_COLOR
+        } else {
+            AB_BUTTON_COLOR
+        };
+
+        let button = Shape::circle_filled(
+            outer_rect_bounds.center(),
+            outer_rect_bounds.width() / 2.0,
+            fill_color,
+        );
+
+        let shape = if interacted | self.activated {
+            button
+        } else {
+            let shadow = DROP_SHADOW.as_shape(outer_rect_bounds, CornerRadius::same(255));
+            Shape::Vec(vec![Shape::from(shadow), button])
+        };
+
+        if ui.is_rect_visible(outer_rect_bounds) {
+            ui.painter().add(shape);
+        }
+
+        response
+    }
+}
+
+struct StartButton {
+    activated: bool,
+}
+
+impl StartButton {
+    fn new(activated: bool) -> Self {
+        Self { activated }
+    }
+}
+
+impl Widget for StartButton {
+    fn ui(self, ui: &mut Ui) -> egui::Response {
+        let outer_rect_bounds = ui.available_rect_before_wrap();
+        let response = ui.allocate_rect(outer_rect_bounds, Sense::drag());
+        let interacted = response.dragged();
+
+        let fill_color = if interacted | self.activated {
+            START_BUTTON_CLICKED_COLOR
+        } else {
+            START_BUTTON_COLOR
+        };
+
+        let button = Shape::from(RectShape::filled(
+            outer_rect_bounds,
+            CornerRadius::same(4),
+            fill_color,
+        ));
+
+        let shape = if interacted | self.activated {
+            button
+        } else {
+            let shadow = DROP_SHADOW.as_shape(outer_rect_bounds, CornerRadius::same(4));
+            Shape::Vec(vec![Shape::from(shadow), button])
+        };
+
+        if ui.is_rect_visible(outer_rect_bounds) {
+            ui.painter().add(shape);
+        }
+
+        response
+    }
+}
+
+struct DPad {
+    keyboard_input_state: DPadButtonState,
+    /// Every direction held, from both the keyboard and the on-screen D-pad
+    buttons: DPadButtonState,
+    state: DPadState,
+}
+
+impl DPad {
+    fn new() -> Self {
+        Self {
+            keyboard_input_state: DPadButtonState::empty(),
+            buttons: DPadButtonState::empty(),
+            state: DPadState::None,
+        }
+    }
+}
+
+impl Widget for &mut DPad {
+    fn ui(self, ui: &mut Ui) -> egui::Response {
+        let outer_rect_bounds = ui.available_rect_before_wrap();
+        let overall_response = ui.allocate_rect(outer_rect_bounds, Sense::drag());
+
+        let (left_rect, rest) = outer_rect_bounds
+            .scale_from_center2(Vec2::new(1.0, 0.3))
+            .split_left_right_at_fraction(0.4);
+        let (center_rect, right_rect) = rest.split_left_right_at_fraction(1.0 / 3.0);
+        let (top_rect, rest) = outer_rect_bounds
+            .scale_from_center2(Vec2::new(0.3, 1.0))
+            .split_top_bottom_at_fraction(0.4);
+        let (_, bottom_rect) = rest.split_top_bottom_at_fraction(1.0 / 3.0);
+
+        let left_response = ui.allocate_rect(left_rect, Sense::drag());
+        let right_response = ui.allocate_rect(right_rect, Sense::drag());
+        let top_response = ui.allocate_rect(top_rect, Sense::drag());
+        let bottom_response = ui.allocate_rect(bottom_rect, Sense::drag());
+
+        let (top, rest) = outer_rect_bounds.split_top_bottom_at_fraction(0.35);
+        let (_, bottom) = rest.split_top_bottom_at_fraction(0.5);
+        let (top_left, rest) = top.split_left_right_at_fraction(0.35);
+        let (_, top_right) = rest.split_left_right_at_fraction(0.5);
+        let (bottom_left, rest) = bottom.split_left_right_at_fraction(0.35);
+        let (_, bottom_right) = rest.split_left_right_at_fraction(0.5);
+
+        let top_left_corner = ui.allocate_rect(top_left, Sense::drag());
+        let top_right_corner = ui.allocate_rect(top_right, Sense::drag());
+        let bottom_left_corner = ui.allocate_rect(bottom_left, Sense::drag());
+        let bottom_right_corner = ui.allocate_rect(bottom_right, Sense::drag());
+
+        let left_activated =
+            top_left_corner.dragged() | bottom_left_corner.dragged() | left_response.dragged();
+        let right_activated =
+            top_right_corner.dragged() | bottom_right_corner.dragged() | right_response.dragged();
+        let top_activated =
+            top_left_corner.dragged() | top_right_corner.dragged() | top_response.dragged();
+        let bottom_activated = bottom_left_corner.dragged()
+            | bottom_right_corner.dragged()
+            | bottom_response.dragged();
+
+        let ui_state = DPadButtonState::new(
+            top_activated,
+            bottom_activated,
+            left_activated,
+            right_activated,
+        );
+        let overall_state = self.keyboard_input_state | ui_state;
+
+        let dpad_state = DPadState::from_buttons(overall_state);
+        self.state = dpad_state;
+        self.buttons = overall_state;
+
+        let mut shadows = Vec::new();
+        let mut buttons = Vec::new();
+
+        let center = Shape::from(RectShape::filled(
+            center_rect,
+            CornerRadius::ZERO,
+            DPAD_BUTTON_COLOR,
+        ));
+        buttons.push(center);
+
+        self.paint_button(left_rect, &mut buttons, &mut shadows, dpad_state.is_left());
+        self.paint_button(
+            right_rect,
+            &mut buttons,
+            &mut shadows,
+            dpad_state.is_right(),
+        );
+        self.paint_button(top_rect, &mut buttons, &mut shadows, dpad_state.is_up());
+        self.paint_button(
+            bottom_rect,
+            &mut buttons,
+            &mut shadows,
+            dpad_state.is_down(),
+        );
+
+        if ui.is_rect_visible(outer_rect_bounds) {
+            let shadows = Shape::Vec(shadows);
+            let buttons = Shape::Vec(buttons);
+
+            ui.painter().add(Shape::Vec(vec![shadows, buttons]));
+        }
+
+        overall_response
+    }
+}
+
+impl DPad {
+    fn paint_button(
+        &mut self,
+        rect: Rect,
+        buttons: &mut Vec<Shape>,
+        shadows: &mut Vec<Shape>,
+        activation_override: bool,
+    ) {
+        let corner_radius = CornerRadius::same(2);
+
+        let fill_color = if activation_override {
+            DPAD_BUTTON_CLICKED_COLOR
+        } else {
+            DPAD_BUTTON_COLOR
+        };
+
+        if !activation_override {
+            let shadow = DROP_SHADOW.as_shape(rect, corner_radius);
+            let shape = Shape::from(shadow);
+            shadows.push(shape);
+        }
+
+        let button = Shape::from(RectShape::filled(rect, corner_radius, fill_color));
+
+        buttons.push(button);
+    }
+}
@@ -0,0 +1,150 @@
+use std::path::{Path, PathBuf};
+
+/// The most ROMs which are remembered, the least recently opened are forgotten first
+pub const MAX_RECENTS: usize = 10;
+
+/// ROMs which have been opened before, most recently opened first
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Recents {
+    paths: Vec<PathBuf>,
+}
+
+impl Recents {
+    pub fn new() -> Self {
+        Self { paths: Vec::new() }
+    }
+
+    /// Builds the list from paths in most recent first order, skipping duplicates
+    pub fn from_paths(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        let mut recents = Self::new();
+
+        for path in paths {
+            if recents.paths.len() == MAX_RECENTS {
+                break;
+            }
+
+            if !recents.paths.contains(&path) {
+                recents.paths.push(path);
+            }
+        }
+
+        recents
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Puts the ROM at the front of the list, moving it there if it was already in the list
+    ///
+    /// If the list is full the least recently opened ROM is removed.
+    pub fn add_or_promote(&mut self, path: &Path) {
+        self.remove(path);
+        self.paths.insert(0, path.to_path_buf());
+        self.paths.truncate(MAX_RECENTS);
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.paths.retain(|existing| existing != path);
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.clear();
+    }
+
+    /// Removes any ROMs which no longer exist
+    pub fn prune_missing(&mut self) {
+        self.paths.retain(|path| path.exists());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{Recents, MAX_RECENTS};
+
+    #[test]
+    fn most_recent_first() {
+        let mut recents = Recents::new();
+        recents.add_or_promote(Path::new("a.gb"));
+        recents.add_or_promote(Path::new("b.gb"));
+
+        assert_eq!(recents.paths(), [Path::new("b.gb"), Path::new("a.gb")]);
+    }
+
+    #[test]
+    fn reopened_rom_promoted() {
+        let mut recents = Recents::new();
+        recents.add_or_promote(Path::new("a.gb"));
+        recents.add_or_promote(Path::new("b.gb"));
+        recents.add_or_promote(Path::new("c.gb"));
+        recents.add_or_promote(Path::new("a.gb"));
+
+        assert_eq!(
+            recents.paths(),
+            [Path::new("a.gb"), Path::new("c.gb"), Path::new("b.gb")]
+        );
+    }
+
+    #[test]
+    fn oldest_evicted() {
+        let mut recents = Recents::new();
+
+        for i in 0..=MAX_RECENTS {
+            recents.add_or_promote(Path::new(&format!("{}.gb", i)));
+        }
+
+        assert_eq!(recents.paths().len(), MAX_RECENTS);
+        assert_eq!(recents.paths()[0], Path::new("10.gb"));
+        assert_eq!(recents.paths()[MAX_RECENTS - 1], Path::new("1.gb"));
+        assert!(!recents.paths().contains(&PathBuf::from("0.gb")));
+    }
+
+    #[test]
+    fn from_paths_keeps_order() {
+        let paths = ["b.gb", "a.gb", "b.gb"].map(PathBuf::from);
+        let recents = Recents::from_paths(paths);
+
+        assert_eq!(recents.paths(), [Path::new("b.gb"), Path::new("a.gb")]);
+
+        let recents = Recents::from_paths((0..20).map(|i| PathBuf::from(format!("{}.gb", i))));
+        assert_eq!(recents.paths().len(), MAX_RECENTS);
+        assert_eq!(recents.paths()[0], Path::new("0.gb"));
+    }
+
+    #[test]
+    fn missing_pruned() {
+        let mut recents = Recents::new();
+        recents.add_or_promote(Path::new("Cargo.toml"));
+        recents.add_or_promote(Path::new("does-not-exist.gb"));
+        recents.prune_missing();
+
+        assert_eq!(recents.paths(), [Path::new("Cargo.toml")]);
+    }
+
+    #[test]
+    fn remove_entry() {
+        let mut recents = Recents::new();
+        recents.add_or_promote(Path::new("a.gb"));
+        recents.add_or_promote(Path::new("b.gb"));
+        recents.add_or_promote(Path::new("c.gb"));
+
+        recents.remove(Path::new("b.gb"));
+        assert_eq!(recents.paths(), [Path::new("c.gb"), Path::new("a.gb")]);
+
+        // Removing something that isn't there does nothing
+        recents.remove(Path::new("b.gb"));
+        assert_eq!(recents.paths().len(), 2);
+    }
+
+    #[test]
+    fn clear_all() {
+        let mut recents = Recents::new();
+        recents.add_or_promote(Path::new("a.gb"));
+        recents.add_or_promote(Path::new("b.gb"));
+
+        recents.clear();
+        assert!(recents.paths().is_empty());
+    }
+}
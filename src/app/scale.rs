@@ -0,0 +1,92 @@
+use gameboy_emulator::ppu::{DISPLAY_HEIGHT_PIXELS, DISPLAY_WIDTH_PIXELS};
+
+pub const MIN_SCALE: u32 = 2;
+pub const MAX_SCALE: u32 = 8;
+pub const DEFAULT_SCALE: u32 = 2;
+
+/// How many pixels are used to draw a millimetre of the Game Boy at a display scale of 1x
+const PIXELS_PER_MM_PER_SCALE: f32 = 3.0;
+
+/// Snaps a requested display scale to the nearest supported integer scale
+pub fn snap_scale(scale: f32) -> u32 {
+    if scale.is_nan() {
+        return DEFAULT_SCALE;
+    }
+
+    (scale.round().max(0.0) as u32).clamp(MIN_SCALE, MAX_SCALE)
+}
+
+/// The on-screen size of the emulated display at the given integer scale
+pub fn display_size(scale: u32) -> [f32; 2] {
+    [
+        (DISPLAY_WIDTH_PIXELS as u32 * scale) as f32,
+        (DISPLAY_HEIGHT_PIXELS as u32 * scale) as f32,
+    ]
+}
+
+/// The largest integer scale at which the display fits within the available space, at least 1x
+pub fn fit_scale(available: [f32; 2]) -> u32 {
+    let horizontal = available[0] / DISPLAY_WIDTH_PIXELS as f32;
+    let vertical = available[1] / DISPLAY_HEIGHT_PIXELS as f32;
+
+    (horizontal.min(vertical).floor() as u32).max(1)
+}
+
+/// The size of a millimetre of the Game Boy shell in pixels, so that the shell grows with the display
+pub fn pixels_per_mm(scale: u32) -> f32 {
+    scale as f32 * PIXELS_PER_MM_PER_SCALE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{display_size, fit_scale, pixels_per_mm, snap_scale, MAX_SCALE, MIN_SCALE};
+
+    #[test]
+    fn display_size_for_each_scale() {
+        let expected = [
+            (2, [320.0, 288.0]),
+            (3, [480.0, 432.0]),
+            (4, [640.0, 576.0]),
+            (5, [800.0, 720.0]),
+            (6, [960.0, 864.0]),
+            (7, [1120.0, 1008.0]),
+            (8, [1280.0, 1152.0]),
+        ];
+
+        for (scale, size) in expected {
+            assert_eq!(display_size(scale), size);
+        }
+    }
+
+    #[test]
+    fn pixels_per_mm_for_each_scale() {
+        assert_eq!(pixels_per_mm(2), 6.0);
+        assert_eq!(pixels_per_mm(8), 24.0);
+    }
+
+    #[test]
+    fn snapping() {
+        assert_eq!(snap_scale(4.0), 4);
+        assert_eq!(snap_scale(4.4), 4);
+        assert_eq!(snap_scale(4.6), 5);
+        assert_eq!(snap_scale(0.0), MIN_SCALE);
+        assert_eq!(snap_scale(-3.0), MIN_SCALE);
+        assert_eq!(snap_scale(100.0), MAX_SCALE);
+    }
+
+    #[test]
+    fn fitting_to_viewport() {
+        // Common monitor resolutions
+        assert_eq!(fit_scale([1920.0, 1080.0]), 7);
+        assert_eq!(fit_scale([2560.0, 1440.0]), 10);
+        assert_eq!(fit_scale([1280.0, 720.0]), 5);
+        // Limited by width rather than height
+        assert_eq!(fit_scale([400.0, 1000.0]), 2);
+        // Exactly fits
+        assert_eq!(fit_scale([480.0, 432.0]), 3);
+        assert_eq!(fit_scale([479.0, 432.0]), 2);
+        // Never smaller than 1x
+        assert_eq!(fit_scale([100.0, 100.0]), 1);
+        assert_eq!(fit_scale([0.0, 0.0]), 1);
+    }
+}
@@ -0,0 +1,62 @@
+use eframe::egui::Color32;
+
+/// The highest scanline intensity setting, as a percentage
+pub const MAX_SCANLINE_INTENSITY: u32 = 100;
+
+/// How much of their brightness odd rows lose at full intensity
+pub const MAX_DARKENING: f32 = 0.5;
+
+/// Darkens every other row of an image, imitating the gaps between the scanlines of a CRT
+///
+/// The intensity goes from 0.0, which leaves the image untouched, to 1.0, which takes away
+/// `MAX_DARKENING` of the brightness of odd rows. Even rows are never changed.
+pub fn apply_scanlines(pixels: &mut [Color32], width: usize, intensity: f32) {
+    let factor = 1.0 - MAX_DARKENING * intensity.clamp(0.0, 1.0);
+    let darken = |channel: u8| (channel as f32 * factor).round() as u8;
+
+    for row in pixels.chunks_exact_mut(width).skip(1).step_by(2) {
+        for pixel in row {
+            *pixel = Color32::from_rgba_premultiplied(
+                darken(pixel.r()),
+                darken(pixel.g()),
+                darken(pixel.b()),
+                pixel.a(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui::Color32;
+
+    use super::apply_scanlines;
+
+    const COLOR: Color32 = Color32::from_rgb(200, 100, 50);
+
+    #[test]
+    fn full_intensity_darkens_odd_rows() {
+        let mut pixels = vec![COLOR; 3 * 4];
+
+        apply_scanlines(&mut pixels, 3, 1.0);
+
+        for (y, row) in pixels.chunks_exact(3).enumerate() {
+            let expected = if y % 2 == 1 {
+                Color32::from_rgb(100, 50, 25)
+            } else {
+                COLOR
+            };
+
+            assert_eq!(row, [expected; 3]);
+        }
+    }
+
+    #[test]
+    fn zero_intensity_changes_nothing() {
+        let mut pixels = vec![COLOR; 3 * 4];
+
+        apply_scanlines(&mut pixels, 3, 0.0);
+
+        assert_eq!(pixels, vec![COLOR; 3 * 4]);
+    }
+}
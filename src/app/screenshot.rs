@@ -0,0 +1,83 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Replaces anything but ASCII letters and digits in a cartridge title, so it can name a file
+pub fn file_stem(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// A path in `dir` for a screenshot taken at `time`, named after the cartridge
+///
+/// The name includes the time in milliseconds since the Unix epoch. If a file with that name
+/// already exists, a counter is added to it, so an existing screenshot is never overwritten.
+pub fn screenshot_path(dir: &Path, title: &str, time: SystemTime) -> PathBuf {
+    let millis = time
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_millis())
+        .unwrap_or(0);
+    let stem = format!("{}-{}", file_stem(title), millis);
+
+    let mut path = dir.join(format!("{}.png", stem));
+    let mut counter = 1;
+
+    while path.exists() {
+        path = dir.join(format!("{}-{}.png", stem, counter));
+        counter += 1;
+    }
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::Path,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use super::{file_stem, screenshot_path};
+
+    #[test]
+    fn titles_become_file_stems() {
+        assert_eq!(file_stem("POKEMON RED"), "POKEMON_RED");
+        assert_eq!(file_stem("DR.MARIO"), "DR_MARIO");
+        assert_eq!(file_stem(""), "");
+    }
+
+    #[test]
+    fn screenshot_names_are_unique() {
+        let dir = std::env::temp_dir().join(format!(
+            "gameboy-emulator-{}-screenshot_names_are_unique",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+
+        let first = screenshot_path(&dir, "TETRIS", time);
+        assert_eq!(first, dir.join("TETRIS-1700000000123.png"));
+        std::fs::write(&first, []).unwrap();
+
+        // Taken in the same millisecond
+        let second = screenshot_path(&dir, "TETRIS", time);
+        assert_eq!(second, dir.join("TETRIS-1700000000123-1.png"));
+        std::fs::write(&second, []).unwrap();
+
+        let third = screenshot_path(&dir, "TETRIS", time);
+        assert_eq!(third, dir.join("TETRIS-1700000000123-2.png"));
+
+        let later = screenshot_path(&dir, "TETRIS", time + Duration::from_millis(1));
+        assert_eq!(
+            later.file_name().unwrap(),
+            Path::new("TETRIS-1700000000124.png")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
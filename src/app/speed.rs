@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use gameboy_emulator::FRAMES_PER_SECOND;
+
+/// How long frames are counted for before the readings are updated, so they change slowly
+/// enough to read
+const SAMPLE_PERIOD: Duration = Duration::from_millis(500);
+
+/// The frames emulated per second, when `frames` frames were emulated over `elapsed`
+pub fn frame_rate(frames: u64, elapsed: Duration) -> f64 {
+    if elapsed.is_zero() {
+        return 0.0;
+    }
+
+    frames as f64 / elapsed.as_secs_f64()
+}
+
+/// How fast emulation ran as a percentage of real hardware, which shows about 59.7 frames per
+/// second, when `frames` frames were emulated over `elapsed`
+pub fn speed_percent(frames: u64, elapsed: Duration) -> f64 {
+    frame_rate(frames, elapsed) / FRAMES_PER_SECOND * 100.0
+}
+
+/// Measures the frame rate and emulation speed from the times frames are finished
+#[derive(Debug, Clone, Default)]
+pub struct SpeedMeter {
+    /// When the current sample began, which is when the first frame was finished
+    sample_start: Option<Duration>,
+    /// Frames finished since the sample began
+    frames: u64,
+    fps: f64,
+    speed_percent: f64,
+}
+
+impl SpeedMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a frame finished at `now`, updating the readings once a sample is over
+    pub fn frame(&mut self, now: Duration) {
+        let Some(sample_start) = self.sample_start else {
+            self.sample_start = Some(now);
+            return;
+        };
+
+        self.frames += 1;
+
+        // The wall clock can be set backwards, which just leaves the sample running longer
+        let elapsed = now.saturating_sub(sample_start);
+
+        if elapsed >= SAMPLE_PERIOD {
+            self.fps = frame_rate(self.frames, elapsed);
+            self.speed_percent = speed_percent(self.frames, elapsed);
+            self.sample_start = Some(now);
+            self.frames = 0;
+        }
+    }
+
+    /// The frame rate over the last sample
+    pub fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    /// The emulation speed over the last sample, as a percentage of real hardware
+    pub fn speed_percent(&self) -> f64 {
+        self.speed_percent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use gameboy_emulator::FRAMES_PER_SECOND;
+
+    use super::{speed_percent, SpeedMeter};
+
+    #[test]
+    fn speed_is_relative_to_hardware() {
+        let second = Duration::from_secs(1);
+
+        assert!((speed_percent(597, Duration::from_secs(10)) - 100.0).abs() < 0.1);
+        assert!((speed_percent(1194, Duration::from_secs(10)) - 200.0).abs() < 0.1);
+        assert!((speed_percent(30, second) - 50.0).abs() < 0.5);
+        assert_eq!(speed_percent(0, second), 0.0);
+        assert_eq!(speed_percent(60, Duration::ZERO), 0.0);
+    }
+
+    #[test]
+    fn meter_updates_after_a_sample() {
+        let mut meter = SpeedMeter::new();
+        let frame_time = Duration::from_secs_f64(1.0 / FRAMES_PER_SECOND);
+
+        // Frames finishing 2 frame times apart, so at half speed
+        for frame in 0..15 {
+            meter.frame(frame_time * 2 * frame);
+        }
+        assert_eq!(meter.fps(), 0.0);
+
+        for frame in 15..40 {
+            meter.frame(frame_time * 2 * frame);
+        }
+        assert!((meter.fps() - FRAMES_PER_SECOND / 2.0).abs() < 0.1);
+        assert!((meter.speed_percent() - 50.0).abs() < 0.1);
+    }
+}
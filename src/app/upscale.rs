@@ -0,0 +1,107 @@
+use eframe::egui::Color32;
+
+/// Doubles the size of an image with the Scale2x algorithm, which smooths diagonal edges
+///
+/// Each pixel becomes a 2x2 block. A corner of the block takes the color of the two neighbours
+/// beside it when they match each other but not the neighbours opposite, otherwise it keeps the
+/// pixel's own color. Pixels past the edges of the image are treated as repeating the edge.
+pub fn upscale2x(pixels: &[Color32], width: usize, height: usize) -> Vec<Color32> {
+    assert_eq!(pixels.len(), width * height);
+
+    let scaled_width = width * 2;
+    let mut scaled = vec![Color32::TRANSPARENT; pixels.len() * 4];
+
+    let pixel = |x: usize, y: usize| pixels[y * width + x];
+
+    for y in 0..height {
+        for x in 0..width {
+            let center = pixel(x, y);
+            let above = pixel(x, y.saturating_sub(1));
+            let below = pixel(x, (y + 1).min(height - 1));
+            let left = pixel(x.saturating_sub(1), y);
+            let right = pixel((x + 1).min(width - 1), y);
+
+            let (top_left, top_right, bottom_left, bottom_right) =
+                if above != below && left != right {
+                    (
+                        if left == above { above } else { center },
+                        if above == right { right } else { center },
+                        if left == below { left } else { center },
+                        if right == below { below } else { center },
+                    )
+                } else {
+                    (center, center, center, center)
+                };
+
+            let top = y * 2 * scaled_width + x * 2;
+            let bottom = top + scaled_width;
+
+            scaled[top] = top_left;
+            scaled[top + 1] = top_right;
+            scaled[bottom] = bottom_left;
+            scaled[bottom + 1] = bottom_right;
+        }
+    }
+
+    scaled
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui::Color32;
+
+    use super::upscale2x;
+
+    const W: Color32 = Color32::WHITE;
+    const K: Color32 = Color32::BLACK;
+
+    #[test]
+    fn solid_image_only_grows() {
+        let scaled = upscale2x(&[W; 6], 3, 2);
+
+        assert_eq!(scaled, vec![W; 24]);
+    }
+
+    #[test]
+    fn checkerboard_corners_are_rounded() {
+        #[rustfmt::skip]
+        let checkerboard = [
+            W, K,
+            K, W,
+        ];
+
+        #[rustfmt::skip]
+        let expected = vec![
+            W, W, K, K,
+            W, K, W, K,
+            K, W, K, W,
+            K, K, W, W,
+        ];
+
+        assert_eq!(upscale2x(&checkerboard, 2, 2), expected);
+    }
+
+    #[test]
+    fn edge_rules_on_3x3() {
+        // The center's top left corner takes the black of the pixels above and to its left, and
+        // the black pixels at the end of the diagonal have their bottom right corners whitened
+        #[rustfmt::skip]
+        let image = [
+            K, K, W,
+            K, W, W,
+            W, W, W,
+        ];
+
+        #[rustfmt::skip]
+        let expected = vec![
+            K, K, K, K, W, W,
+            K, K, K, W, W, W,
+            K, K, K, W, W, W,
+            K, W, W, W, W, W,
+            W, W, W, W, W, W,
+            W, W, W, W, W, W,
+        ];
+
+        assert_eq!(upscale2x(&image, 3, 3), expected);
+    }
+}
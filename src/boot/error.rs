@@ -1,6 +1,8 @@
 #[derive(Debug)]
 pub enum Error {
     Io(std::io::Error),
+    /// The boot ROM is neither the DMG's 256 bytes nor the CGB's 2304
+    InvalidSize(usize),
 }
 
 impl From<std::io::Error> for Error {
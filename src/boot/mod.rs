@@ -6,30 +6,138 @@ pub mod error;
 
 pub const DEFAULT_BOOT_ROM: BootRom = BootRom::new(*include_bytes!("dmg_boot.bin"));
 
+/// A freely distributable replacement for `DEFAULT_BOOT_ROM`, assembled from `open_dmg_boot.asm`.
+///
+/// It scrolls the logo read from the cartridge header and verifies the header checksum before
+/// handing control to the cartridge at 0x0100 with the usual post-boot register values.
+pub const OPEN_SOURCE_BOOT_ROM: BootRom = BootRom::new(*include_bytes!("open_dmg_boot.bin"));
+
+pub const DMG_BOOT_ROM_SIZE: usize = 0x100;
+/// The CGB boot ROM covers 0x0000-0x08FF, though 0x0100-0x01FF is never mapped, leaving the
+/// cartridge header visible
+pub const CGB_BOOT_ROM_SIZE: usize = 0x900;
+
 #[derive(Debug, Clone, Copy)]
 pub struct BootRom {
-    contents: [u8; 256],
+    contents: [u8; CGB_BOOT_ROM_SIZE],
+    length: usize,
 }
 
 impl BootRom {
-    pub const fn new(contents: [u8; 256]) -> Self {
-        Self { contents }
+    /// A 256 byte DMG boot ROM
+    pub const fn new(contents: [u8; DMG_BOOT_ROM_SIZE]) -> Self {
+        let mut padded = [0u8; CGB_BOOT_ROM_SIZE];
+        let mut i = 0;
+
+        while i < DMG_BOOT_ROM_SIZE {
+            padded[i] = contents[i];
+            i += 1;
+        }
+
+        Self {
+            contents: padded,
+            length: DMG_BOOT_ROM_SIZE,
+        }
+    }
+
+    /// A 2304 byte CGB boot ROM, laid out as it is mapped, including the unmapped gap
+    pub const fn new_cgb(contents: [u8; CGB_BOOT_ROM_SIZE]) -> Self {
+        Self {
+            contents,
+            length: CGB_BOOT_ROM_SIZE,
+        }
+    }
+
+    pub const fn open_source_dmg() -> Self {
+        OPEN_SOURCE_BOOT_ROM
     }
 
-    pub fn contents(&self) -> &[u8; 256] {
-        &self.contents
+    pub fn contents(&self) -> &[u8] {
+        &self.contents[..self.length]
+    }
+
+    pub fn is_cgb(&self) -> bool {
+        self.length == CGB_BOOT_ROM_SIZE
+    }
+
+    /// Whether the boot ROM covers an address while it is mapped, which is 0x0000-0x00FF for
+    /// both, and 0x0200-0x08FF as well for a CGB boot ROM
+    pub fn maps(&self, address: u16) -> bool {
+        match address {
+            0x0000..=0x00FF => true,
+            0x0200..=0x08FF => self.is_cgb(),
+            _ => false,
+        }
     }
 }
 
 pub struct BootRomReader {}
 
 impl BootRomReader {
+    /// Reads a DMG or CGB boot ROM, telling them apart by their size
     pub fn read(reader: &mut impl Read) -> Result<BootRom, Error> {
-        let mut contents = [0u8; 256];
-        reader
-            .read_exact(&mut contents)
-            .map_err(|e| Error::from(e))?;
+        let mut contents = Vec::new();
+        reader.read_to_end(&mut contents)?;
+
+        match contents.len() {
+            DMG_BOOT_ROM_SIZE => Ok(BootRom::new(contents.try_into().unwrap())),
+            CGB_BOOT_ROM_SIZE => Ok(BootRom::new_cgb(contents.try_into().unwrap())),
+            length => Err(Error::InvalidSize(length)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{error::Error, BootRom, BootRomReader, CGB_BOOT_ROM_SIZE, OPEN_SOURCE_BOOT_ROM};
+
+    #[test]
+    fn open_source_boot_rom_size() {
+        let bytes = include_bytes!("open_dmg_boot.bin");
+        assert_eq!(bytes.len(), 256);
+
+        let boot_rom = BootRomReader::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(boot_rom.contents(), OPEN_SOURCE_BOOT_ROM.contents());
+        assert_eq!(BootRom::open_source_dmg().contents(), bytes);
+    }
+
+    #[test]
+    fn open_source_boot_rom_unmaps_itself_last() {
+        let contents = OPEN_SOURCE_BOOT_ROM.contents();
+
+        // ldh [$50], a as the final instruction so execution falls through to 0x0100
+        assert_eq!(&contents[0xFE..], &[0xE0, 0x50]);
+    }
+
+    #[test]
+    fn dmg_boot_rom_maps_first_page_only() {
+        let boot_rom = OPEN_SOURCE_BOOT_ROM;
+
+        assert!(!boot_rom.is_cgb());
+        assert!(boot_rom.maps(0x0000) && boot_rom.maps(0x00FF));
+        assert!(!boot_rom.maps(0x0100) && !boot_rom.maps(0x0200));
+    }
+
+    #[test]
+    fn cgb_boot_rom_read_by_size() {
+        let bytes = vec![0xAB; CGB_BOOT_ROM_SIZE];
+        let boot_rom = BootRomReader::read(&mut bytes.as_slice()).unwrap();
+
+        assert!(boot_rom.is_cgb());
+        assert_eq!(boot_rom.contents(), bytes.as_slice());
+        assert!(boot_rom.maps(0x00FF));
+        assert!(!boot_rom.maps(0x0100) && !boot_rom.maps(0x01FF));
+        assert!(boot_rom.maps(0x0200) && boot_rom.maps(0x08FF));
+        assert!(!boot_rom.maps(0x0900));
+    }
+
+    #[test]
+    fn other_sizes_are_rejected() {
+        let bytes = [0u8; 300];
 
-        Ok(BootRom::new(contents))
+        assert!(matches!(
+            BootRomReader::read(&mut bytes.as_slice()),
+            Err(Error::InvalidSize(300))
+        ));
     }
 }
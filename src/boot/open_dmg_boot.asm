@@ -0,0 +1,128 @@
+; Freely distributable DMG boot ROM, used as an alternative to `dmg_boot.bin`.
+;
+; It mirrors what the original boot ROM does closely enough for games to start:
+;   1. Clears VRAM and sets up the background palette
+;   2. Reads the logo from the cartridge header and expands it into tiles
+;   3. Scrolls the logo down from the top of the screen
+;   4. Verifies the header checksum, locking up if it doesn't match
+;   5. Sets the post-boot register values and unmaps itself
+;
+; The logo is only displayed, it is not compared against a reference copy.
+;
+; Assembles with RGBDS (rgbasm/rgblink), padded to exactly 256 bytes.
+
+SECTION "Boot", ROM0[$0000]
+
+EntryPoint:
+    ld sp, $FFFE
+
+    xor a
+    ld hl, $9FFF
+.clearVram:
+    ld [hl-], a
+    bit 7, h
+    jr nz, .clearVram
+
+    ld a, $FC
+    ldh [$47], a ; BGP
+
+    ; Each header byte holds two 4x4 rows, expanded to 8x8 tiles at $8010
+    ld de, $0104
+    ld hl, $8010
+.logoLoop:
+    ld a, [de]
+    ld c, a
+    call ExpandNibble
+    call ExpandNibble
+    inc de
+    ld a, e
+    cp $34
+    jr nz, .logoLoop
+
+    ; Tiles 1-12 on the top row, 13-24 on the bottom row
+    ld a, 1
+    ld hl, $9904
+.topRow:
+    ld [hl+], a
+    inc a
+    cp 13
+    jr nz, .topRow
+    ld l, $24
+.bottomRow:
+    ld [hl+], a
+    inc a
+    cp 25
+    jr nz, .bottomRow
+
+    ld a, $64
+    ldh [$42], a ; SCY
+    ld a, $91
+    ldh [$40], a ; LCDC
+.scroll:
+    call WaitVBlank
+    ldh a, [$42]
+    dec a
+    ldh [$42], a
+    jr nz, .scroll
+
+    ld b, 60
+.pause:
+    call WaitVBlank
+    dec b
+    jr nz, .pause
+
+    ; $19 + sum($0134-$014C) + checksum must be zero
+    ld hl, $0134
+    ld b, $19
+    ld a, b
+.checksum:
+    add a, [hl]
+    inc l
+    dec b
+    jr nz, .checksum
+    add a, [hl]
+.lockUp:
+    jr nz, .lockUp
+
+    ld bc, $01B0
+    push bc
+    pop af
+    ld bc, $0013
+    ld de, $00D8
+    ld hl, $014D
+    jp Done
+
+; Shifts the top nibble out of C, doubling each bit into A, and writes the
+; result to two consecutive tile rows
+ExpandNibble:
+    ld b, 4
+.bit:
+    add a, a
+    add a, a
+    sla c
+    jr nc, .skip
+    or $03
+.skip:
+    dec b
+    jr nz, .bit
+    ld [hl+], a
+    inc hl
+    ld [hl+], a
+    inc hl
+    ret
+
+WaitVBlank:
+.leave:
+    ldh a, [$44] ; LY
+    cp $90
+    jr z, .leave
+.wait:
+    ldh a, [$44]
+    cp $90
+    jr nz, .wait
+    ret
+
+    ds $FE - @, $00
+
+Done:
+    ldh [$50], a ; Unmap the boot ROM, execution continues at $0100
@@ -2,11 +2,11 @@ use eframe::egui;
 
 use crate::{
     boot::BootRom,
-    cartridge::Cartridge,
+    cartridge::{header::NINTENDO_LOGO, Cartridge},
     cpu::error::Error,
-    io::{interrupts::Interrupt, IO},
+    io::{interrupts::Interrupt, vram_dma::VramDmaTransfer, IO},
     memory::ram::{HighRam, WorkRam},
-    ppu::{Ppu, TOTAL_PIXELS},
+    ppu::{vram::ColorId, Ppu, PpuMode, RGBA_FRAME_LENGTH, TOTAL_PIXELS},
 };
 
 #[derive(Clone)]
@@ -17,96 +17,207 @@ pub struct Bus {
     work_ram: WorkRam,
     io: IO,
     high_ram: HighRam,
+    cgb_mode: bool,
+    bypass_logo_check: bool,
 }
 
 impl Bus {
     pub fn new(boot_rom: BootRom, cartridge: Cartridge) -> Self {
+        let cgb_mode = cartridge.header().cgb_flag().requests_cgb();
+
         Self {
             ppu: Ppu::new(),
             boot_rom,
             cartridge,
-            work_ram: WorkRam::new(),
+            work_ram: WorkRam::new(cgb_mode),
             io: IO::new(),
             high_ram: HighRam::new(),
+            cgb_mode,
+            bypass_logo_check: false,
         }
     }
 
+    /// Reads a byte as the CPU sees it, which is restricted while an OAM DMA transfer is running
     pub fn read_u8(&self, address: u16) -> Result<u8, Error> {
+        if self.blocked_by_dma(address) {
+            return Ok(0xFF);
+        }
+
+        self.read_u8_unrestricted(address)
+    }
+
+    fn read_u8_unrestricted(&self, address: u16) -> Result<u8, Error> {
         Ok(match address {
-            0x0000..=0x00FF => {
-                if self.boot_rom_enabled() {
-                    self.boot_rom.contents()[address as usize]
-                } else {
-                    self.cartridge.bank0()[address as usize]
-                }
+            0x0000..=0x08FF if self.boot_rom_mapped() && self.boot_rom.maps(address) => {
+                self.boot_rom.contents()[address as usize]
             }
-            0x0100..=0x3FFF => self.cartridge.bank0()[address as usize],
+            0x0104..=0x0133 if self.bypass_logo_check && self.boot_rom_mapped() => {
+                NINTENDO_LOGO[address as usize - 0x0104]
+            }
+            0x0000..=0x3FFF => self.cartridge.bank0()[address as usize],
             0x4000..=0x7FFF => self.cartridge.bank1()[(address as usize) - 0x4000],
             0x8000..=0x9FFF => self.ppu.vram().read_u8(address)?,
+            0xA000..=0xBFFF => self.cartridge.read_ram(address),
             0xC000..=0xDFFF => self.work_ram.read_u8(address),
             0xE000..=0xFDFF => {
                 // Echo RAM
                 let wrapped_address = address - 0x2000;
                 self.work_ram.read_u8(wrapped_address)
             }
-            0xFE00..=0xFE9F => self.ppu.oam().read_u8(address),
+            0xFE00..=0xFE9F => self.ppu.oam().read_u8(address)?,
             0xFEA0..=0xFEFF => 0xFF, // Unusable, but some games have bugs that read/write it
+            0xFF70 => self.work_ram.read_bank_select(),
             0xFF00..=0xFF7E => self.io.read_u8(address)?,
             0xFF7F => 0xFF, // Tetris writes to this on accident
             0xFF80..=0xFFFE => self.high_ram.read_u8(address),
-            _ => {
-                0xFF
-                // return Err(Error::MemoryReadFault(address));
-            }
+            0xFFFF => self.io.read_u8(address)?,
         })
     }
 
     pub fn read_u16(&self, address: u16) -> Result<u16, Error> {
         let lower = self.read_u8(address)?;
-        let higher = self.read_u8(address + 1)?;
+        let higher = self.read_u8(address.wrapping_add(1))?;
 
         Ok(((higher as u16) << 8) | lower as u16)
     }
 
     pub fn write_u8(&mut self, address: u16, data: u8) -> Result<(), Error> {
+        if self.blocked_by_dma(address) {
+            return Ok(());
+        }
+
         Ok(match address {
-            0x0000..=0x7FFF => {}
+            0x0000..=0x7FFF => self.cartridge.write_u8(address, data),
             0x8000..=0x9FFF => self.ppu.vram_mut().write_u8(address, data)?,
+            0xA000..=0xBFFF => self.cartridge.write_ram(address, data),
             0xC000..=0xDFFF => self.work_ram.write_u8(address, data),
             0xE000..=0xFDFF => {
                 // Echo RAM
                 let wrapped_address = address - 0x2000;
                 self.work_ram.write_u8(wrapped_address, data)
             }
-            0xFE00..=0xFE9F => self.ppu.oam_mut().write_u8(address, data),
+            0xFE00..=0xFE9F => self.ppu.oam_mut().write_u8(address, data)?,
             0xFEA0..=0xFEFF => {} // Unusable, but some games have bugs that read/write it
+            0xFF70 => self.work_ram.write_bank_select(data),
+            0xFF51..=0xFF55 if !self.cgb_mode => {} // VRAM DMA is only available on the CGB
+            0xFF6C if !self.cgb_mode => {}          // DMG object priority is fixed
+            // Unlike the DMG, the CGB doesn't let the length timers be written while the APU is off
+            0xFF10..=0xFF25 if self.cgb_mode && !self.io.audio().powered_on() => {}
+            0xFF55 => {
+                self.io.write_u8(address, data)?;
+
+                if let Some(transfer) = self.io.vram_dma_mut().take_general_transfer() {
+                    self.copy_to_vram(transfer)?;
+                }
+            }
             0xFF00..=0xFF7E => self.io.write_u8(address, data)?,
             0xFF7F => {} // Tetris writes to this on accident
             0xFF80..=0xFFFE => self.high_ram.write_u8(address, data),
             0xFFFF => self.io.write_u8(address, data)?,
-            _ => {
-                // return Err(Error::MemoryWriteFault(address, data));
-            }
         })
     }
 
     pub fn write_u16(&mut self, address: u16, data: u16) -> Result<(), Error> {
-        self.write_u8(address + 1, (data >> 8) as u8)?;
+        self.write_u8(address.wrapping_add(1), (data >> 8) as u8)?;
         self.write_u8(address, (data & 0xFF) as u8)
     }
 
-    fn boot_rom_enabled(&self) -> bool {
+    /// Reads a byte for the debugger, ignoring DMA restrictions and without any read side effects
+    pub fn debug_read_u8(&self, address: u16) -> u8 {
+        match address {
+            0xFF70 => self.work_ram.read_bank_select(),
+            0xFF00..=0xFF7E | 0xFFFF => self.io.debug_read_u8(address),
+            _ => self.read_u8_unrestricted(address).unwrap_or(0xFF),
+        }
+    }
+
+    /// While OAM DMA is running the CPU can only reach IO registers and high RAM
+    fn blocked_by_dma(&self, address: u16) -> bool {
+        self.io.dma().transferring() && (address < 0xFF00)
+    }
+
+    /// Copies the bytes that the OAM DMA transfer reaches within the given number of cycles
+    pub fn step_dma(&mut self, cycles: usize) -> Result<(), Error> {
+        let source_address = self.io.dma().full_source_address();
+
+        for offset in self.io.dma_mut().step(cycles) {
+            let byte = self.read_u8_unrestricted(source_address + offset)?;
+            self.ppu.oam_mut().write_u8(0xFE00 + offset, byte)?;
+        }
+
+        Ok(())
+    }
+
+    /// Performs a VRAM DMA transfer all at once
+    fn copy_to_vram(&mut self, transfer: VramDmaTransfer) -> Result<(), Error> {
+        for offset in 0..transfer.length {
+            let byte = self.read_u8_unrestricted(transfer.source.wrapping_add(offset))?;
+            let destination = 0x8000 | (transfer.destination.wrapping_add(offset) & 0x1FFF);
+            self.ppu.vram_mut().write_u8(destination, byte)?;
+        }
+
+        Ok(())
+    }
+
+    /// With `bypass`, the boot ROM reads the Nintendo logo in place of the cartridge's, so that
+    /// cartridges with a missing or wrong logo boot rather than locking up
+    ///
+    /// The cartridge's own logo is seen again once the boot ROM is unmapped.
+    pub fn set_bypass_logo_check(&mut self, bypass: bool) {
+        self.bypass_logo_check = bypass;
+    }
+
+    /// Whether the boot ROM is visible at 0x0000-0x00FF, which is until anything is written to 0xFF50
+    pub fn boot_rom_mapped(&self) -> bool {
         self.io.boot_rom_enable() == 0
     }
 
-    pub fn step_ppu(&mut self, cycles: usize) -> (Option<Interrupt>, Option<Interrupt>, bool) {
-        self.ppu.step(self.io.lcd_mut(), cycles)
+    pub fn set_boot_rom_mapped(&mut self, mapped: bool) {
+        let value = if mapped { 0x00 } else { 0x01 };
+        self.io.set_boot_rom_enable(value);
+    }
+
+    pub fn step_ppu(
+        &mut self,
+        cycles: usize,
+    ) -> Result<(Option<Interrupt>, Option<Interrupt>, bool), Error> {
+        let old_mode = self.io.lcd().status().ppu_mode();
+        let result = self.ppu.step(self.io.lcd_mut(), cycles);
+        let new_mode = self.io.lcd().status().ppu_mode();
+
+        if (new_mode == PpuMode::HBlank) & (old_mode != PpuMode::HBlank) {
+            if let Some(transfer) = self.io.vram_dma_mut().hblank() {
+                self.copy_to_vram(transfer)?;
+            }
+        }
+
+        Ok(result)
     }
 
     pub fn render(&mut self) -> &[egui::Color32; TOTAL_PIXELS] {
         self.ppu.render(self.io.lcd_mut())
     }
 
+    pub fn frame_rgba(&self) -> &[u8; RGBA_FRAME_LENGTH] {
+        self.ppu.frame_rgba(self.io.lcd())
+    }
+
+    pub fn frame_color_ids(&self) -> &[ColorId; TOTAL_PIXELS] {
+        self.ppu.frame_color_ids()
+    }
+
+    pub fn cgb_mode(&self) -> bool {
+        self.cgb_mode
+    }
+
+    pub fn cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    pub fn work_ram(&self) -> &WorkRam {
+        &self.work_ram
+    }
+
     pub fn io(&self) -> &IO {
         &self.io
     }
@@ -115,6 +226,10 @@ impl Bus {
         &mut self.io
     }
 
+    pub fn ppu(&self) -> &Ppu {
+        &self.ppu
+    }
+
     pub fn ppu_mut(&mut self) -> &mut Ppu {
         &mut self.ppu
     }
@@ -1,3 +1,5 @@
+use super::header::CartridgeType;
+
 #[derive(Debug)]
 pub enum Error {
     Io(std::io::Error),
@@ -6,6 +8,17 @@ pub enum Error {
     InvalidCartridgeRomSize,
     InvalidCartridgeRamSize,
     InvalidCartridgeDestinationCode,
+    UnsupportedCartridgeType(CartridgeType),
+    /// The ROM's length in bytes doesn't match the ROM size given in its header
+    RomSizeMismatch {
+        expected: usize,
+        found: usize,
+    },
+    /// A save file's length doesn't match the RAM size given in the cartridge header
+    SaveSizeMismatch {
+        expected: usize,
+        found: usize,
+    },
 }
 
 impl From<std::io::Error> for Error {
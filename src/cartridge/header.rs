@@ -2,10 +2,19 @@ use std::fmt::Display;
 
 use super::error::Error;
 
-use super::BANK_SIZE;
+use super::{BANK_SIZE, RAM_BANK_SIZE};
+
+/// The Nintendo logo at 0x0104-0x0133, which the boot ROM refuses to start a cartridge without
+#[rustfmt::skip]
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
 
 #[derive(Debug, Clone)]
 pub struct CartridgeHeader {
+    logo: [u8; 48],
     title: String,
     manufacturer_code: ManufacturerCode,
     cgb_flag: CgbFlag,
@@ -24,6 +33,7 @@ pub struct CartridgeHeader {
 }
 
 impl CartridgeHeader {
+    /// A header with a blank logo, which the boot ROM would reject
     pub fn new(
         title: impl Into<String>,
         manufacturer_code: ManufacturerCode,
@@ -40,6 +50,7 @@ impl CartridgeHeader {
         global_checksum: u16,
     ) -> Self {
         Self {
+            logo: [0; 48],
             title: title.into(),
             manufacturer_code,
             new_licensee_code,
@@ -58,6 +69,16 @@ impl CartridgeHeader {
         }
     }
 
+    /// The logo bytes at 0x0104-0x0133
+    pub fn logo(&self) -> &[u8; 48] {
+        &self.logo
+    }
+
+    /// Whether the logo matches the Nintendo logo the boot ROM checks it against
+    pub fn logo_valid(&self) -> bool {
+        self.logo == NINTENDO_LOGO
+    }
+
     pub fn title(&self) -> &str {
         &self.title
     }
@@ -138,6 +159,7 @@ pub struct CartridgeHeaderReader {}
 
 impl CartridgeHeaderReader {
     pub fn read(bank0: &[u8], extra_banks: &[u8]) -> Result<CartridgeHeader, Error> {
+        let logo = Self::read_logo(bank0);
         let title = Self::read_title(bank0)?;
         let manufacturer_code = Self::read_manufacturer_code(bank0)?;
         let cgb_flag = Self::read_cgb_flag(bank0);
@@ -155,6 +177,7 @@ impl CartridgeHeaderReader {
         let computed_global_checksum = Self::calculate_global_checksum(bank0, extra_banks);
 
         Ok(CartridgeHeader {
+            logo,
             title: title.to_string(),
             manufacturer_code,
             cgb_flag,
@@ -173,6 +196,12 @@ impl CartridgeHeaderReader {
         })
     }
 
+    fn read_logo(bank0: &[u8]) -> [u8; 48] {
+        let mut logo = [0; 48];
+        logo.copy_from_slice(&bank0[0x0104..=0x0133]);
+        logo
+    }
+
     fn read_title(bank0: &[u8]) -> Result<&'_ str, Error> {
         let start = 0x0134;
         let mut end = start;
@@ -365,6 +394,20 @@ impl TryFrom<u8> for CartridgeType {
     }
 }
 
+impl CartridgeType {
+    /// Whether the emulator implements this cartridge's mapper, so that it can be loaded
+    pub fn is_supported(self) -> bool {
+        matches!(
+            self,
+            Self::RomOnly
+                | Self::Mmm01
+                | Self::Mmm01Ram
+                | Self::Mmm01RamBattery
+                | Self::Huc1RamBattery
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ManufacturerCode {
     code: String,
@@ -393,6 +436,13 @@ pub enum CgbFlag {
     CgbOnly,
 }
 
+impl CgbFlag {
+    /// Whether the cartridge wants CGB features, which is true even if it also runs on a DMG
+    pub fn requests_cgb(self) -> bool {
+        self != Self::No
+    }
+}
+
 impl From<u8> for CgbFlag {
     fn from(value: u8) -> Self {
         match value {
@@ -449,6 +499,23 @@ impl TryFrom<u8> for RamSize {
     }
 }
 
+impl RamSize {
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::NoRam => 0,
+            Self::Size8KiB => 8 * 1024,
+            Self::Size32KiB => 32 * 1024,
+            Self::Size64KiB => 64 * 1024,
+            Self::Size128KiB => 128 * 1024,
+        }
+    }
+
+    /// The number of 8 KiB RAM banks
+    pub fn num_banks(self) -> usize {
+        self.bytes() / RAM_BANK_SIZE
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RomSize {
     Size32KiB,
@@ -483,6 +550,29 @@ impl TryFrom<u8> for RomSize {
     }
 }
 
+impl RomSize {
+    pub fn bytes(self) -> usize {
+        self.num_banks() * BANK_SIZE
+    }
+
+    /// The number of 16 KiB ROM banks, which doubles with each size
+    pub fn num_banks(self) -> usize {
+        let doublings = match self {
+            Self::Size32KiB => 0,
+            Self::Size64KiB => 1,
+            Self::Size128KiB => 2,
+            Self::Size256KiB => 3,
+            Self::Size512KiB => 4,
+            Self::Size1MiB => 5,
+            Self::Size2MiB => 6,
+            Self::Size4MiB => 7,
+            Self::Size8MiB => 8,
+        };
+
+        2 << doublings
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NewLicenseeCode {
     None,
@@ -0,0 +1,84 @@
+use super::{mbc::Mbc, RAM_BANK_SIZE};
+
+/// What the IR register reads as when no infrared light is being received
+pub const IR_NO_LIGHT: u8 = 0xC0;
+
+/// The value written to 0x0000-0x1FFF that maps the IR register at 0xA000-0xBFFF
+const IR_SELECT: u8 = 0x0E;
+
+/// The banking registers of Hudson's HuC1 mapper
+///
+/// It banks ROM and RAM like an MBC1, but instead of a RAM enable register it selects whether
+/// 0xA000-0xBFFF shows cartridge RAM or the infrared port. The IR port isn't connected to
+/// anything, so it never receives light.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Huc1 {
+    ir_selected: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+    ir_led_on: bool,
+}
+
+impl Huc1 {
+    pub fn new() -> Self {
+        Self {
+            ir_selected: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            ir_led_on: false,
+        }
+    }
+
+    pub fn ir_led_on(&self) -> bool {
+        self.ir_led_on
+    }
+
+    fn ram_offset(&self, address: u16, ram_size: usize) -> Option<usize> {
+        if ram_size == 0 {
+            return None;
+        }
+
+        let offset = self.ram_bank as usize * RAM_BANK_SIZE + (address as usize - 0xA000);
+        Some(offset % ram_size)
+    }
+}
+
+impl Mbc for Huc1 {
+    fn write_u8(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ir_selected = data == IR_SELECT,
+            0x2000..=0x3FFF => self.rom_bank = (data & 0x3F).max(1),
+            0x4000..=0x5FFF => self.ram_bank = data & 0x03,
+            _ => {}
+        }
+    }
+
+    fn rom_banks(&self, num_banks: usize) -> (usize, usize) {
+        (0, self.rom_bank as usize % num_banks)
+    }
+
+    fn read_ram(&self, address: u16, ram: &[u8]) -> u8 {
+        if self.ir_selected {
+            return IR_NO_LIGHT;
+        }
+
+        match self.ram_offset(address, ram.len()) {
+            Some(offset) => ram[offset],
+            None => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8, ram: &mut [u8]) {
+        if self.ir_selected {
+            self.ir_led_on = (data & 0x01) != 0;
+        } else if let Some(offset) = self.ram_offset(address, ram.len()) {
+            ram[offset] = data;
+        }
+    }
+}
+
+impl Default for Huc1 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
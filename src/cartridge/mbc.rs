@@ -0,0 +1,42 @@
+use super::BANK_SIZE;
+
+/// A memory bank controller, the mapper chip that decides which parts of the cartridge's ROM and
+/// RAM are visible to the Game Boy
+///
+/// Mappers only hold their banking registers, the ROM and RAM themselves belong to the
+/// [`Cartridge`](super::Cartridge) and are passed in.
+pub trait Mbc {
+    /// Handles a write to the mapper's registers at 0x0000-0x7FFF
+    fn write_u8(&mut self, address: u16, data: u8);
+
+    /// The ROM banks mapped at 0x0000-0x3FFF and 0x4000-0x7FFF, given how many banks the ROM has
+    fn rom_banks(&self, num_banks: usize) -> (usize, usize);
+
+    /// Reads from cartridge RAM, or whatever else the mapper shows, at 0xA000-0xBFFF
+    fn read_ram(&self, _address: u16, _ram: &[u8]) -> u8 {
+        0xFF
+    }
+
+    fn write_ram(&mut self, _address: u16, _data: u8, _ram: &mut [u8]) {}
+
+    /// Reads a byte of the cartridge as the bus sees it, at 0x0000-0x7FFF or 0xA000-0xBFFF
+    fn read(&self, address: u16, banks: &[[u8; BANK_SIZE]], ram: &[u8]) -> u8 {
+        let (bank0, bank1) = self.rom_banks(banks.len());
+
+        match address {
+            0x0000..=0x3FFF => banks[bank0][address as usize],
+            0x4000..=0x7FFF => banks[bank1][address as usize - 0x4000],
+            0xA000..=0xBFFF => self.read_ram(address, ram),
+            _ => 0xFF,
+        }
+    }
+
+    /// Writes a byte to the cartridge as the bus would, at 0x0000-0x7FFF or 0xA000-0xBFFF
+    fn write(&mut self, address: u16, data: u8, ram: &mut [u8]) {
+        match address {
+            0x0000..=0x7FFF => self.write_u8(address, data),
+            0xA000..=0xBFFF => self.write_ram(address, data, ram),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use super::mbc::Mbc;
+
+/// The banking registers of an MMM01, the mapper used by multi-game compilation cartridges
+///
+/// At power on the MMM01 is "unmapped", and shows the last 32 KiB of the ROM, which is where the
+/// compilation's menu lives. The menu picks a game by writing the upper bits of the ROM bank number
+/// and a mask of how many banks the game has, then sets the map enable bit. From then on those
+/// bits are locked, and the game sees an MBC1-like mapper limited to its own banks.
+///
+/// External RAM isn't emulated yet, so the RAM enable and RAM bank registers are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mmm01 {
+    mapped: bool,
+    /// Bits 0-4 of the ROM bank number, written at 0x2000-0x3FFF
+    rom_bank_low: u8,
+    /// Bits 5-8 of the ROM bank number, only writable while unmapped
+    rom_bank_high: u8,
+    /// Which of bits 1-4 of the ROM bank number are fixed by the menu rather than the game
+    rom_bank_mask: u8,
+}
+
+impl Mmm01 {
+    pub fn new() -> Self {
+        Self {
+            mapped: false,
+            rom_bank_low: 0,
+            rom_bank_high: 0,
+            rom_bank_mask: 0,
+        }
+    }
+
+    pub fn mapped(&self) -> bool {
+        self.mapped
+    }
+}
+
+impl Mbc for Mmm01 {
+    fn write_u8(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1FFF if !self.mapped => self.mapped = (data & 0x40) != 0,
+            0x2000..=0x3FFF => {
+                // Bits fixed by the mask keep the value the menu gave them
+                let writable = if self.mapped {
+                    !(self.rom_bank_mask << 1) & 0x1F
+                } else {
+                    0x1F
+                };
+
+                self.rom_bank_low = (self.rom_bank_low & !writable) | (data & writable);
+
+                if !self.mapped {
+                    self.rom_bank_high = (self.rom_bank_high & 0b1100) | ((data >> 5) & 0b0011);
+                }
+            }
+            0x4000..=0x5FFF if !self.mapped => {
+                self.rom_bank_high = (self.rom_bank_high & 0b0011) | ((data >> 2) & 0b1100);
+            }
+            0x6000..=0x7FFF if !self.mapped => self.rom_bank_mask = (data >> 2) & 0x0F,
+            _ => {}
+        }
+    }
+
+    /// Before the menu maps a game, the last two banks are shown, which hold the menu
+    fn rom_banks(&self, num_banks: usize) -> (usize, usize) {
+        if !self.mapped {
+            return (num_banks.saturating_sub(2), num_banks.saturating_sub(1));
+        }
+
+        let fixed_low = (self.rom_bank_mask << 1) & 0x1F;
+        let base = ((self.rom_bank_high as usize) << 5) | (self.rom_bank_low & fixed_low) as usize;
+
+        // Like the MBC1, bank 0 can't be selected for the switchable area
+        let mut selected_low = self.rom_bank_low & !fixed_low;
+        if selected_low == 0 {
+            selected_low = 1;
+        }
+
+        (base % num_banks, (base | selected_low as usize) % num_banks)
+    }
+}
+
+impl Default for Mmm01 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Mbc, Mmm01};
+
+    #[test]
+    fn menu_selects_game() {
+        let mut mmm01 = Mmm01::new();
+        assert_eq!(mmm01.rom_banks(16), (14, 15));
+
+        // Select the game starting at bank 4, which has 4 banks
+        mmm01.write_u8(0x2000, 0x04);
+        mmm01.write_u8(0x6000, 0b0011_1000);
+        mmm01.write_u8(0x0000, 0x40);
+        assert!(mmm01.mapped());
+        assert_eq!(mmm01.rom_banks(16), (4, 5));
+
+        mmm01.write_u8(0x2000, 0x03);
+        assert_eq!(mmm01.rom_banks(16), (4, 7));
+
+        // The game can't leave its own banks
+        mmm01.write_u8(0x2000, 0x1E);
+        mmm01.write_u8(0x6000, 0x00);
+        assert_eq!(mmm01.rom_banks(16), (4, 6));
+    }
+}
@@ -1,20 +1,54 @@
 use std::io::Read;
 
 use error::Error;
-use header::{CartridgeHeader, CartridgeHeaderReader, ManufacturerCode};
+use header::{CartridgeHeader, CartridgeHeaderReader, CartridgeType, ManufacturerCode};
+use huc1::Huc1;
+use mbc::Mbc;
+use mmm01::Mmm01;
+use rom_only::RomOnly;
 
-mod error;
+pub mod error;
 pub mod header;
+pub mod huc1;
+pub mod mbc;
+pub mod mmm01;
 pub mod ram;
+pub mod rom_only;
 
 const BANK_SIZE: usize = 16 * 1024;
+const RAM_BANK_SIZE: usize = 8 * 1024;
+
+#[derive(Debug, Clone)]
+enum Mapper {
+    RomOnly(RomOnly),
+    Mmm01(Mmm01),
+    Huc1(Huc1),
+}
+
+impl Mapper {
+    fn mbc(&self) -> &dyn Mbc {
+        match self {
+            Self::RomOnly(rom_only) => rom_only,
+            Self::Mmm01(mmm01) => mmm01,
+            Self::Huc1(huc1) => huc1,
+        }
+    }
+
+    fn mbc_mut(&mut self) -> &mut dyn Mbc {
+        match self {
+            Self::RomOnly(rom_only) => rom_only,
+            Self::Mmm01(mmm01) => mmm01,
+            Self::Huc1(huc1) => huc1,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Cartridge {
-    bank0: [u8; BANK_SIZE],
-    extra_banks: Vec<[u8; BANK_SIZE]>,
+    banks: Vec<[u8; BANK_SIZE]>,
+    ram: Vec<u8>,
     header: CartridgeHeader,
-    bank_selected: usize,
+    mapper: Mapper,
 }
 
 impl Cartridge {
@@ -38,10 +72,10 @@ impl Cartridge {
         );
 
         Self {
-            bank0,
-            extra_banks: vec![bank1],
+            banks: vec![bank0, bank1],
+            ram: Vec::new(),
             header,
-            bank_selected: 0,
+            mapper: Mapper::RomOnly(RomOnly),
         }
     }
 
@@ -54,41 +88,335 @@ impl Cartridge {
             .read_to_end(&mut remaining_rom_bytes)
             .map_err(|e| Error::from(e))?;
 
-        let header = CartridgeHeaderReader::read(&bank0, &remaining_rom_bytes)?;
+        let mut banks = vec![bank0];
 
-        if header.cartridge_type() != header::CartridgeType::RomOnly {
-            panic!("Just ROM-only cartridges are currently supported, cartridge was {:?}", header.cartridge_type());
+        for chunk in remaining_rom_bytes.chunks_exact(BANK_SIZE) {
+            let mut bank = [0u8; BANK_SIZE];
+            bank.copy_from_slice(chunk);
+            banks.push(bank);
         }
 
-        if (remaining_rom_bytes.len() % BANK_SIZE) != 0 {
-            panic!("ROM doesn't have a size in a multiple of banks, possibly a not yet supported format: {}", (remaining_rom_bytes.len() + BANK_SIZE));
+        let header = match Self::read_mmm01_menu_header(&banks) {
+            Some(header) => header,
+            None => CartridgeHeaderReader::read(&bank0, &remaining_rom_bytes)?,
+        };
+
+        // Truncated, overdumped, or padded ROMs would otherwise bank into the wrong data
+        let rom_length = BANK_SIZE + remaining_rom_bytes.len();
+        if rom_length != header.rom_size().bytes() {
+            return Err(Error::RomSizeMismatch {
+                expected: header.rom_size().bytes(),
+                found: rom_length,
+            });
         }
 
-        let mut extra_banks = Vec::new();
+        let mapper = match header.cartridge_type() {
+            CartridgeType::RomOnly => Mapper::RomOnly(RomOnly),
+            CartridgeType::Mmm01 | CartridgeType::Mmm01Ram | CartridgeType::Mmm01RamBattery => {
+                Mapper::Mmm01(Mmm01::new())
+            }
+            CartridgeType::Huc1RamBattery => Mapper::Huc1(Huc1::new()),
+            other => return Err(Error::UnsupportedCartridgeType(other)),
+        };
 
-        for chunk in remaining_rom_bytes.chunks_exact(BANK_SIZE) {
-            let mut bank = [0u8; BANK_SIZE];
-            bank.copy_from_slice(chunk);
-            extra_banks.push(bank);
-        }
+        let ram = vec![0; header.ram_size().bytes()];
 
         Ok(Self {
-            bank0,
+            banks,
+            ram,
             header,
-            extra_banks,
-            bank_selected: 0,
+            mapper,
         })
     }
 
+    /// MMM01 compilations keep the menu, and the header describing the whole cartridge, in the
+    /// last 32 KiB of the ROM, while the first bank holds the first game's own header
+    fn read_mmm01_menu_header(banks: &[[u8; BANK_SIZE]]) -> Option<CartridgeHeader> {
+        let menu = banks.len().checked_sub(2)?;
+        let header = CartridgeHeaderReader::read(&banks[menu], &banks[menu + 1]).ok()?;
+
+        match header.cartridge_type() {
+            CartridgeType::Mmm01 | CartridgeType::Mmm01Ram | CartridgeType::Mmm01RamBattery => {
+                Some(header)
+            }
+            _ => None,
+        }
+    }
+
     pub fn header(&self) -> &CartridgeHeader {
         &self.header
     }
 
+    /// The contents of cartridge RAM, sized as the header specifies, which a save file holds
+    pub fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    /// Replaces the contents of cartridge RAM with a save file, which must be exactly the size of
+    /// the RAM given in the header. On error, RAM is left untouched.
+    pub fn load_ram(&mut self, reader: &mut impl Read) -> Result<(), Error> {
+        let mut save = Vec::with_capacity(self.ram.len());
+        reader.read_to_end(&mut save)?;
+
+        if save.len() != self.ram.len() {
+            return Err(Error::SaveSizeMismatch {
+                expected: self.ram.len(),
+                found: save.len(),
+            });
+        }
+
+        self.ram = save;
+        Ok(())
+    }
+
+    /// The number of 16 KiB banks the ROM has, which always matches the header's ROM size
+    pub fn num_rom_banks(&self) -> usize {
+        self.banks.len()
+    }
+
+    /// The ROM bank mapped at 0x0000-0x3FFF
     pub fn bank0(&self) -> &[u8; BANK_SIZE] {
-        &self.bank0
+        &self.banks[self.rom_banks().0]
     }
 
+    /// The ROM bank mapped at 0x4000-0x7FFF
     pub fn bank1(&self) -> &[u8; BANK_SIZE] {
-        &self.extra_banks[self.bank_selected]
+        &self.banks[self.rom_banks().1]
+    }
+
+    /// Handles a write to the mapper's registers at 0x0000-0x7FFF
+    pub fn write_u8(&mut self, address: u16, data: u8) {
+        self.mapper.mbc_mut().write_u8(address, data);
+    }
+
+    /// Reads from cartridge RAM, or whatever else the mapper shows, at 0xA000-0xBFFF
+    pub fn read_ram(&self, address: u16) -> u8 {
+        self.mapper.mbc().read_ram(address, &self.ram)
+    }
+
+    pub fn write_ram(&mut self, address: u16, data: u8) {
+        self.mapper
+            .mbc_mut()
+            .write_ram(address, data, &mut self.ram);
+    }
+
+    /// The numbers of the ROM banks mapped at 0x0000-0x3FFF and 0x4000-0x7FFF
+    pub fn rom_banks(&self) -> (usize, usize) {
+        self.mapper.mbc().rom_banks(self.banks.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        error::Error,
+        header::{CartridgeType, RamSize, RomSize, NINTENDO_LOGO},
+        huc1::IR_NO_LIGHT,
+        Cartridge, BANK_SIZE,
+    };
+
+    #[test]
+    fn read_exposes_header() {
+        // Loading is silent, everything parsed from the header is available through header()
+        let mut rom = vec![0u8; BANK_SIZE * 2];
+        rom[0x0134..0x0138].copy_from_slice(b"TEST");
+
+        let cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+
+        assert_eq!(cartridge.header().title(), "TEST");
+        assert_eq!(cartridge.header().cartridge_type(), CartridgeType::RomOnly);
+    }
+
+    #[test]
+    fn logo_must_match_nintendo_logo() {
+        let mut rom = vec![0u8; BANK_SIZE * 2];
+        rom[0x0104..0x0134].copy_from_slice(&NINTENDO_LOGO);
+
+        let cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+        assert!(cartridge.header().logo_valid());
+
+        rom[0x0110] ^= 0x01;
+        let cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+        assert!(!cartridge.header().logo_valid());
+        assert_eq!(cartridge.header().logo()[0x0C], NINTENDO_LOGO[0x0C] ^ 0x01);
+    }
+
+    #[test]
+    fn unsupported_type_is_an_error() {
+        let mut rom = vec![0u8; BANK_SIZE * 2];
+        rom[0x0147] = 0x20; // MBC6
+
+        assert!(matches!(
+            Cartridge::read(&mut rom.as_slice()),
+            Err(Error::UnsupportedCartridgeType(CartridgeType::Mbc6))
+        ));
+    }
+
+    #[test]
+    fn mmm01_boots_to_menu() {
+        // A compilation of one 32 KiB game and the 32 KiB menu, marked with each bank's number
+        let mut rom = vec![0u8; BANK_SIZE * 4];
+        for (number, bank) in rom.chunks_exact_mut(BANK_SIZE).enumerate() {
+            bank[0x0200] = number as u8;
+        }
+
+        let menu = BANK_SIZE * 2;
+        rom[menu + 0x0134..menu + 0x0138].copy_from_slice(b"MENU");
+        rom[menu + 0x0147] = 0x0B; // MMM01
+        rom[menu + 0x0148] = 0x01; // 64 KiB
+
+        let mut cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+
+        assert_eq!(cartridge.header().title(), "MENU");
+        assert_eq!(cartridge.header().cartridge_type(), CartridgeType::Mmm01);
+        assert_eq!(cartridge.bank0()[0x0200], 2);
+        assert_eq!(cartridge.bank1()[0x0200], 3);
+
+        // The menu starts the game in banks 0-1
+        cartridge.write_u8(0x2000, 0x00);
+        cartridge.write_u8(0x6000, 0b0011_1100);
+        cartridge.write_u8(0x0000, 0x40);
+
+        assert_eq!(cartridge.bank0()[0x0200], 0);
+        assert_eq!(cartridge.bank1()[0x0200], 1);
+    }
+
+    #[test]
+    fn huc1_banks_rom_and_stubs_ir() {
+        let mut rom = vec![0u8; BANK_SIZE * 4];
+        for (number, bank) in rom.chunks_exact_mut(BANK_SIZE).enumerate() {
+            bank[0x0200] = number as u8;
+        }
+        rom[0x0147] = 0xFF; // HuC1+RAM+BATTERY
+        rom[0x0148] = 0x01; // 64 KiB
+        rom[0x0149] = 0x02; // 8 KiB
+
+        let mut cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+        assert_eq!(cartridge.bank1()[0x0200], 1);
+
+        cartridge.write_u8(0x2000, 0x03);
+        assert_eq!(cartridge.bank0()[0x0200], 0);
+        assert_eq!(cartridge.bank1()[0x0200], 3);
+
+        cartridge.write_u8(0x2000, 0x00);
+        assert_eq!(cartridge.bank1()[0x0200], 1);
+
+        cartridge.write_ram(0xA010, 0x42);
+        assert_eq!(cartridge.read_ram(0xA010), 0x42);
+
+        // With the IR port selected, nothing is ever received
+        cartridge.write_u8(0x0000, 0x0E);
+        assert_eq!(cartridge.read_ram(0xA010), IR_NO_LIGHT);
+        cartridge.write_ram(0xA000, 0x01);
+
+        cartridge.write_u8(0x0000, 0x00);
+        assert_eq!(cartridge.read_ram(0xA010), 0x42);
+    }
+
+    #[test]
+    fn ram_is_allocated_from_header() {
+        for (code, size) in [
+            (0x00, 0),
+            (0x02, 8 * 1024),
+            (0x03, 32 * 1024),
+            (0x04, 128 * 1024),
+            (0x05, 64 * 1024),
+        ] {
+            let mut rom = vec![0u8; BANK_SIZE * 2];
+            rom[0x0147] = 0xFF; // HuC1+RAM+BATTERY
+            rom[0x0149] = code;
+
+            let cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+            assert_eq!(cartridge.ram().len(), size, "RAM size code {:02X}", code);
+        }
+    }
+
+    #[test]
+    fn save_must_match_ram_size() {
+        let mut rom = vec![0u8; BANK_SIZE * 2];
+        rom[0x0147] = 0xFF; // HuC1+RAM+BATTERY
+        rom[0x0149] = 0x02; // 8 KiB
+
+        let mut cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+
+        let short = vec![0x42; 8 * 1024 - 1];
+        assert!(matches!(
+            cartridge.load_ram(&mut short.as_slice()),
+            Err(Error::SaveSizeMismatch {
+                expected: 0x2000,
+                found: 0x1FFF
+            })
+        ));
+        assert_eq!(cartridge.read_ram(0xA000), 0x00);
+
+        let save = vec![0x42; 8 * 1024];
+        cartridge.load_ram(&mut save.as_slice()).unwrap();
+        assert_eq!(cartridge.read_ram(0xA000), 0x42);
+        assert_eq!(cartridge.ram(), save.as_slice());
+    }
+
+    #[test]
+    fn sizes_in_bytes_and_banks() {
+        assert_eq!(RomSize::Size32KiB.bytes(), 32 * 1024);
+        assert_eq!(RomSize::Size32KiB.num_banks(), 2);
+        assert_eq!(RomSize::Size1MiB.bytes(), 1024 * 1024);
+        assert_eq!(RomSize::Size1MiB.num_banks(), 64);
+        assert_eq!(RomSize::Size8MiB.num_banks(), 512);
+
+        assert_eq!(RamSize::NoRam.bytes(), 0);
+        assert_eq!(RamSize::NoRam.num_banks(), 0);
+        assert_eq!(RamSize::Size8KiB.num_banks(), 1);
+        assert_eq!(RamSize::Size32KiB.bytes(), 32 * 1024);
+        assert_eq!(RamSize::Size32KiB.num_banks(), 4);
+        assert_eq!(RamSize::Size128KiB.num_banks(), 16);
+    }
+
+    #[test]
+    fn rom_length_must_match_header() {
+        let mut rom = vec![0u8; BANK_SIZE * 4];
+        rom[0x0148] = 0x01; // 64 KiB
+
+        let cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+        assert_eq!(cartridge.num_rom_banks(), 4);
+
+        // Truncated to 32 KiB
+        assert!(matches!(
+            Cartridge::read(&mut &rom[..BANK_SIZE * 2]),
+            Err(Error::RomSizeMismatch {
+                expected: 0x10000,
+                found: 0x8000
+            })
+        ));
+
+        // Not even a whole number of banks
+        assert!(matches!(
+            Cartridge::read(&mut &rom[..BANK_SIZE * 3 + 0x100]),
+            Err(Error::RomSizeMismatch {
+                expected: 0x10000,
+                found: 0xC100
+            })
+        ));
+    }
+
+    #[test]
+    fn supported_types_are_the_ones_that_load() {
+        assert!(CartridgeType::RomOnly.is_supported());
+        assert!(!CartridgeType::Mbc6.is_supported());
+
+        for type_byte in 0..=0xFF {
+            let Ok(cartridge_type) = CartridgeType::try_from(type_byte) else {
+                continue;
+            };
+
+            let mut rom = vec![0u8; BANK_SIZE * 2];
+            rom[0x0147] = type_byte;
+
+            assert_eq!(
+                Cartridge::read(&mut rom.as_slice()).is_ok(),
+                cartridge_type.is_supported(),
+                "{:?}",
+                cartridge_type
+            );
+        }
     }
 }
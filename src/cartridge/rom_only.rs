@@ -0,0 +1,39 @@
+use super::mbc::Mbc;
+
+/// A cartridge without a mapper, which has just two ROM banks and no RAM
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RomOnly;
+
+impl Mbc for RomOnly {
+    fn write_u8(&mut self, _address: u16, _data: u8) {}
+
+    fn rom_banks(&self, _num_banks: usize) -> (usize, usize) {
+        (0, 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{mbc::Mbc, BANK_SIZE},
+        RomOnly,
+    };
+
+    #[test]
+    fn maps_both_banks_and_ignores_writes() {
+        let banks = [[0x11; BANK_SIZE], [0x22; BANK_SIZE]];
+        let mut ram = [];
+        let mut mbc: Box<dyn Mbc> = Box::new(RomOnly);
+
+        assert_eq!(mbc.read(0x0000, &banks, &ram), 0x11);
+        assert_eq!(mbc.read(0x3FFF, &banks, &ram), 0x11);
+        assert_eq!(mbc.read(0x4000, &banks, &ram), 0x22);
+        assert_eq!(mbc.read(0x7FFF, &banks, &ram), 0x22);
+
+        // There are no registers to bank with, and no RAM
+        mbc.write(0x2000, 0x05, &mut ram);
+        mbc.write(0xA000, 0x42, &mut ram);
+        assert_eq!(mbc.read(0x4000, &banks, &ram), 0x22);
+        assert_eq!(mbc.read(0xA000, &banks, &ram), 0xFF);
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    cell::Cell,
+    rc::Rc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// A source of wall clock time, so that anything timed in real time rather than emulated cycles
+/// can be driven deterministically in tests
+pub trait Clock {
+    /// The current time, as the time since the Unix epoch
+    fn now(&self) -> Duration;
+}
+
+/// The system's real time clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        // A system clock set before 1970 is treated as being at the epoch
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+}
+
+/// A clock that only moves when told to
+///
+/// Clones share the same time, so a test can keep one and hand another to the emulator.
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    now: Rc<Cell<Duration>>,
+}
+
+impl MockClock {
+    pub fn new(now: Duration) -> Self {
+        Self {
+            now: Rc::new(Cell::new(now)),
+        }
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.now.set(now);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Clock, MockClock};
+
+    #[test]
+    fn mock_clock_clones_share_time() {
+        let clock = MockClock::new(Duration::from_secs(100));
+        let shared = clock.clone();
+
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(shared.now(), Duration::from_millis(101_500));
+
+        shared.set(Duration::ZERO);
+        assert_eq!(clock.now(), Duration::ZERO);
+    }
+}
@@ -174,7 +174,7 @@ impl Decoder {
                 Instruction::Push(r16stk)
             }
             Opcode::Prefix => {
-                let prefixed_byte = bus.read_u8(ip + 1)?;
+                let prefixed_byte = bus.read_u8(ip.wrapping_add(1))?;
                 let prefixed = Prefixed::try_from(prefixed_byte)
                     .map_err(|_| Error::InvalidInstruction(ip, prefixed_byte))?;
 
@@ -323,16 +323,38 @@ impl Decoder {
     }
 
     fn read_imm8(&self, bus: &Bus, ip: u16) -> Result<Imm8, Error> {
-        let value = bus.read_u8(ip + 1)?;
+        let value = bus.read_u8(ip.wrapping_add(1))?;
         Ok(Imm8::from(value))
     }
 
     fn read_imm16(&self, bus: &Bus, ip: u16) -> Result<Imm16, Error> {
-        let value = bus.read_u16(ip + 1)?;
+        let value = bus.read_u16(ip.wrapping_add(1))?;
         Ok(Imm16::from(value))
     }
 }
 
+/// Every byte's opcode, or None if the byte is not a valid instruction
+const OPCODE_TABLE: [Option<Opcode>; 256] = {
+    let mut table = [None; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = Opcode::decode(i as u8);
+        i += 1;
+    }
+    table
+};
+
+/// Every byte's opcode when following the 0xCB prefix
+const PREFIXED_TABLE: [Option<Prefixed>; 256] = {
+    let mut table = [None; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = Prefixed::decode(i as u8);
+        i += 1;
+    }
+    table
+};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Opcode {
     // Block 0
@@ -420,11 +442,11 @@ pub enum Prefixed {
     Set,
 }
 
-impl TryFrom<u8> for Opcode {
-    type Error = ();
-
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        Ok(if value == 0 {
+impl Opcode {
+    /// Decodes a byte by matching it against each bit pattern in turn, which is used to build the
+    /// lookup table at compile time
+    const fn decode(value: u8) -> Option<Self> {
+        Some(if value == 0 {
             Opcode::Nop
         } else if value == 0b0111_0110 {
             Opcode::Halt
@@ -551,16 +573,24 @@ impl TryFrom<u8> for Opcode {
         } else if value == 0b1111_1011 {
             Opcode::Ei
         } else {
-            return Err(());
+            return None;
         })
     }
 }
 
-impl TryFrom<u8> for Prefixed {
+impl TryFrom<u8> for Opcode {
     type Error = ();
 
     fn try_from(value: u8) -> Result<Self, Self::Error> {
-        Ok(if value & 0b1111_1000 == 0b0000_0000 {
+        OPCODE_TABLE[value as usize].ok_or(())
+    }
+}
+
+impl Prefixed {
+    /// Decodes a byte by matching it against each bit pattern in turn, which is used to build the
+    /// lookup table at compile time
+    const fn decode(value: u8) -> Option<Self> {
+        Some(if value & 0b1111_1000 == 0b0000_0000 {
             Prefixed::Rlc
         } else if value & 0b1111_1000 == 0b0000_1000 {
             Prefixed::Rrc
@@ -583,7 +613,99 @@ impl TryFrom<u8> for Prefixed {
         } else if value & 0b1100_0000 == 0b1100_0000 {
             Prefixed::Set
         } else {
-            return Err(());
+            return None;
         })
     }
 }
+
+impl TryFrom<u8> for Prefixed {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        PREFIXED_TABLE[value as usize].ok_or(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        boot::DEFAULT_BOOT_ROM,
+        bus::Bus,
+        cartridge::Cartridge,
+        cpu::{
+            error::Error,
+            execution_state::ExecutionState,
+            instruction::{Instruction, Register16},
+        },
+    };
+
+    use super::{Decoder, Opcode, Prefixed};
+
+    #[test]
+    fn immediate_fetch_wraps_around_address_space() {
+        let mut bus = Bus::new(DEFAULT_BOOT_ROM, Cartridge::empty());
+        // ld bc, imm16 in the last byte of high RAM, the immediate spans 0xFFFF and 0x0000
+        bus.write_u8(0xFFFE, 0x01).unwrap();
+
+        let mut state = ExecutionState::new();
+        state.set_instruction_pointer(0xFFFE);
+
+        let instruction = Decoder::new().decode_one(&state, &bus).unwrap();
+
+        let Instruction::LdReg16(Register16::Bc, imm16) = instruction else {
+            panic!("Decoded unexpected instruction {:?}", instruction);
+        };
+        // The upper byte comes from the first byte of the boot ROM
+        assert_eq!(u16::from(imm16) >> 8, 0x31);
+    }
+
+    #[test]
+    fn read_u16_wraps_around_address_space() {
+        let bus = Bus::new(DEFAULT_BOOT_ROM, Cartridge::empty());
+
+        let value = bus.read_u16(0xFFFF).unwrap();
+        assert_eq!(value >> 8, 0x31);
+    }
+
+    #[test]
+    fn lookup_tables_match_bit_patterns() {
+        for byte in 0..=255u8 {
+            assert_eq!(
+                Opcode::try_from(byte).ok(),
+                Opcode::decode(byte),
+                "{:02x}",
+                byte
+            );
+            assert_eq!(
+                Prefixed::try_from(byte).ok(),
+                Prefixed::decode(byte),
+                "cb {:02x}",
+                byte
+            );
+        }
+
+        // The 11 unused opcodes on the Game Boy
+        let invalid: Vec<u8> = (0..=255u8)
+            .filter(|byte| Opcode::try_from(*byte).is_err())
+            .collect();
+        assert_eq!(
+            invalid,
+            vec![0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD]
+        );
+        assert!((0..=255u8).all(|byte| Prefixed::try_from(byte).is_ok()));
+    }
+
+    #[test]
+    fn invalid_opcode_error_has_address_and_byte() {
+        let mut bus = Bus::new(DEFAULT_BOOT_ROM, Cartridge::empty());
+        bus.write_u8(0xC123, 0xFC).unwrap();
+
+        let mut state = ExecutionState::new();
+        state.set_instruction_pointer(0xC123);
+
+        let error = Decoder::new().decode_one(&state, &bus).unwrap_err();
+
+        assert_eq!(error, Error::InvalidInstruction(0xC123, 0xFC));
+        assert_eq!(format!("{:?}", error), "InvalidInstruction(0xc123, 0xfc)");
+    }
+}
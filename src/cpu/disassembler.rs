@@ -0,0 +1,213 @@
+use crate::symbols::SymbolTable;
+
+use super::instruction::{
+    Condition, Imm16, Imm8, Instruction, Register16, Register16Memory, Register16Stack, Register8,
+};
+
+/// Formats an instruction in RGBDS syntax, given the address it was decoded from
+///
+/// Jump and call targets with a label in `symbols` are shown as the label instead of an address,
+/// looking up targets in 0x4000-0x7FFF in `rom_bank`.
+pub fn disassemble(
+    instruction: &Instruction,
+    address: u16,
+    symbols: &SymbolTable,
+    rom_bank: usize,
+) -> String {
+    let target = |target: u16| match symbols.label_at(target, rom_bank) {
+        Some(label) => label.to_string(),
+        None => format!("${:04X}", target),
+    };
+    let relative_target = |offset: Imm8| {
+        let next = address.wrapping_add(instruction.length());
+        target(next.wrapping_add_signed(i8::from(offset) as i16))
+    };
+
+    match *instruction {
+        Instruction::Nop => "nop".to_string(),
+        Instruction::LdReg16(r16, imm16) => format!("ld {}, {}", r16_name(r16), imm16_text(imm16)),
+        Instruction::LdMemA(r16mem) => format!("ld [{}], a", r16_memory_name(r16mem)),
+        Instruction::LdAMem(r16mem) => format!("ld a, [{}]", r16_memory_name(r16mem)),
+        Instruction::LdImm16Sp(imm16) => format!("ld [{}], sp", imm16_text(imm16)),
+        Instruction::Inc16(r16) => format!("inc {}", r16_name(r16)),
+        Instruction::Dec16(r16) => format!("dec {}", r16_name(r16)),
+        Instruction::AddHl(r16) => format!("add hl, {}", r16_name(r16)),
+        Instruction::Inc8(r8) => format!("inc {}", r8_name(r8)),
+        Instruction::Dec8(r8) => format!("dec {}", r8_name(r8)),
+        Instruction::LdReg8Imm(r8, imm8) => format!("ld {}, {}", r8_name(r8), imm8_text(imm8)),
+        Instruction::Rlca => "rlca".to_string(),
+        Instruction::Rrca => "rrca".to_string(),
+        Instruction::Rla => "rla".to_string(),
+        Instruction::Rra => "rra".to_string(),
+        Instruction::Daa => "daa".to_string(),
+        Instruction::Cpl => "cpl".to_string(),
+        Instruction::Scf => "scf".to_string(),
+        Instruction::Ccf => "ccf".to_string(),
+        Instruction::JrImm(imm8) => format!("jr {}", relative_target(imm8)),
+        Instruction::JrCond(cond, imm8) => {
+            format!("jr {}, {}", condition_name(cond), relative_target(imm8))
+        }
+        Instruction::Stop => "stop".to_string(),
+        Instruction::LdReg8Reg8(dest, src) => format!("ld {}, {}", r8_name(dest), r8_name(src)),
+        Instruction::Halt => "halt".to_string(),
+        Instruction::AddReg8(r8) => format!("add a, {}", r8_name(r8)),
+        Instruction::AdcReg8(r8) => format!("adc a, {}", r8_name(r8)),
+        Instruction::SubReg8(r8) => format!("sub a, {}", r8_name(r8)),
+        Instruction::SbcReg8(r8) => format!("sbc a, {}", r8_name(r8)),
+        Instruction::AndReg8(r8) => format!("and a, {}", r8_name(r8)),
+        Instruction::XorReg8(r8) => format!("xor a, {}", r8_name(r8)),
+        Instruction::OrReg8(r8) => format!("or a, {}", r8_name(r8)),
+        Instruction::CpReg8(r8) => format!("cp a, {}", r8_name(r8)),
+        Instruction::AddImm8(imm8) => format!("add a, {}", imm8_text(imm8)),
+        Instruction::AdcImm8(imm8) => format!("adc a, {}", imm8_text(imm8)),
+        Instruction::SubImm8(imm8) => format!("sub a, {}", imm8_text(imm8)),
+        Instruction::SbcImm8(imm8) => format!("sbc a, {}", imm8_text(imm8)),
+        Instruction::AndImm8(imm8) => format!("and a, {}", imm8_text(imm8)),
+        Instruction::XorImm8(imm8) => format!("xor a, {}", imm8_text(imm8)),
+        Instruction::OrImm8(imm8) => format!("or a, {}", imm8_text(imm8)),
+        Instruction::CpImm8(imm8) => format!("cp a, {}", imm8_text(imm8)),
+        Instruction::RetCond(cond) => format!("ret {}", condition_name(cond)),
+        Instruction::Ret => "ret".to_string(),
+        Instruction::Reti => "reti".to_string(),
+        Instruction::JpCond(cond, imm16) => {
+            format!("jp {}, {}", condition_name(cond), target(imm16.into()))
+        }
+        Instruction::JpImm(imm16) => format!("jp {}", target(imm16.into())),
+        Instruction::JpHl => "jp hl".to_string(),
+        Instruction::CallCond(cond, imm16) => {
+            format!("call {}, {}", condition_name(cond), target(imm16.into()))
+        }
+        Instruction::CallImm(imm16) => format!("call {}", target(imm16.into())),
+        Instruction::Rst(tgt) => format!("rst {}", target(tgt.into())),
+        Instruction::Pop(r16stk) => format!("pop {}", r16_stack_name(r16stk)),
+        Instruction::Push(r16stk) => format!("push {}", r16_stack_name(r16stk)),
+        Instruction::LdhMemA => "ldh [c], a".to_string(),
+        Instruction::LdhImmA(imm8) => format!("ldh [{}], a", high_address_text(imm8)),
+        Instruction::LdImmA(imm16) => format!("ld [{}], a", imm16_text(imm16)),
+        Instruction::LdhAMem => "ldh a, [c]".to_string(),
+        Instruction::LdhAImm(imm8) => format!("ldh a, [{}]", high_address_text(imm8)),
+        Instruction::LdAImm(imm16) => format!("ld a, [{}]", imm16_text(imm16)),
+        Instruction::AddSp(imm8) => format!("add sp, {}", i8::from(imm8)),
+        Instruction::LdHlSpImm8(imm8) => format!("ld hl, sp{:+}", i8::from(imm8)),
+        Instruction::LdSpHl => "ld sp, hl".to_string(),
+        Instruction::Di => "di".to_string(),
+        Instruction::Ei => "ei".to_string(),
+        Instruction::Rlc(r8) => format!("rlc {}", r8_name(r8)),
+        Instruction::Rrc(r8) => format!("rrc {}", r8_name(r8)),
+        Instruction::Rl(r8) => format!("rl {}", r8_name(r8)),
+        Instruction::Rr(r8) => format!("rr {}", r8_name(r8)),
+        Instruction::Sla(r8) => format!("sla {}", r8_name(r8)),
+        Instruction::Sra(r8) => format!("sra {}", r8_name(r8)),
+        Instruction::Swap(r8) => format!("swap {}", r8_name(r8)),
+        Instruction::Srl(r8) => format!("srl {}", r8_name(r8)),
+        Instruction::Bit(bit, r8) => format!("bit {}, {}", u8::from(bit), r8_name(r8)),
+        Instruction::Res(bit, r8) => format!("res {}, {}", u8::from(bit), r8_name(r8)),
+        Instruction::Set(bit, r8) => format!("set {}, {}", u8::from(bit), r8_name(r8)),
+    }
+}
+
+fn imm8_text(imm8: Imm8) -> String {
+    format!("${:02X}", u8::from(imm8))
+}
+
+fn imm16_text(imm16: Imm16) -> String {
+    format!("${:04X}", u16::from(imm16))
+}
+
+fn high_address_text(imm8: Imm8) -> String {
+    format!("${:04X}", 0xFF00 | u16::from(imm8))
+}
+
+fn r8_name(r8: Register8) -> &'static str {
+    match r8 {
+        Register8::A => "a",
+        Register8::B => "b",
+        Register8::C => "c",
+        Register8::D => "d",
+        Register8::E => "e",
+        Register8::H => "h",
+        Register8::L => "l",
+        Register8::HlIndirect => "[hl]",
+    }
+}
+
+fn r16_name(r16: Register16) -> &'static str {
+    match r16 {
+        Register16::Bc => "bc",
+        Register16::De => "de",
+        Register16::Hl => "hl",
+        Register16::Sp => "sp",
+    }
+}
+
+fn r16_stack_name(r16stk: Register16Stack) -> &'static str {
+    match r16stk {
+        Register16Stack::Bc => "bc",
+        Register16Stack::De => "de",
+        Register16Stack::Hl => "hl",
+        Register16Stack::Af => "af",
+    }
+}
+
+fn r16_memory_name(r16mem: Register16Memory) -> &'static str {
+    match r16mem {
+        Register16Memory::Bc => "bc",
+        Register16Memory::De => "de",
+        Register16Memory::Hli => "hl+",
+        Register16Memory::Hld => "hl-",
+    }
+}
+
+fn condition_name(cond: Condition) -> &'static str {
+    match cond {
+        Condition::Nz => "nz",
+        Condition::Z => "z",
+        Condition::Nc => "nc",
+        Condition::C => "c",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cpu::instruction::{Condition, Instruction, Register16Memory, Register8},
+        symbols::SymbolTable,
+    };
+
+    use super::disassemble;
+
+    #[test]
+    fn operands() {
+        let symbols = SymbolTable::new();
+        let cases = [
+            (Instruction::LdMemA(Register16Memory::Hli), "ld [hl+], a"),
+            (
+                Instruction::LdReg8Reg8(Register8::B, Register8::HlIndirect),
+                "ld b, [hl]",
+            ),
+            (Instruction::LdhImmA(0x40.into()), "ldh [$FF40], a"),
+            (Instruction::LdHlSpImm8(0xFE.into()), "ld hl, sp-2"),
+            (Instruction::Bit(7.into(), Register8::H), "bit 7, h"),
+            (Instruction::CallImm(0x1234.into()), "call $1234"),
+        ];
+
+        for (instruction, text) in cases {
+            assert_eq!(disassemble(&instruction, 0x0150, &symbols, 1), text);
+        }
+    }
+
+    #[test]
+    fn targets_use_labels() {
+        let mut symbols = SymbolTable::new();
+        symbols.insert(0, 0x0150, "Loop");
+        symbols.insert(0, 0x0038, "Crash");
+
+        // Relative jumps are from the end of the instruction
+        let jr = Instruction::JrCond(Condition::Nz, 0xFC.into());
+        assert_eq!(disassemble(&jr, 0x0152, &symbols, 1), "jr nz, Loop");
+        assert_eq!(disassemble(&jr, 0x0200, &symbols, 1), "jr nz, $01FE");
+
+        let rst = Instruction::Rst(0x38.into());
+        assert_eq!(disassemble(&rst, 0x0000, &symbols, 1), "rst Crash");
+    }
+}
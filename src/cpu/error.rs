@@ -2,7 +2,6 @@ use std::fmt::Debug;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Error {
-    BootRomReadFailure,
     InvalidInstruction(u16, u8),
     MemoryReadFault(u16),
     MemoryWriteFault(u16, u8),
@@ -11,7 +10,6 @@ pub enum Error {
 impl Debug for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::BootRomReadFailure => write!(f, "BootRomReadFailure"),
             Self::InvalidInstruction(addr, byte) => {
                 write!(f, "InvalidInstruction(0x{:04x}, 0x{:02x})", addr, byte)
             }
@@ -22,3 +20,24 @@ impl Debug for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+
+    #[test]
+    fn debug_formats_addresses_and_bytes_as_hex() {
+        assert_eq!(
+            format!("{:?}", Error::InvalidInstruction(0x0150, 0xDD)),
+            "InvalidInstruction(0x0150, 0xdd)"
+        );
+        assert_eq!(
+            format!("{:?}", Error::MemoryReadFault(0xFEA0)),
+            "MemoryReadFault(0xfea0)"
+        );
+        assert_eq!(
+            format!("{:?}", Error::MemoryWriteFault(0x9800, 0x7F)),
+            "MemoryWriteFault(0x9800, 0x7f)"
+        );
+    }
+}
@@ -484,3 +484,89 @@ impl std::fmt::Debug for Imm16 {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        boot::DEFAULT_BOOT_ROM,
+        bus::Bus,
+        cartridge::Cartridge,
+        cpu::{decoder::Decoder, execution_state::ExecutionState},
+    };
+
+    use super::Instruction;
+
+    /// M-cycles for each unprefixed opcode, with conditional branches not taken
+    ///
+    /// Unused opcodes and the 0xCB prefix are 0.
+    #[rustfmt::skip]
+    const BASE_CYCLES: [usize; 256] = [
+        1, 3, 2, 2, 1, 1, 2, 1, 5, 2, 2, 2, 1, 1, 2, 1, // 0x00
+        1, 3, 2, 2, 1, 1, 2, 1, 3, 2, 2, 2, 1, 1, 2, 1, // 0x10
+        2, 3, 2, 2, 1, 1, 2, 1, 2, 2, 2, 2, 1, 1, 2, 1, // 0x20
+        2, 3, 2, 2, 3, 3, 3, 1, 2, 2, 2, 2, 1, 1, 2, 1, // 0x30
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, // 0x40
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, // 0x50
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, // 0x60
+        2, 2, 2, 2, 2, 2, 1, 2, 1, 1, 1, 1, 1, 1, 2, 1, // 0x70
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, // 0x80
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, // 0x90
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, // 0xA0
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, // 0xB0
+        2, 3, 3, 4, 3, 4, 2, 4, 2, 4, 3, 0, 3, 6, 2, 4, // 0xC0
+        2, 3, 3, 0, 3, 4, 2, 4, 2, 4, 3, 0, 3, 0, 2, 4, // 0xD0
+        3, 3, 2, 0, 0, 4, 2, 4, 4, 1, 4, 0, 0, 0, 2, 4, // 0xE0
+        3, 3, 2, 1, 0, 4, 2, 4, 3, 2, 4, 1, 0, 0, 2, 4, // 0xF0
+    ];
+
+    fn decode(bytes: &[u8]) -> Instruction {
+        let mut bus = Bus::new(DEFAULT_BOOT_ROM, Cartridge::empty());
+        for (i, byte) in bytes.iter().enumerate() {
+            bus.write_u8(0xC000 + i as u16, *byte).unwrap();
+        }
+
+        let mut state = ExecutionState::new();
+        state.set_instruction_pointer(0xC000);
+
+        Decoder::new().decode_one(&state, &bus).unwrap()
+    }
+
+    #[test]
+    fn base_cycles_match_timing_chart() {
+        for (opcode, &cycles) in BASE_CYCLES.iter().enumerate() {
+            if cycles == 0 {
+                continue;
+            }
+
+            let instruction = decode(&[opcode as u8]);
+            assert_eq!(
+                instruction.base_num_cycles(),
+                cycles,
+                "{:02x} {:?}",
+                opcode,
+                instruction
+            );
+        }
+    }
+
+    #[test]
+    fn prefixed_base_cycles_match_timing_chart() {
+        for opcode in 0..=255u8 {
+            // Only BIT doesn't write its result back to [hl]
+            let cycles = match (opcode & 0x07, opcode >> 6) {
+                (6, 1) => 3,
+                (6, _) => 4,
+                _ => 2,
+            };
+
+            let instruction = decode(&[0xCB, opcode]);
+            assert_eq!(
+                instruction.base_num_cycles(),
+                cycles,
+                "cb {:02x} {:?}",
+                opcode,
+                instruction
+            );
+        }
+    }
+}
@@ -4,17 +4,17 @@ use execution_state::ExecutionState;
 use instruction::{
     Condition, Instruction, Register16, Register16Memory, Register16Stack, Register8,
 };
+use write_log::{WriteLog, WriteLogEntry};
 
-use crate::{
-    bus::Bus,
-    io::{dma::DMA_TRANSFER_CYCLES_LENGTH, interrupts::Interrupt},
-};
+use crate::{bus::Bus, io::interrupts::Interrupt};
 
 pub mod alu;
 pub mod decoder;
+pub mod disassembler;
 pub mod error;
 pub mod execution_state;
 pub mod instruction;
+pub mod write_log;
 
 pub struct Cpu {
     state: ExecutionState,
@@ -23,12 +23,19 @@ pub struct Cpu {
     after_ei: bool,
     interrupt_enable_next: bool,
     halted: bool,
-    breakpoints_enabled: bool,
+    debug_instructions: bool,
     hit_breakpoint_instruction: bool,
+    debug_messages: Vec<String>,
+    instruction_count: u64,
+    write_log: Option<WriteLog>,
+    stack_checks: bool,
+    stack_out_of_range: bool,
+    stack_warnings: Vec<String>,
 }
 
 impl Cpu {
-    pub fn new(bus: Bus, enable_breakpoints: bool) -> Self {
+    /// With `debug_instructions`, `LD B,B` acts as a breakpoint and `LD D,D` logs a debug message
+    pub fn new(bus: Bus, debug_instructions: bool) -> Self {
         Self {
             state: ExecutionState::new(),
             bus,
@@ -36,8 +43,14 @@ impl Cpu {
             after_ei: false,
             interrupt_enable_next: false,
             halted: false,
-            breakpoints_enabled: enable_breakpoints,
+            debug_instructions,
             hit_breakpoint_instruction: false,
+            debug_messages: Vec::new(),
+            instruction_count: 0,
+            write_log: None,
+            stack_checks: false,
+            stack_out_of_range: false,
+            stack_warnings: Vec::new(),
         }
     }
 
@@ -45,12 +58,86 @@ impl Cpu {
         &self.state
     }
 
+    pub fn execution_state_mut(&mut self) -> &mut ExecutionState {
+        &mut self.state
+    }
+
+    /// The number of instructions executed since the CPU was created, not counting cycles spent
+    /// halted
+    pub fn instruction_count(&self) -> u64 {
+        self.instruction_count
+    }
+
+    /// Decodes the instruction at an address without executing it
+    pub fn decode_at(&self, address: u16) -> Result<Instruction, Error> {
+        let mut state = self.state;
+        state.set_instruction_pointer(address);
+
+        self.decoder.decode_one(&state, &self.bus)
+    }
+
+    pub fn set_debug_instructions(&mut self, enabled: bool) {
+        self.debug_instructions = enabled;
+    }
+
+    /// With `enabled`, pushes and pops that move the stack pointer out of RAM log a warning
+    pub fn set_stack_checks(&mut self, enabled: bool) {
+        self.stack_checks = enabled;
+    }
+
+    /// Drains the warnings logged by stack checks so far
+    pub fn take_stack_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.stack_warnings)
+    }
+
+    /// Sets the interrupt master enable flag straight away, cancelling the delayed enable of an
+    /// `EI` that has just executed
+    pub fn set_ime(&mut self, enabled: bool) {
+        self.state.set_interrupts_enabled(enabled);
+        self.after_ei = false;
+        self.interrupt_enable_next = false;
+    }
+
+    /// Whether the CPU is waiting in a `HALT` for an interrupt to be requested
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Whether the last instruction executed was an `LD B,B` breakpoint
     pub fn hit_breakpoint_instruction(&self) -> bool {
-        self.breakpoints_enabled & self.hit_breakpoint_instruction
+        self.debug_instructions & self.hit_breakpoint_instruction
+    }
+
+    /// Drains the messages logged with `LD D,D` so far
+    pub fn take_debug_messages(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.debug_messages)
+    }
+
+    /// Starts recording the last `capacity` memory writes, clearing any already recorded
+    pub fn enable_write_log(&mut self, capacity: usize) {
+        self.write_log = Some(WriteLog::new(capacity));
+    }
+
+    pub fn disable_write_log(&mut self) {
+        self.write_log = None;
+    }
+
+    /// How many writes are kept, if writes are being recorded
+    pub fn write_log_capacity(&self) -> Option<usize> {
+        self.write_log.as_ref().map(WriteLog::capacity)
+    }
+
+    /// The most recent memory writes, oldest first, which is empty unless the write log is enabled
+    pub fn recent_writes(&self) -> &[WriteLogEntry] {
+        match &self.write_log {
+            Some(write_log) => write_log.entries(),
+            None => &[],
+        }
     }
 
     pub fn step(&mut self) -> Result<usize, Error> {
         let mut cycles = 0;
+        self.hit_breakpoint_instruction = false;
 
         if self.interrupt_enable_next {
             self.state.set_interrupts_enabled(true);
@@ -66,6 +153,7 @@ impl Cpu {
             if self.detect_interrupt().is_some() {
                 self.halted = false;
             } else {
+                self.bus.step_dma(1)?;
                 return Ok(1);
             }
         }
@@ -84,6 +172,7 @@ impl Cpu {
         }
 
         let current_instruction = self.decoder.decode_one(&self.state, &self.bus)?;
+        self.instruction_count += 1;
         let mut next_instruction_address = self
             .state
             .instruction_pointer()
@@ -103,8 +192,7 @@ impl Cpu {
                 self.update_r8(Register8::A, new_a)?;
             }
             Instruction::LdImm16Sp(imm16) => {
-                self.bus
-                    .write_u16(imm16.into(), self.state.stack_pointer())?;
+                self.write_u16(imm16.into(), self.state.stack_pointer())?;
             }
             Instruction::Inc16(r16) => {
                 self.update_r16(r16, self.inc_u16(self.get_r16(r16)));
@@ -162,10 +250,10 @@ impl Cpu {
             }
             Instruction::JrImm(imm8) => {
                 next_instruction_address =
-                    self.rel_jump_dest(imm8.into(), current_instruction.length());
+                    Self::rel_jump_dest(next_instruction_address, imm8.into());
             }
             Instruction::JrCond(cond, imm8) => {
-                let dest = self.rel_jump_dest(imm8.into(), current_instruction.length());
+                let dest = Self::rel_jump_dest(next_instruction_address, imm8.into());
 
                 if self.is_condition_met(cond) {
                     next_instruction_address = dest;
@@ -184,6 +272,14 @@ impl Cpu {
                 if (dest == src) & (dest == Register8::B) {
                     self.hit_breakpoint_instruction = true;
                 }
+
+                if self.debug_instructions & (dest == src) & (dest == Register8::D) {
+                    let address = self.state.instruction_pointer();
+
+                    if let Some(message) = self.read_debug_message(address) {
+                        self.debug_messages.push(message);
+                    }
+                }
             }
             Instruction::Halt => {
                 self.halted = true;
@@ -295,16 +391,16 @@ impl Cpu {
             Instruction::LdhMemA => {
                 let val = self.get_r8(Register8::A)?;
                 let addr = 0xFF00 + (self.get_r8(Register8::C)? as u16);
-                self.bus.write_u8(addr, val)?;
+                self.write_u8(addr, val)?;
             }
             Instruction::LdhImmA(imm8) => {
                 let val = self.get_r8(Register8::A)?;
                 let addr = 0xFF00 + u16::from(imm8);
-                self.bus.write_u8(addr, val)?;
+                self.write_u8(addr, val)?;
             }
             Instruction::LdImmA(imm16) => {
                 let val = self.get_r8(Register8::A)?;
-                self.bus.write_u8(u16::from(imm16), val)?;
+                self.write_u8(u16::from(imm16), val)?;
             }
             Instruction::LdhAMem => {
                 let addr = 0xFF00 + (self.get_r8(Register8::C)? as u16);
@@ -376,33 +472,11 @@ impl Cpu {
 
         self.state.set_instruction_pointer(next_instruction_address);
 
-        if self.step_dma(cycles) {
-            self.do_dma_transfer()?;
-        }
+        self.bus.step_dma(cycles)?;
 
         Ok(cycles)
     }
 
-    fn do_dma_transfer(&mut self) -> Result<(), Error> {
-        let source_address = self.bus().io().dma().full_source_address();
-
-        // Do the entire DMA transfer all at once, for simplicity
-        // The number of cycles is also the number of bytes
-        for i in 0..DMA_TRANSFER_CYCLES_LENGTH {
-            let source_addr = source_address + i;
-            let dest_addr = 0xFE00 + i;
-
-            let byte = self.bus.read_u8(source_addr)?;
-            self.bus.write_u8(dest_addr, byte)?;
-        }
-
-        Ok(())
-    }
-
-    fn step_dma(&mut self, cycles: usize) -> bool {
-        self.bus.io_mut().dma_mut().step(cycles)
-    }
-
     fn clear_requested_interrupt(&mut self, interrupt: Interrupt) {
         self.bus_mut()
             .io_mut()
@@ -432,24 +506,72 @@ impl Cpu {
         Ok(())
     }
 
+    /// Writes to the bus, recording the write if the write log is enabled
+    fn write_u8(&mut self, address: u16, value: u8) -> Result<(), Error> {
+        if let Some(write_log) = &mut self.write_log {
+            // The instruction pointer is only moved on once an instruction has finished
+            write_log.push(WriteLogEntry {
+                pc: self.state.instruction_pointer(),
+                address,
+                value,
+            });
+        }
+
+        self.bus.write_u8(address, value)
+    }
+
+    fn write_u16(&mut self, address: u16, value: u16) -> Result<(), Error> {
+        self.write_u8(address.wrapping_add(1), (value >> 8) as u8)?;
+        self.write_u8(address, (value & 0xFF) as u8)
+    }
+
     fn push_u16(&mut self, value: u16) -> Result<(), Error> {
         self.push_u8((value >> 8) as u8)?;
-        self.push_u8((value & 0xFF) as u8)
+        self.push_u8((value & 0xFF) as u8)?;
+        self.check_stack_pointer("push");
+
+        Ok(())
     }
 
     fn push_u8(&mut self, value: u8) -> Result<(), Error> {
         let new_sp = self.state.stack_pointer().wrapping_sub(1);
         self.state.set_stack_pointer(new_sp);
 
-        self.bus.write_u8(new_sp, value)
+        self.write_u8(new_sp, value)
     }
 
     fn pop_u16(&mut self) -> Result<u16, Error> {
         let lo = self.pop_u8()? as u16;
         let hi = self.pop_u8()? as u16;
+        self.check_stack_pointer("pop");
+
         Ok((hi << 8) | lo)
     }
 
+    /// Warns when the stack pointer leaves RAM, which usually means runaway recursion or
+    /// unbalanced pushes and pops
+    ///
+    /// Only the first move out of range is warned about, until the stack pointer is back in RAM.
+    fn check_stack_pointer(&mut self, operation: &str) {
+        if !self.stack_checks {
+            return;
+        }
+
+        let stack_pointer = self.state.stack_pointer();
+        let out_of_range = !plausible_stack_pointer(stack_pointer);
+
+        if out_of_range && !self.stack_out_of_range {
+            self.stack_warnings.push(format!(
+                "Stack pointer moved to {:04X} by {} at {:04X}",
+                stack_pointer,
+                operation,
+                self.state.instruction_pointer()
+            ));
+        }
+
+        self.stack_out_of_range = out_of_range;
+    }
+
     fn pop_u8(&mut self) -> Result<u8, Error> {
         let old_sp = self.state.stack_pointer();
 
@@ -468,9 +590,31 @@ impl Cpu {
         }
     }
 
-    fn rel_jump_dest(&self, offset: i8, instr_len: u16) -> u16 {
-        let address_after = self.state.instruction_pointer().wrapping_add(instr_len);
-        ((address_after as i32 + (offset as i32)) & 0xFFFF) as u16
+    /// Reads the inline message following an `LD D,D`, in the form used by bgb:
+    ///
+    /// ```text
+    /// ld d, d
+    /// jr .end
+    /// dw $6464, $0000
+    /// db "message"
+    /// .end:
+    /// ```
+    fn read_debug_message(&self, address: u16) -> Option<String> {
+        let byte = |offset: u16| self.bus.debug_read_u8(address.wrapping_add(offset));
+
+        if (byte(1) != 0x18) || (byte(3), byte(4), byte(5), byte(6)) != (0x64, 0x64, 0x00, 0x00) {
+            return None;
+        }
+
+        // The jump skips over the signature and the message
+        let length = u8::try_from((byte(2) as i8).saturating_sub(4)).ok()?;
+
+        Some((0..length as u16).map(|i| byte(7 + i) as char).collect())
+    }
+
+    /// Relative jumps are taken from the address of the instruction following the jump
+    fn rel_jump_dest(next_instruction_address: u16, offset: i8) -> u16 {
+        next_instruction_address.wrapping_add_signed(offset as i16)
     }
 
     fn update_r16_stack(&mut self, r16stk: Register16Stack, value: u16) {
@@ -494,13 +638,13 @@ impl Cpu {
     fn update_r16_mem_u8(&mut self, r16mem: Register16Memory, value: u8) -> Result<(), Error> {
         match r16mem {
             Register16Memory::Bc => {
-                self.bus.write_u8(self.state.reg_bc(), value)?;
+                self.write_u8(self.state.reg_bc(), value)?;
             }
             Register16Memory::De => {
-                self.bus.write_u8(self.state.reg_de(), value)?;
+                self.write_u8(self.state.reg_de(), value)?;
             }
             Register16Memory::Hli | Register16Memory::Hld => {
-                self.bus.write_u8(self.state.reg_hl(), value)?;
+                self.write_u8(self.state.reg_hl(), value)?;
             }
         }
 
@@ -583,7 +727,7 @@ impl Cpu {
                 self.state.set_reg_l(value);
             }
             Register8::HlIndirect => {
-                self.bus.write_u8(self.state.reg_hl(), value)?;
+                self.write_u8(self.state.reg_hl(), value)?;
             }
         }
         Ok(())
@@ -611,3 +755,198 @@ impl Cpu {
         &mut self.bus
     }
 }
+
+/// Whether a stack pointer is somewhere a stack could be, in cartridge RAM, work RAM or high RAM
+///
+/// 0xE000 is allowed too, since stacks are often placed at the top of work RAM, empty until the
+/// first push.
+fn plausible_stack_pointer(stack_pointer: u16) -> bool {
+    matches!(stack_pointer, 0xA000..=0xE000 | 0xFF80..=0xFFFE)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        boot::DEFAULT_BOOT_ROM,
+        bus::Bus,
+        cartridge::Cartridge,
+        cpu::{execution_state::Flags, Cpu},
+    };
+
+    fn cpu_with_program(address: u16, program: &[u8]) -> Cpu {
+        let mut cpu = Cpu::new(Bus::new(DEFAULT_BOOT_ROM, Cartridge::empty()), false);
+
+        for (i, byte) in program.iter().enumerate() {
+            cpu.bus_mut().write_u8(address + i as u16, *byte).unwrap();
+        }
+
+        cpu.execution_state_mut().set_instruction_pointer(address);
+        cpu
+    }
+
+    #[test]
+    fn rel_jump_dest_offsets() {
+        assert_eq!(Cpu::rel_jump_dest(0xC002, 0), 0xC002);
+        assert_eq!(Cpu::rel_jump_dest(0xC002, 127), 0xC081);
+        assert_eq!(Cpu::rel_jump_dest(0xC082, -128), 0xC002);
+        assert_eq!(Cpu::rel_jump_dest(0xC002, -2), 0xC000);
+        assert_eq!(Cpu::rel_jump_dest(0xFFFF, 1), 0x0000);
+        assert_eq!(Cpu::rel_jump_dest(0x0001, -2), 0xFFFF);
+    }
+
+    #[test]
+    fn jr_forward_max() {
+        // jr +127
+        let mut cpu = cpu_with_program(0xC000, &[0x18, 0x7F]);
+        cpu.step().unwrap();
+        assert_eq!(cpu.execution_state().instruction_pointer(), 0xC081);
+    }
+
+    #[test]
+    fn jr_backward_max() {
+        // jr -128
+        let mut cpu = cpu_with_program(0xC100, &[0x18, 0x80]);
+        cpu.step().unwrap();
+        assert_eq!(cpu.execution_state().instruction_pointer(), 0xC082);
+    }
+
+    #[test]
+    fn jr_to_self() {
+        // jr -2
+        let mut cpu = cpu_with_program(0xC000, &[0x18, 0xFE]);
+        cpu.step().unwrap();
+        assert_eq!(cpu.execution_state().instruction_pointer(), 0xC000);
+    }
+
+    #[test]
+    fn hl_indirect_operands_cost_memory_cycles() {
+        // ld hl, $C100 / inc b / inc [hl] / add a, b / add a, [hl] / rlc b / rlc [hl]
+        let mut cpu = cpu_with_program(
+            0xC000,
+            &[
+                0x21, 0x00, 0xC1, 0x04, 0x34, 0x80, 0x86, 0xCB, 0x00, 0xCB, 0x06,
+            ],
+        );
+        cpu.bus_mut().write_u8(0xC100, 0x41).unwrap();
+        cpu.step().unwrap();
+
+        // inc [hl] reads and writes back, add a, [hl] only reads
+        let cycles: Vec<usize> = (0..6).map(|_| cpu.step().unwrap()).collect();
+        assert_eq!(cycles, vec![1, 3, 1, 2, 2, 4]);
+        assert_eq!(cpu.bus().read_u8(0xC100).unwrap(), 0x84);
+    }
+
+    #[test]
+    fn jr_cond_taken_and_not_taken() {
+        // jr nz, +4 ; jr z, -128
+        let mut cpu = cpu_with_program(0xC100, &[0x20, 0x04, 0x28, 0x80]);
+
+        cpu.execution_state_mut().set_flags(Flags::zeros());
+        assert_eq!(cpu.step().unwrap(), 3);
+        assert_eq!(cpu.execution_state().instruction_pointer(), 0xC106);
+
+        cpu.execution_state_mut().set_instruction_pointer(0xC102);
+        assert_eq!(cpu.step().unwrap(), 2);
+        assert_eq!(cpu.execution_state().instruction_pointer(), 0xC104);
+
+        cpu.execution_state_mut().set_flags(Flags::just_zero());
+        cpu.execution_state_mut().set_instruction_pointer(0xC102);
+        cpu.step().unwrap();
+        assert_eq!(cpu.execution_state().instruction_pointer(), 0xC084);
+    }
+
+    #[test]
+    fn dma_from_work_ram() {
+        // ld a, $C1 ; ldh [$46], a ; jr -2
+        let mut cpu = cpu_with_program(0xFF80, &[0x3E, 0xC1, 0xE0, 0x46, 0x18, 0xFE]);
+
+        for i in 0..160u16 {
+            cpu.bus_mut().write_u8(0xC100 + i, i as u8 ^ 0x5A).unwrap();
+        }
+
+        for _ in 0..200 {
+            cpu.step().unwrap();
+        }
+
+        for i in 0..160u16 {
+            let byte = i as u8 ^ 0x5A;
+            // Only the upper nibble of the attribute flags is used on DMG
+            let expected = if i % 4 == 3 { byte & 0xF0 } else { byte };
+
+            assert_eq!(cpu.bus().read_u8(0xFE00 + i).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn dma_blocks_cpu_outside_high_ram() {
+        // ld a, $C1 ; ldh [$46], a ; ld a, [$C100] ; jr -2
+        let mut cpu = cpu_with_program(
+            0xFF80,
+            &[0x3E, 0xC1, 0xE0, 0x46, 0xFA, 0x00, 0xC1, 0x18, 0xFE],
+        );
+        cpu.bus_mut().write_u8(0xC100, 0x42).unwrap();
+
+        cpu.step().unwrap();
+        let mut cycles = cpu.step().unwrap();
+        assert!(cpu.bus().io().dma().transferring());
+
+        cycles += cpu.step().unwrap();
+        assert_eq!(cpu.execution_state().reg_a(), 0xFF);
+
+        while cpu.bus().io().dma().transferring() {
+            assert_eq!(cpu.bus().read_u8(0xC100).unwrap(), 0xFF);
+            cycles += cpu.step().unwrap();
+        }
+
+        assert!(cycles >= 160);
+        assert_eq!(cpu.bus().read_u8(0xC100).unwrap(), 0x42);
+        assert_eq!(cpu.bus().read_u8(0xFE00).unwrap(), 0x42);
+    }
+
+    #[test]
+    fn stack_overflow_out_of_ram_warns_once() {
+        // loop: push bc ; jr loop
+        let mut cpu = cpu_with_program(0xC000, &[0xC5, 0x18, 0xFD]);
+        cpu.execution_state_mut().set_stack_pointer(0xA004);
+        cpu.set_stack_checks(true);
+
+        // Two pushes fit, the third runs into VRAM
+        for _ in 0..4 {
+            cpu.step().unwrap();
+        }
+        assert!(cpu.take_stack_warnings().is_empty());
+
+        for _ in 0..6 {
+            cpu.step().unwrap();
+        }
+        assert_eq!(
+            cpu.take_stack_warnings(),
+            vec!["Stack pointer moved to 9FFE by push at C000".to_string()]
+        );
+    }
+
+    #[test]
+    fn stack_underflow_warns() {
+        // pop bc
+        let mut cpu = cpu_with_program(0xC000, &[0xC1]);
+        cpu.execution_state_mut().set_stack_pointer(0xFFFE);
+        cpu.set_stack_checks(true);
+
+        cpu.step().unwrap();
+
+        assert_eq!(
+            cpu.take_stack_warnings(),
+            vec!["Stack pointer moved to 0000 by pop at C000".to_string()]
+        );
+    }
+
+    #[test]
+    fn stack_checks_are_off_by_default() {
+        let mut cpu = cpu_with_program(0xC000, &[0xC1]);
+        cpu.execution_state_mut().set_stack_pointer(0xFFFE);
+
+        cpu.step().unwrap();
+
+        assert!(cpu.take_stack_warnings().is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+/// The most entries room is made for up front, larger logs grow as writes are made
+const MAX_PREALLOCATED_ENTRIES: usize = 4096;
+
+/// A memory write made by the CPU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteLogEntry {
+    /// The address of the instruction that made the write, or of the instruction interrupted when
+    /// the write was the return address pushed by an interrupt
+    pub pc: u16,
+    pub address: u16,
+    pub value: u8,
+}
+
+/// Keeps the most recent memory writes, dropping the oldest once `capacity` is reached
+pub struct WriteLog {
+    capacity: usize,
+    entries: Vec<WriteLogEntry>,
+}
+
+impl WriteLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Vec::with_capacity(capacity.saturating_mul(2).min(MAX_PREALLOCATED_ENTRIES)),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn push(&mut self, entry: WriteLogEntry) {
+        // Old entries are dropped in batches, so the recent ones can always be borrowed as one
+        // slice without moving them on every write
+        if self.entries.len() >= self.capacity.saturating_mul(2) {
+            self.entries.drain(..self.entries.len() - self.capacity);
+        }
+
+        self.entries.push(entry);
+    }
+
+    /// The logged writes, oldest first
+    pub fn entries(&self) -> &[WriteLogEntry] {
+        &self.entries[self.entries.len().saturating_sub(self.capacity)..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WriteLog, WriteLogEntry};
+
+    fn entry(value: u8) -> WriteLogEntry {
+        WriteLogEntry {
+            pc: 0xC000,
+            address: 0xD000,
+            value,
+        }
+    }
+
+    #[test]
+    fn keeps_only_most_recent_writes() {
+        let mut log = WriteLog::new(3);
+
+        for value in 0..10 {
+            log.push(entry(value));
+
+            let values: Vec<u8> = log.entries().iter().map(|entry| entry.value).collect();
+            let expected: Vec<u8> = (value.saturating_sub(2)..=value).collect();
+            assert_eq!(values, expected);
+        }
+    }
+
+    #[test]
+    fn zero_capacity_keeps_nothing() {
+        let mut log = WriteLog::new(0);
+        log.push(entry(1));
+
+        assert!(log.entries().is_empty());
+    }
+
+    #[test]
+    fn huge_capacity_is_not_allocated_up_front() {
+        let mut log = WriteLog::new(usize::MAX);
+        log.push(entry(1));
+
+        assert_eq!(log.capacity(), usize::MAX);
+        assert_eq!(log.entries(), [entry(1)]);
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::{BufRead, Write};
+
+use gameboy_emulator::Emulator;
+
+/// A command typed at the debugger prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Step,
+    Continue,
+    Registers,
+    Memory(u16),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+
+        match (words.next(), words.next()) {
+            (Some("step" | "s"), None) => Ok(Self::Step),
+            (Some("continue" | "c"), None) => Ok(Self::Continue),
+            (Some("regs" | "r"), None) => Ok(Self::Registers),
+            (Some("mem" | "m"), Some(address)) => Ok(Self::Memory(parse_address(address)?)),
+            _ => Err(format!("Unknown command: {}", line.trim())),
+        }
+    }
+}
+
+/// How execution should carry on once the prompt returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// Execute one instruction, then prompt again
+    Step,
+    /// Run until the next breakpoint
+    Continue,
+}
+
+/// Parses a hexadecimal address, with or without a leading `0x`
+pub fn parse_address(text: &str) -> Result<u16, String> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+
+    u16::from_str_radix(digits, 16).map_err(|_| format!("Invalid address: {}", text))
+}
+
+/// A line based debugger for stopping at breakpoints without the GUI
+pub struct Debugger<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Debugger<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+
+    /// Shows where execution stopped and reads commands until one of them resumes it
+    ///
+    /// Reaching the end of the input continues execution.
+    pub fn prompt(&mut self, emulator: &Emulator) -> std::io::Result<Resume> {
+        writeln!(self.output, "{}", emulator.execution_state())?;
+
+        loop {
+            write!(self.output, "> ")?;
+            self.output.flush()?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(Resume::Continue);
+            }
+
+            match Command::parse(&line) {
+                Ok(Command::Step) => return Ok(Resume::Step),
+                Ok(Command::Continue) => return Ok(Resume::Continue),
+                Ok(Command::Registers) => writeln!(self.output, "{}", emulator.execution_state())?,
+                Ok(Command::Memory(address)) => writeln!(
+                    self.output,
+                    "${:04X}: ${:02X}",
+                    address,
+                    emulator.peek_u8(address)
+                )?,
+                Err(e) => writeln!(self.output, "{} (try step, continue, regs or mem ADDR)", e)?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gameboy_emulator::{cartridge::Cartridge, Emulator};
+
+    use super::{parse_address, Command, Debugger, Resume};
+
+    #[test]
+    fn breakpoint_addresses() {
+        assert_eq!(parse_address("0x0150"), Ok(0x0150));
+        assert_eq!(parse_address("0XFF80"), Ok(0xFF80));
+        assert_eq!(parse_address("c000"), Ok(0xC000));
+        assert!(parse_address("0x10000").is_err());
+        assert!(parse_address("main").is_err());
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(Command::parse("step\n"), Ok(Command::Step));
+        assert_eq!(Command::parse("c"), Ok(Command::Continue));
+        assert_eq!(Command::parse("regs"), Ok(Command::Registers));
+        assert_eq!(Command::parse("mem 0xC000"), Ok(Command::Memory(0xC000)));
+        assert!(Command::parse("mem").is_err());
+        assert!(Command::parse("jump 0x0100").is_err());
+    }
+
+    #[test]
+    fn prompt_reads_until_resumed() {
+        let emulator = Emulator::builder()
+            .with_cartridge(Cartridge::empty())
+            .build();
+        let mut output = Vec::new();
+
+        let mut debugger = Debugger::new("mem 0x0000\nbogus\nstep\n".as_bytes(), &mut output);
+        assert_eq!(debugger.prompt(&emulator).unwrap(), Resume::Step);
+        assert_eq!(debugger.prompt(&emulator).unwrap(), Resume::Continue);
+
+        let output = String::from_utf8(output).unwrap();
+        let boot_rom_byte = format!("$0000: ${:02X}", emulator.peek_u8(0x0000));
+        assert!(output.contains(&boot_rom_byte));
+        assert!(output.contains("Unknown command: bogus"));
+    }
+}
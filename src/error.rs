@@ -0,0 +1,49 @@
+use std::fmt::Display;
+
+use crate::{boot, cartridge, cpu};
+
+/// Any error that can be produced by the emulator's public API
+#[derive(Debug)]
+pub enum EmulatorError {
+    Cpu(cpu::error::Error),
+    Cartridge(cartridge::error::Error),
+    BootRom(boot::error::Error),
+    Io(std::io::Error),
+}
+
+impl Display for EmulatorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cpu(e) => write!(f, "CPU error: {:?}", e),
+            Self::Cartridge(e) => write!(f, "Failed to read cartridge: {:?}", e),
+            Self::BootRom(e) => write!(f, "Failed to read boot ROM: {:?}", e),
+            Self::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for EmulatorError {}
+
+impl From<cpu::error::Error> for EmulatorError {
+    fn from(value: cpu::error::Error) -> Self {
+        Self::Cpu(value)
+    }
+}
+
+impl From<cartridge::error::Error> for EmulatorError {
+    fn from(value: cartridge::error::Error) -> Self {
+        Self::Cartridge(value)
+    }
+}
+
+impl From<boot::error::Error> for EmulatorError {
+    fn from(value: boot::error::Error) -> Self {
+        Self::BootRom(value)
+    }
+}
+
+impl From<std::io::Error> for EmulatorError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    fmt::Display,
+    io::{BufRead, Write},
+    path::Path,
+};
+
+use gameboy_emulator::{
+    error::EmulatorError,
+    ppu::{DISPLAY_HEIGHT_PIXELS, DISPLAY_WIDTH_PIXELS},
+    Emulator, InputState, CYCLES_PER_FRAME,
+};
+
+use crate::debugger::{Debugger, Resume};
+
+#[derive(Debug)]
+pub enum Error {
+    Emulator(EmulatorError),
+    Image(image::ImageError),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Emulator(e) => write!(f, "{}", e),
+            Self::Image(e) => write!(f, "Failed to write frame: {}", e),
+        }
+    }
+}
+
+impl From<EmulatorError> for Error {
+    fn from(value: EmulatorError) -> Self {
+        Self::Emulator(value)
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(value: image::ImageError) -> Self {
+        Self::Image(value)
+    }
+}
+
+/// Runs the emulator without a window, writing every frame to `out_dir` as `frame-NNNNN.png`
+///
+/// Execution stops at breakpoints and hands over to the debugger until it resumes. While the LCD
+/// is off, a frame is written after every frame's worth of cycles, as with [`Emulator::run_frame`].
+/// With `trace` set, every instruction is logged to stderr before it is executed, along with any
+/// stack warnings it causes.
+pub fn run(
+    emulator: &mut Emulator,
+    frames: u32,
+    out_dir: &Path,
+    trace: bool,
+    debugger: &mut Debugger<impl BufRead, impl Write>,
+) -> Result<(), Error> {
+    std::fs::create_dir_all(out_dir).map_err(EmulatorError::from)?;
+
+    let mut frame = 0;
+    let mut cycles_since_frame = 0;
+    let mut stepping = false;
+
+    while frame < frames {
+        if stepping || emulator.breakpoint_reached().is_some() {
+            let resume = debugger.prompt(emulator).map_err(EmulatorError::from)?;
+            stepping = resume == Resume::Step;
+        }
+
+        if trace {
+            eprintln!("{}", emulator.trace_line()?);
+        }
+
+        let outcome = emulator.step(InputState::empty())?;
+        cycles_since_frame += outcome.cycles;
+
+        if trace {
+            for warning in emulator.take_stack_warnings() {
+                eprintln!("warning: {}", warning);
+            }
+        }
+
+        if outcome.new_frame || (!emulator.lcd_enabled() && cycles_since_frame >= CYCLES_PER_FRAME)
+        {
+            save_frame_png(
+                emulator.frame_rgba(),
+                &out_dir.join(format!("frame-{:05}.png", frame)),
+            )?;
+            frame += 1;
+            cycles_since_frame = 0;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a frame of tightly packed RGBA bytes as a PNG
+pub fn save_frame_png(frame_rgba: &[u8], path: &Path) -> Result<(), image::ImageError> {
+    image::save_buffer_with_format(
+        path,
+        frame_rgba,
+        DISPLAY_WIDTH_PIXELS as u32,
+        DISPLAY_HEIGHT_PIXELS as u32,
+        image::ExtendedColorType::Rgba8,
+        image::ImageFormat::Png,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use gameboy_emulator::{cartridge::Cartridge, Emulator};
+
+    use crate::debugger::Debugger;
+
+    use super::run;
+
+    #[test]
+    fn writes_a_png_per_frame() {
+        let out_dir = std::env::temp_dir().join(format!(
+            "gameboy-emulator-{}-writes_a_png_per_frame",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&out_dir);
+
+        let mut emulator = Emulator::builder()
+            .with_cartridge(Cartridge::empty())
+            .build();
+
+        let mut debugger = Debugger::new(std::io::empty(), std::io::sink());
+        run(&mut emulator, 3, &out_dir, false, &mut debugger).unwrap();
+
+        for frame in 0..3 {
+            let path = out_dir.join(format!("frame-{:05}.png", frame));
+            let image = image::open(&path).unwrap();
+
+            assert_eq!((image.width(), image.height()), (160, 144));
+        }
+        assert!(!out_dir.join("frame-00003.png").exists());
+
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+}
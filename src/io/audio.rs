@@ -1,22 +1,45 @@
-use super::IORegister;
+use super::{IORegister, MaskedIORegister};
+
+// The bits of each register that always read as 1, because they are unused or write-only. NRx1
+// only reads back the duty cycle, the period is write-only, and of NRx4 only the length enable
+// bit can be read.
+const NR10_READ_MASK: u8 = 0b1000_0000;
+const NRX1_READ_MASK: u8 = 0b0011_1111;
+const NRX1_LENGTH_MASK: u8 = 0b0011_1111;
+const NR30_READ_MASK: u8 = 0b0111_1111;
+const NR31_READ_MASK: u8 = 0b1111_1111;
+const NR32_READ_MASK: u8 = 0b1001_1111;
+const NR41_READ_MASK: u8 = 0b1111_1111;
+const PERIOD_LOW_READ_MASK: u8 = 0b1111_1111;
+const CONTROL_READ_MASK: u8 = 0b1011_1111;
+
+// The bits of each register that hold a value, the rest are unused
+const NR10_WRITE_MASK: u8 = 0b0111_1111;
+const NR32_WRITE_MASK: u8 = 0b0110_0000;
+const NR41_WRITE_MASK: u8 = 0b0011_1111;
+const NR44_WRITE_MASK: u8 = 0b1100_0000;
+const PERIOD_HIGH_AND_CONTROL_WRITE_MASK: u8 = 0b1100_0111;
 
 #[derive(Debug, Clone, Copy)]
 pub struct AudioChannel1 {
-    sweep: IORegister,
-    length_timer_and_duty_cycle: IORegister,
+    sweep: MaskedIORegister,
+    length_timer_and_duty_cycle: MaskedIORegister,
     volume_and_envelope: IORegister,
-    period_low: IORegister,
-    period_high_and_control: IORegister,
+    period_low: MaskedIORegister,
+    period_high_and_control: MaskedIORegister,
 }
 
 impl AudioChannel1 {
     pub fn new() -> Self {
         Self {
-            sweep: IORegister::new(),
-            length_timer_and_duty_cycle: IORegister::new(),
+            sweep: MaskedIORegister::new(NR10_READ_MASK, NR10_WRITE_MASK),
+            length_timer_and_duty_cycle: MaskedIORegister::new(NRX1_READ_MASK, 0xFF),
             volume_and_envelope: IORegister::new(),
-            period_low: IORegister::new(),
-            period_high_and_control: IORegister::new(),
+            period_low: MaskedIORegister::new(PERIOD_LOW_READ_MASK, 0xFF),
+            period_high_and_control: MaskedIORegister::new(
+                CONTROL_READ_MASK,
+                PERIOD_HIGH_AND_CONTROL_WRITE_MASK,
+            ),
         }
     }
 
@@ -45,7 +68,7 @@ impl AudioChannel1 {
     }
 
     pub fn read_period_low(&self) -> u8 {
-        0
+        self.period_low.read()
     }
 
     pub fn write_period_low(&mut self, value: u8) {
@@ -63,19 +86,22 @@ impl AudioChannel1 {
 
 #[derive(Debug, Clone, Copy)]
 pub struct AudioChannel2 {
-    length_timer_and_duty_cycle: IORegister,
+    length_timer_and_duty_cycle: MaskedIORegister,
     volume_and_envelope: IORegister,
-    period_low: IORegister,
-    period_high_and_control: IORegister,
+    period_low: MaskedIORegister,
+    period_high_and_control: MaskedIORegister,
 }
 
 impl AudioChannel2 {
     pub fn new() -> Self {
         Self {
-            length_timer_and_duty_cycle: IORegister::new(),
+            length_timer_and_duty_cycle: MaskedIORegister::new(NRX1_READ_MASK, 0xFF),
             volume_and_envelope: IORegister::new(),
-            period_low: IORegister::new(),
-            period_high_and_control: IORegister::new(),
+            period_low: MaskedIORegister::new(PERIOD_LOW_READ_MASK, 0xFF),
+            period_high_and_control: MaskedIORegister::new(
+                CONTROL_READ_MASK,
+                PERIOD_HIGH_AND_CONTROL_WRITE_MASK,
+            ),
         }
     }
 
@@ -96,7 +122,7 @@ impl AudioChannel2 {
     }
 
     pub fn read_period_low(&self) -> u8 {
-        0
+        self.period_low.read()
     }
 
     pub fn write_period_low(&mut self, value: u8) {
@@ -115,10 +141,10 @@ impl AudioChannel2 {
 #[derive(Debug, Clone, Copy)]
 pub struct AudioChannel3 {
     dac_enable: bool,
-    length_timer: IORegister,
-    output_level: IORegister,
-    period_low: IORegister,
-    period_high_and_control: IORegister,
+    length_timer: MaskedIORegister,
+    output_level: MaskedIORegister,
+    period_low: MaskedIORegister,
+    period_high_and_control: MaskedIORegister,
     wave_pattern_ram: [u8; 16],
 }
 
@@ -126,20 +152,21 @@ impl AudioChannel3 {
     pub fn new() -> Self {
         Self {
             dac_enable: false,
-            length_timer: IORegister::new(),
-            output_level: IORegister::new(),
-            period_low: IORegister::new(),
-            period_high_and_control: IORegister::new(),
+            length_timer: MaskedIORegister::new(NR31_READ_MASK, 0xFF),
+            output_level: MaskedIORegister::new(NR32_READ_MASK, NR32_WRITE_MASK),
+            period_low: MaskedIORegister::new(PERIOD_LOW_READ_MASK, 0xFF),
+            period_high_and_control: MaskedIORegister::new(
+                CONTROL_READ_MASK,
+                PERIOD_HIGH_AND_CONTROL_WRITE_MASK,
+            ),
             wave_pattern_ram: [0u8; 16],
         }
     }
 
     pub fn read_dac_enable(&self) -> u8 {
-        if self.dac_enable {
-            1 << 7
-        } else {
-            0
-        }
+        let dac_enable = if self.dac_enable { 1 << 7 } else { 0 };
+
+        dac_enable | NR30_READ_MASK
     }
 
     pub fn write_dac_enable(&mut self, value: u8) {
@@ -163,7 +190,7 @@ impl AudioChannel3 {
     }
 
     pub fn read_period_low(&self) -> u8 {
-        0
+        self.period_low.read()
     }
 
     pub fn write_period_low(&mut self, value: u8) {
@@ -187,26 +214,96 @@ impl AudioChannel3 {
     }
 }
 
+/// M-cycles between LFSR clocks for each clock divider, before the shift is applied
+const NOISE_DIVISOR_CYCLES: [usize; 8] = [2, 4, 8, 12, 16, 20, 24, 28];
+
 #[derive(Debug, Clone, Copy)]
 pub struct AudioChannel4 {
-    length_timer: IORegister,
+    length_timer: MaskedIORegister,
     volume_and_envelope: IORegister,
     frequency_and_randomness: IORegister,
-    control: IORegister,
+    control: MaskedIORegister,
+    enabled: bool,
+    lfsr: u16,
+    lfsr_seed: u16,
+    lfsr_cycles: usize,
 }
 
 impl AudioChannel4 {
     pub fn new() -> Self {
         Self {
-            length_timer: IORegister::new(),
+            length_timer: MaskedIORegister::new(NR41_READ_MASK, NR41_WRITE_MASK),
             volume_and_envelope: IORegister::new(),
             frequency_and_randomness: IORegister::new(),
-            control: IORegister::new(),
+            control: MaskedIORegister::new(CONTROL_READ_MASK, NR44_WRITE_MASK),
+            enabled: false,
+            lfsr: 0,
+            lfsr_seed: 0,
+            lfsr_cycles: 0,
+        }
+    }
+
+    /// Sets the value the LFSR is reset to when the channel is triggered, hardware uses 0
+    pub fn set_lfsr_seed(&mut self, seed: u16) {
+        self.lfsr_seed = seed & 0x7FFF;
+        self.lfsr = self.lfsr_seed;
+    }
+
+    pub fn lfsr_seed(&self) -> u16 {
+        self.lfsr_seed
+    }
+
+    pub fn lfsr(&self) -> u16 {
+        self.lfsr
+    }
+
+    /// The current output level of the channel, from 0 to 15
+    pub fn sample(&self) -> u8 {
+        if !self.enabled || (self.lfsr & 1) != 0 {
+            return 0;
+        }
+
+        self.volume_and_envelope.read() >> 4
+    }
+
+    fn lfsr_period(&self) -> usize {
+        let value = self.frequency_and_randomness.read();
+        let shift = value >> 4;
+        let divider = (value & 0b0000_0111) as usize;
+
+        NOISE_DIVISOR_CYCLES[divider] << shift
+    }
+
+    fn clock_lfsr(&mut self) {
+        let short_mode = (self.frequency_and_randomness.read() & 0b0000_1000) != 0;
+
+        let xnor = !(self.lfsr ^ (self.lfsr >> 1)) & 1;
+        self.lfsr = (self.lfsr & 0x7FFF) | (xnor << 15);
+
+        if short_mode {
+            self.lfsr = (self.lfsr & !(1 << 7)) | (xnor << 7);
+        }
+
+        self.lfsr >>= 1;
+    }
+
+    pub fn step(&mut self, cycles: usize) {
+        if !self.enabled {
+            return;
+        }
+
+        self.lfsr_cycles += cycles;
+
+        let period = self.lfsr_period();
+
+        while self.lfsr_cycles >= period {
+            self.lfsr_cycles -= period;
+            self.clock_lfsr();
         }
     }
 
     pub fn read_length_timer(&self) -> u8 {
-        0
+        self.length_timer.read()
     }
 
     pub fn write_length_timer(&mut self, value: u8) {
@@ -235,12 +332,23 @@ impl AudioChannel4 {
 
     pub fn write_control(&mut self, value: u8) {
         self.control.write(value);
+
+        if (value & 0b1000_0000) != 0 {
+            self.enabled = true;
+            self.lfsr = self.lfsr_seed;
+            self.lfsr_cycles = 0;
+        }
     }
 }
 
+/// NR52 bits 4-6 are unused, and only the power bit can be written; the low bits show which
+/// channels are on
+const NR52_READ_MASK: u8 = 0b0111_0000;
+const NR52_WRITE_MASK: u8 = 0b1000_0000;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Audio {
-    audio_master_control: IORegister,
+    audio_master_control: MaskedIORegister,
     sound_panning: IORegister,
     master_volume_vin_panning: IORegister,
     channel_1: AudioChannel1,
@@ -252,7 +360,7 @@ pub struct Audio {
 impl Audio {
     pub fn new() -> Self {
         Self {
-            audio_master_control: IORegister::new(),
+            audio_master_control: MaskedIORegister::new(NR52_READ_MASK, NR52_WRITE_MASK),
             sound_panning: IORegister::new(),
             master_volume_vin_panning: IORegister::new(),
             channel_1: AudioChannel1::new(),
@@ -263,11 +371,57 @@ impl Audio {
     }
 
     pub fn read_audio_master_control(&self) -> u8 {
-        self.audio_master_control.read()
+        let channel_4_on = if self.channel_4.enabled { 0b1000 } else { 0 };
+
+        self.audio_master_control.read() | channel_4_on
+    }
+
+    /// Whether the APU is on, if it isn't all of the sound registers but wave RAM are read-only
+    pub fn powered_on(&self) -> bool {
+        (self.audio_master_control.value() & 0b1000_0000) != 0
     }
 
     pub fn write_audio_master_control(&mut self, value: u8) {
+        let was_powered_on = self.powered_on();
         self.audio_master_control.write(value);
+
+        if was_powered_on && !self.powered_on() {
+            self.power_off();
+        }
+    }
+
+    /// Clears every sound register, which turns all of the channels off
+    ///
+    /// Wave RAM isn't a register, so it keeps its contents, and the LFSR seed isn't hardware state.
+    fn power_off(&mut self) {
+        let wave_pattern_ram = self.channel_3.wave_pattern_ram;
+        let lfsr_seed = self.channel_4.lfsr_seed;
+
+        self.sound_panning = IORegister::new();
+        self.master_volume_vin_panning = IORegister::new();
+        self.channel_1 = AudioChannel1::new();
+        self.channel_2 = AudioChannel2::new();
+        self.channel_3 = AudioChannel3::new();
+        self.channel_3.wave_pattern_ram = wave_pattern_ram;
+        self.channel_4 = AudioChannel4::new();
+        self.channel_4.set_lfsr_seed(lfsr_seed);
+    }
+
+    /// A write to 0xFF10-0xFF25 while the APU is off
+    ///
+    /// These are all ignored, except that the DMG still lets the length timers be written.
+    pub fn write_while_powered_off(&mut self, address: u16, value: u8) {
+        match address {
+            0xFF11 => self
+                .channel_1
+                .write_length_timer_and_duty_cycle(value & NRX1_LENGTH_MASK),
+            0xFF16 => self
+                .channel_2
+                .write_length_timer_and_duty_cycle(value & NRX1_LENGTH_MASK),
+            0xFF1B => self.channel_3.write_length_timer(value),
+            0xFF20 => self.channel_4.write_length_timer(value),
+            _ => {}
+        }
     }
 
     pub fn read_sound_panning(&self) -> u8 {
@@ -317,4 +471,191 @@ impl Audio {
     pub fn channel_4_mut(&mut self) -> &mut AudioChannel4 {
         &mut self.channel_4
     }
+
+    pub fn step(&mut self, cycles: usize) {
+        self.channel_4.step(cycles);
+    }
+}
+
+/// Flattens stereo samples into interleaved left, right frames, the layout WAV files use
+pub fn interleave_stereo(samples: &[(i16, i16)]) -> Vec<i16> {
+    samples
+        .iter()
+        .flat_map(|&(left, right)| [left, right])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io::IO;
+
+    use super::{interleave_stereo, Audio, AudioChannel4};
+
+    /// Checks that each register reads back what was written, with its unreadable bits set
+    fn assert_read_masks(registers: &[(u16, u8)]) {
+        let mut io = IO::new();
+        io.write_u8(0xFF26, 0x80).unwrap();
+
+        for &(address, read_mask) in registers {
+            for value in [0x00, 0xA5, 0x5A, 0xFF] {
+                io.write_u8(address, value).unwrap();
+                assert_eq!(
+                    io.read_u8(address).unwrap(),
+                    value | read_mask,
+                    "{:#06X} after writing {:#04X}",
+                    address,
+                    value
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn channel_1_read_masks() {
+        assert_read_masks(&[
+            (0xFF10, 0x80),
+            (0xFF11, 0x3F),
+            (0xFF12, 0x00),
+            (0xFF13, 0xFF),
+            (0xFF14, 0xBF),
+        ]);
+    }
+
+    #[test]
+    fn channel_2_read_masks() {
+        assert_read_masks(&[
+            (0xFF16, 0x3F),
+            (0xFF17, 0x00),
+            (0xFF18, 0xFF),
+            (0xFF19, 0xBF),
+        ]);
+    }
+
+    #[test]
+    fn channel_3_read_masks() {
+        assert_read_masks(&[
+            (0xFF1A, 0x7F),
+            (0xFF1B, 0xFF),
+            (0xFF1C, 0x9F),
+            (0xFF1D, 0xFF),
+            (0xFF1E, 0xBF),
+        ]);
+    }
+
+    #[test]
+    fn channel_4_read_masks() {
+        assert_read_masks(&[
+            (0xFF20, 0xFF),
+            (0xFF21, 0x00),
+            (0xFF22, 0x00),
+            (0xFF23, 0xBF),
+        ]);
+    }
+
+    fn triggered_channel(frequency_and_randomness: u8) -> AudioChannel4 {
+        let mut channel = AudioChannel4::new();
+        channel.write_volume_and_envelope(0xF0);
+        channel.write_frequency_and_randomness(frequency_and_randomness);
+        channel.write_control(0x80);
+        channel
+    }
+
+    #[test]
+    fn lfsr_sequence_from_zero() {
+        let mut channel = triggered_channel(0x00);
+
+        // Every clock shifts a 1 in from the top while the low bits are equal
+        channel.step(2);
+        assert_eq!(channel.lfsr(), 0x4000);
+        channel.step(2);
+        assert_eq!(channel.lfsr(), 0x6000);
+        assert_eq!(channel.sample(), 0xF);
+    }
+
+    #[test]
+    fn short_mode_lfsr() {
+        let mut channel = triggered_channel(0x08);
+
+        channel.step(2);
+        assert_eq!(channel.lfsr(), 0x4040);
+    }
+
+    #[test]
+    fn lfsr_period() {
+        let mut channel = triggered_channel(0x21);
+
+        // Divider 1 with a shift of 2 clocks every 16 M-cycles
+        channel.step(15);
+        assert_eq!(channel.lfsr(), 0);
+        channel.step(1);
+        assert_eq!(channel.lfsr(), 0x4000);
+    }
+
+    #[test]
+    fn seed_used_on_trigger() {
+        let mut channel = AudioChannel4::new();
+        channel.set_lfsr_seed(0x1234);
+        channel.step(100);
+        assert_eq!(channel.lfsr(), 0x1234);
+
+        channel.write_control(0x80);
+        assert_eq!(channel.lfsr(), 0x1234);
+    }
+
+    #[test]
+    fn stereo_samples_interleave() {
+        let samples = [(1, -1), (i16::MAX, i16::MIN), (0, 300)];
+
+        assert_eq!(
+            interleave_stereo(&samples),
+            vec![1, -1, i16::MAX, i16::MIN, 0, 300]
+        );
+        assert!(interleave_stereo(&[]).is_empty());
+    }
+
+    #[test]
+    fn nr52_reads_unused_bits_as_one() {
+        let mut audio = Audio::new();
+        assert_eq!(audio.read_audio_master_control(), 0x70);
+
+        // The channel flags can't be written
+        audio.write_audio_master_control(0xFF);
+        assert_eq!(audio.read_audio_master_control(), 0xF0);
+
+        audio.channel_4_mut().write_control(0x80);
+        assert_eq!(audio.read_audio_master_control(), 0xF8);
+
+        audio.write_audio_master_control(0x00);
+        assert_eq!(audio.read_audio_master_control(), 0x70);
+    }
+
+    #[test]
+    fn powered_off_registers_are_cleared_and_read_only() {
+        let mut io = IO::new();
+        io.write_u8(0xFF26, 0x80).unwrap();
+        io.write_u8(0xFF12, 0xF3).unwrap();
+        io.write_u8(0xFF25, 0xFF).unwrap();
+        io.write_u8(0xFF30, 0x12).unwrap();
+
+        io.write_u8(0xFF26, 0x00).unwrap();
+        assert_eq!(io.read_u8(0xFF12).unwrap(), 0x00);
+        assert_eq!(io.read_u8(0xFF25).unwrap(), 0x00);
+        assert_eq!(io.read_u8(0xFF30).unwrap(), 0x12);
+
+        io.write_u8(0xFF12, 0xF3).unwrap();
+        io.write_u8(0xFF30, 0x34).unwrap();
+        assert_eq!(io.read_u8(0xFF12).unwrap(), 0x00);
+        assert_eq!(io.read_u8(0xFF30).unwrap(), 0x34);
+
+        // Only the length bits of NRx1 can be written on the DMG
+        io.write_u8(0xFF11, 0xFF).unwrap();
+        assert_eq!(
+            io.audio().channel_1().length_timer_and_duty_cycle.value(),
+            0x3F
+        );
+
+        io.write_u8(0xFF26, 0x80).unwrap();
+        io.write_u8(0xFF12, 0xF3).unwrap();
+        assert_eq!(io.read_u8(0xFF12).unwrap(), 0xF3);
+    }
 }
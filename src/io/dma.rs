@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use super::IORegister;
 
 pub const DMA_TRANSFER_CYCLES_LENGTH: u16 = 160;
@@ -6,7 +8,7 @@ pub const DMA_TRANSFER_CYCLES_LENGTH: u16 = 160;
 pub struct DMAController {
     transferring: bool,
     source_address: u16,
-    cycles_in: u16,
+    bytes_transferred: u16,
     source_reg: IORegister,
 }
 
@@ -15,7 +17,7 @@ impl DMAController {
         Self {
             transferring: false,
             source_address: 0,
-            cycles_in: 0,
+            bytes_transferred: 0,
             source_reg: IORegister::new(),
         }
     }
@@ -30,28 +32,91 @@ impl DMAController {
 
     pub fn start_new_transfer(&mut self, source: u8) {
         self.transferring = true;
-        self.source_address = source as u16 * 0x100;
+        self.source_address = Self::source_address_for(source);
         self.source_reg.write(source);
-        self.cycles_in = 0;
+        self.bytes_transferred = 0;
+    }
+
+    /// The DMA unit only decodes addresses up to 0xDFFF, so sources from 0xE000 upward read
+    /// from work RAM 0x2000 bytes lower, just like echo RAM does
+    fn source_address_for(source: u8) -> u16 {
+        let source = if source >= 0xE0 {
+            source - 0x20
+        } else {
+            source
+        };
+
+        source as u16 * 0x100
     }
 
     pub fn transferring(&self) -> bool {
         self.transferring
     }
 
-    pub fn step(&mut self, cycles: usize) -> bool {
-        if self.transferring {
-            let now = self.cycles_in + cycles as u16;
+    /// Advances the transfer, returning the offsets of the bytes which should be copied now
+    ///
+    /// One byte is copied every M-cycle, so the transfer finishes after 160 M-cycles.
+    pub fn step(&mut self, cycles: usize) -> Range<u16> {
+        if !self.transferring {
+            return 0..0;
+        }
 
-            if now > DMA_TRANSFER_CYCLES_LENGTH {
-                self.cycles_in = 0;
-                self.transferring = false;
-                return true;
-            } else {
-                self.cycles_in += now;
-            }
+        let start = self.bytes_transferred;
+        let end = (start as usize + cycles).min(DMA_TRANSFER_CYCLES_LENGTH as usize) as u16;
+        self.bytes_transferred = end;
+
+        if end == DMA_TRANSFER_CYCLES_LENGTH {
+            self.transferring = false;
         }
 
-        false
+        start..end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DMAController;
+
+    #[test]
+    fn source_addresses() {
+        let mut dma = DMAController::new();
+
+        dma.start_new_transfer(0x00);
+        assert_eq!(dma.full_source_address(), 0x0000);
+        dma.start_new_transfer(0x80);
+        assert_eq!(dma.full_source_address(), 0x8000);
+        dma.start_new_transfer(0xC1);
+        assert_eq!(dma.full_source_address(), 0xC100);
+        dma.start_new_transfer(0xDF);
+        assert_eq!(dma.full_source_address(), 0xDF00);
+    }
+
+    #[test]
+    fn high_sources_mirror_work_ram() {
+        let mut dma = DMAController::new();
+
+        dma.start_new_transfer(0xE0);
+        assert_eq!(dma.full_source_address(), 0xC000);
+        dma.start_new_transfer(0xFE);
+        assert_eq!(dma.full_source_address(), 0xDE00);
+        dma.start_new_transfer(0xFF);
+        assert_eq!(dma.full_source_address(), 0xDF00);
+
+        // The register still reads back what was written
+        assert_eq!(dma.read_source_address(), 0xFF);
+    }
+
+    #[test]
+    fn transfer_spread_over_cycles() {
+        let mut dma = DMAController::new();
+        assert_eq!(dma.step(4), 0..0);
+
+        dma.start_new_transfer(0xC0);
+        assert_eq!(dma.step(4), 0..4);
+        assert_eq!(dma.step(150), 4..154);
+        assert!(dma.transferring());
+        assert_eq!(dma.step(10), 154..160);
+        assert!(!dma.transferring());
+        assert_eq!(dma.step(4), 0..0);
     }
 }
@@ -9,6 +9,63 @@ pub enum Interrupt {
     VBlank,
 }
 
+impl Interrupt {
+    /// Every interrupt, highest priority first
+    pub const ALL: [Interrupt; 5] = [
+        Self::VBlank,
+        Self::Lcd,
+        Self::Timer,
+        Self::Serial,
+        Self::Joypad,
+    ];
+
+    /// The interrupt's bit in the IE and IF registers
+    pub fn mask(self) -> u8 {
+        match self {
+            Self::VBlank => 1 << 0,
+            Self::Lcd => 1 << 1,
+            Self::Timer => 1 << 2,
+            Self::Serial => 1 << 3,
+            Self::Joypad => 1 << 4,
+        }
+    }
+}
+
+/// A set of interrupts, stored the way the IE and IF registers store them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterruptFlags(u8);
+
+impl InterruptFlags {
+    pub fn new(bits: u8) -> Self {
+        Self(bits & 0b0001_1111)
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(self, interrupt: Interrupt) -> bool {
+        self.0 & interrupt.mask() != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The interrupts in the set, highest priority first
+    pub fn iter(self) -> impl Iterator<Item = Interrupt> {
+        Interrupt::ALL
+            .into_iter()
+            .filter(move |interrupt| self.contains(*interrupt))
+    }
+
+    /// The interrupt in the set the CPU would service first, VBlank being the highest priority and
+    /// Joypad the lowest
+    pub fn highest_priority(self) -> Option<Interrupt> {
+        self.iter().next()
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct Interrupts {
     interrupt_flag: IORegister,
@@ -23,46 +80,33 @@ impl Interrupts {
         }
     }
 
-    pub fn highest_priority_triggered_interrupt(&self) -> Option<Interrupt> {
-        let triggered = self.interrupt_enable.read() & self.interrupt_flag.read();
+    /// The interrupts requested in IF
+    pub fn requested(&self) -> InterruptFlags {
+        InterruptFlags::new(self.interrupt_flag.read())
+    }
 
-        if (triggered & 0b0000_0001) != 0 {
-            Some(Interrupt::VBlank)
-        } else if (triggered & 0b0000_0010) != 0 {
-            Some(Interrupt::Lcd)
-        } else if (triggered & 0b0000_0100) != 0 {
-            Some(Interrupt::Timer)
-        } else if (triggered & 0b0000_1000) != 0 {
-            Some(Interrupt::Serial)
-        } else if (triggered & 0b0001_0000) != 0 {
-            Some(Interrupt::Joypad)
-        } else {
-            None
-        }
+    /// The interrupts enabled in IE
+    pub fn enabled(&self) -> InterruptFlags {
+        InterruptFlags::new(self.interrupt_enable.read())
     }
 
-    pub fn clear_requested_interrupt(&mut self, interrupt: Interrupt) {
-        let mask = !match interrupt {
-            Interrupt::VBlank => 1 << 0,
-            Interrupt::Lcd => 1 << 1,
-            Interrupt::Timer => 1 << 2,
-            Interrupt::Serial => 1 << 3,
-            Interrupt::Joypad => 1 << 4,
-        };
+    /// The interrupts both requested and enabled, which will be serviced when IME is set
+    pub fn pending(&self) -> InterruptFlags {
+        InterruptFlags::new(self.interrupt_enable.read() & self.interrupt_flag.read())
+    }
 
-        self.interrupt_flag.write(self.interrupt_flag.read() & mask);
+    pub fn highest_priority_triggered_interrupt(&self) -> Option<Interrupt> {
+        self.pending().highest_priority()
     }
 
-    pub fn set_interrupt_requested(&mut self, interrupt: Interrupt) {
-        let bit = match interrupt {
-            Interrupt::VBlank => 1 << 0,
-            Interrupt::Lcd => 1 << 1,
-            Interrupt::Timer => 1 << 2,
-            Interrupt::Serial => 1 << 3,
-            Interrupt::Joypad => 1 << 4,
-        };
+    pub fn clear_requested_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt_flag
+            .write(self.interrupt_flag.read() & !interrupt.mask());
+    }
 
-        self.interrupt_flag.write(self.interrupt_flag.read() | bit);
+    pub fn set_interrupt_requested(&mut self, interrupt: Interrupt) {
+        self.interrupt_flag
+            .write(self.interrupt_flag.read() | interrupt.mask());
     }
 
     pub fn read_interrupt_enable(&self) -> u8 {
@@ -81,3 +125,47 @@ impl Interrupts {
         self.interrupt_flag.write(value & 0b0001_1111);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Interrupt, InterruptFlags, Interrupts};
+
+    #[test]
+    fn pending_is_enabled_and_requested() {
+        let mut interrupts = Interrupts::new();
+        interrupts.write_interrupt_enable(0b0001_0110);
+        interrupts.write_interrupt_flag(0b0001_1101);
+
+        let pending = interrupts.pending();
+        assert_eq!(pending, InterruptFlags::new(0b0001_0100));
+        assert_eq!(
+            pending.iter().collect::<Vec<_>>(),
+            vec![Interrupt::Timer, Interrupt::Joypad]
+        );
+        // VBlank is requested but not enabled, so Timer goes first
+        assert_eq!(
+            interrupts.highest_priority_triggered_interrupt(),
+            Some(Interrupt::Timer)
+        );
+
+        interrupts.clear_requested_interrupt(Interrupt::Timer);
+        assert_eq!(
+            interrupts.pending().highest_priority(),
+            Some(Interrupt::Joypad)
+        );
+
+        interrupts.write_interrupt_enable(0);
+        assert!(interrupts.pending().is_empty());
+        assert_eq!(interrupts.highest_priority_triggered_interrupt(), None);
+        assert!(interrupts.requested().contains(Interrupt::VBlank));
+    }
+
+    #[test]
+    fn highest_priority_order() {
+        for (i, interrupt) in Interrupt::ALL.into_iter().enumerate() {
+            // Every lower priority interrupt is pending too
+            let flags = InterruptFlags::new(0b0001_1111 << i);
+            assert_eq!(flags.highest_priority(), Some(interrupt));
+        }
+    }
+}
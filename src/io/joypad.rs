@@ -8,11 +8,33 @@ pub enum InputSelection {
     Both,
 }
 
-#[derive(Clone, Copy)]
+/// The number of bytes in a Super Game Boy command packet
+pub const SGB_PACKET_LENGTH: usize = 16;
+
+/// Super Game Boy commands are sent by pulsing P14 and P15 low
+///
+/// Each packet starts with both lines pulled low, then sends 128 bits least significant bit first,
+/// with P14 low meaning a 0 and P15 low meaning a 1, and ends with a single 0 bit. The lines are
+/// released between each pulse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SgbTransfer {
+    Idle,
+    Receiving {
+        packet: [u8; SGB_PACKET_LENGTH],
+        bits_received: usize,
+    },
+}
+
+#[derive(Clone)]
 pub struct JoypadInput {
     selection: InputSelection,
     inputs: InputState,
     previous_inputs: InputState,
+    lines: u8,
+    sgb_transfer: SgbTransfer,
+    sgb_packets: Vec<[u8; SGB_PACKET_LENGTH]>,
+    capture_sgb_packets: bool,
+    allow_opposing_directions: bool,
 }
 
 impl JoypadInput {
@@ -21,22 +43,47 @@ impl JoypadInput {
             selection: InputSelection::None,
             inputs: InputState::empty(),
             previous_inputs: InputState::empty(),
+            lines: 0b0011_0000,
+            sgb_transfer: SgbTransfer::Idle,
+            sgb_packets: Vec::new(),
+            capture_sgb_packets: false,
+            allow_opposing_directions: false,
         }
     }
 
+    pub fn capture_sgb_packets(&self) -> bool {
+        self.capture_sgb_packets
+    }
+
+    /// Keeps Super Game Boy packets to be read with `take_sgb_packets`, which otherwise aren't
+    /// kept so that they don't pile up when nothing reads them
+    pub fn set_capture_sgb_packets(&mut self, capture: bool) {
+        self.capture_sgb_packets = capture;
+    }
+
+    pub fn allow_opposing_directions(&self) -> bool {
+        self.allow_opposing_directions
+    }
+
+    pub fn set_allow_opposing_directions(&mut self, allow: bool) {
+        self.allow_opposing_directions = allow;
+    }
+
     pub fn step(&mut self, input_state: InputState) -> bool {
         self.update_inputs(input_state);
         self.input_changed()
     }
 
+    /// Whether any of the currently selected input lines went from high to low, which is
+    /// what requests the joypad interrupt on hardware
     pub fn input_changed(&self) -> bool {
         let before = self.read_state(self.previous_inputs);
         let now = self.read_state(self.inputs);
 
-        for i in 0..8 {
+        for i in 0..4 {
             let mask = 1 << i;
 
-            if ((before & mask) == 0) & ((now & mask) != 0) {
+            if ((before & mask) != 0) & ((now & mask) == 0) {
                 return true;
             }
         }
@@ -50,6 +97,8 @@ impl JoypadInput {
     }
 
     pub fn write(&mut self, value: u8) {
+        self.detect_sgb_pulse(value & 0b0011_0000);
+
         let masked = (!value & 0b0011_0000) >> 4;
 
         self.selection = match masked {
@@ -60,8 +109,74 @@ impl JoypadInput {
         };
     }
 
+    fn detect_sgb_pulse(&mut self, lines: u8) {
+        let previous_lines = self.lines;
+        self.lines = lines;
+
+        if lines == previous_lines {
+            return;
+        }
+
+        if lines == 0b0000_0000 {
+            self.sgb_transfer = SgbTransfer::Receiving {
+                packet: [0; SGB_PACKET_LENGTH],
+                bits_received: 0,
+            };
+            return;
+        }
+
+        // Bits are only sent by pulling a single line low after both were released
+        if previous_lines != 0b0011_0000 {
+            return;
+        }
+
+        let bit = match lines {
+            0b0010_0000 => 0,
+            0b0001_0000 => 1,
+            _ => return,
+        };
+
+        if let SgbTransfer::Receiving {
+            mut packet,
+            bits_received,
+        } = self.sgb_transfer
+        {
+            if bits_received < SGB_PACKET_LENGTH * 8 {
+                packet[bits_received / 8] |= bit << (bits_received % 8);
+
+                self.sgb_transfer = SgbTransfer::Receiving {
+                    packet,
+                    bits_received: bits_received + 1,
+                };
+            } else {
+                // Only a 0 is a valid stop bit
+                if bit == 0 && self.capture_sgb_packets {
+                    self.sgb_packets.push(packet);
+                }
+
+                self.sgb_transfer = SgbTransfer::Idle;
+            }
+        }
+    }
+
+    /// All of the complete Super Game Boy packets received since they were last taken, if they
+    /// are being captured
+    pub fn take_sgb_packets(&mut self) -> Vec<[u8; SGB_PACKET_LENGTH]> {
+        std::mem::take(&mut self.sgb_packets)
+    }
+
+    /// Bits 6-7 are unused and always read as 1, bits 4-5 read back the selection
     pub fn read(&self) -> u8 {
-        self.read_state(self.inputs)
+        0b1100_0000 | self.selection_bits() | self.read_state(self.inputs)
+    }
+
+    fn selection_bits(&self) -> u8 {
+        match self.selection {
+            InputSelection::None => 0b0011_0000,
+            InputSelection::DPad => 0b0010_0000,
+            InputSelection::Buttons => 0b0001_0000,
+            InputSelection::Both => 0b0000_0000,
+        }
     }
 
     fn read_state(&self, state: InputState) -> u8 {
@@ -69,16 +184,29 @@ impl JoypadInput {
             InputSelection::Buttons => self.read_buttons(state),
             InputSelection::DPad => self.read_dpad(state),
             InputSelection::Both => self.read_buttons(state) & self.read_dpad(state),
-            InputSelection::None => 0b1100_1111,
+            InputSelection::None => 0b0000_1111,
         }
     }
 
     fn read_dpad(&self, state: InputState) -> u8 {
+        let (up, down, left, right) = if self.allow_opposing_directions {
+            let buttons = state.dpad_buttons;
+            (buttons.up, buttons.down, buttons.left, buttons.right)
+        } else {
+            let dpad = state.dpad_state();
+            (
+                dpad.is_up(),
+                dpad.is_down(),
+                dpad.is_left(),
+                dpad.is_right(),
+            )
+        };
+
         let mut value = 0;
-        value |= if state.dpad_state.is_down() { 0 } else { 1 << 3 };
-        value |= if state.dpad_state.is_up() { 0 } else { 1 << 2 };
-        value |= if state.dpad_state.is_left() { 0 } else { 1 << 1 };
-        value |= if state.dpad_state.is_right() { 0 } else { 1 << 0 };
+        value |= if down { 0 } else { 1 << 3 };
+        value |= if up { 0 } else { 1 << 2 };
+        value |= if left { 0 } else { 1 << 1 };
+        value |= if right { 0 } else { 1 << 0 };
         value
     }
 
@@ -91,3 +219,184 @@ impl JoypadInput {
         value
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{DPadButtonState, DPadState, InputState};
+
+    use super::{InputSelection, JoypadInput, SGB_PACKET_LENGTH};
+
+    const SELECTIONS: [(InputSelection, u8); 4] = [
+        (InputSelection::None, 0b0011_0000),
+        (InputSelection::DPad, 0b0010_0000),
+        (InputSelection::Buttons, 0b0001_0000),
+        (InputSelection::Both, 0b0000_0000),
+    ];
+
+    fn a_pressed() -> InputState {
+        let mut state = InputState::empty();
+        state.a_pressed = true;
+        state
+    }
+
+    fn up_pressed() -> InputState {
+        InputState::empty().with_dpad(DPadState::Up)
+    }
+
+    fn joypad_with_selection(selection: InputSelection) -> JoypadInput {
+        let mut joypad = JoypadInput::new();
+        let (_, value) = SELECTIONS.iter().find(|(s, _)| *s == selection).unwrap();
+        joypad.write(*value);
+        assert_eq!(joypad.selection, selection);
+        joypad
+    }
+
+    #[test]
+    fn button_press_interrupts_only_when_selected() {
+        for (selection, _) in SELECTIONS {
+            let mut joypad = joypad_with_selection(selection);
+
+            let expected = matches!(selection, InputSelection::Buttons | InputSelection::Both);
+            assert_eq!(joypad.step(a_pressed()), expected, "{:?}", selection);
+        }
+    }
+
+    #[test]
+    fn dpad_press_interrupts_only_when_selected() {
+        for (selection, _) in SELECTIONS {
+            let mut joypad = joypad_with_selection(selection);
+
+            let expected = matches!(selection, InputSelection::DPad | InputSelection::Both);
+            assert_eq!(joypad.step(up_pressed()), expected, "{:?}", selection);
+        }
+    }
+
+    #[test]
+    fn release_and_hold_do_not_interrupt() {
+        let mut joypad = joypad_with_selection(InputSelection::Both);
+
+        assert!(joypad.step(a_pressed()));
+        assert!(!joypad.step(a_pressed()));
+        assert!(!joypad.step(InputState::empty()));
+    }
+
+    #[test]
+    fn unused_bits_read_high() {
+        for (selection, value) in SELECTIONS {
+            let joypad = joypad_with_selection(selection);
+
+            assert_eq!(joypad.read(), 0b1100_1111 | value, "{:?}", selection);
+        }
+    }
+
+    #[test]
+    fn read_pressed_button() {
+        let mut joypad = joypad_with_selection(InputSelection::Buttons);
+        joypad.step(a_pressed());
+
+        assert_eq!(joypad.read(), 0b1101_1110);
+    }
+
+    fn send_sgb_packet(joypad: &mut JoypadInput, packet: &[u8; SGB_PACKET_LENGTH]) {
+        joypad.write(0x00);
+        joypad.write(0x30);
+
+        for byte in packet {
+            for i in 0..8 {
+                let pulse = if (byte >> i) & 1 == 0 { 0x20 } else { 0x10 };
+                joypad.write(pulse);
+                joypad.write(0x30);
+            }
+        }
+
+        // Stop bit
+        joypad.write(0x20);
+        joypad.write(0x30);
+    }
+
+    #[test]
+    fn sgb_packets_captured() {
+        let mut joypad = JoypadInput::new();
+        joypad.set_capture_sgb_packets(true);
+
+        let first = [
+            0x79, 0x00, 0x09, 0x00, 0x0B, 0xEF, 0xBE, 0xAD, 0xDE, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ];
+        let second = [
+            0x89, 0xFF, 0x01, 0x80, 0x7F, 0x55, 0xAA, 0x00, 0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC,
+            0xDE, 0xF0,
+        ];
+
+        send_sgb_packet(&mut joypad, &first);
+        send_sgb_packet(&mut joypad, &second);
+
+        assert_eq!(joypad.take_sgb_packets(), vec![first, second]);
+        assert!(joypad.take_sgb_packets().is_empty());
+    }
+
+    #[test]
+    fn sgb_packet_needs_stop_bit() {
+        let mut joypad = JoypadInput::new();
+        joypad.set_capture_sgb_packets(true);
+
+        joypad.write(0x00);
+        joypad.write(0x30);
+        for _ in 0..SGB_PACKET_LENGTH * 8 {
+            joypad.write(0x10);
+            joypad.write(0x30);
+        }
+        // A 1 where the stop bit should be
+        joypad.write(0x10);
+        joypad.write(0x30);
+
+        assert!(joypad.take_sgb_packets().is_empty());
+    }
+
+    #[test]
+    fn normal_polling_is_not_a_packet() {
+        let mut joypad = JoypadInput::new();
+        joypad.set_capture_sgb_packets(true);
+
+        for _ in 0..200 {
+            joypad.write(0x20);
+            joypad.write(0x10);
+            joypad.write(0x30);
+        }
+
+        assert!(joypad.take_sgb_packets().is_empty());
+    }
+
+    #[test]
+    fn sgb_packets_ignored_unless_captured() {
+        let mut joypad = JoypadInput::new();
+        send_sgb_packet(&mut joypad, &[0x79; SGB_PACKET_LENGTH]);
+
+        assert!(joypad.take_sgb_packets().is_empty());
+    }
+
+    fn left_and_right_held() -> InputState {
+        let buttons = DPadButtonState::new(false, false, true, true);
+
+        let mut state = InputState::empty();
+        state.dpad_buttons = buttons;
+        state
+    }
+
+    #[test]
+    fn opposing_directions_cancel_by_default() {
+        let mut joypad = joypad_with_selection(InputSelection::DPad);
+        joypad.update_inputs(left_and_right_held());
+
+        assert_eq!(joypad.read() & 0x0F, 0x0F);
+    }
+
+    #[test]
+    fn opposing_directions_allowed() {
+        let mut joypad = joypad_with_selection(InputSelection::DPad);
+        joypad.set_allow_opposing_directions(true);
+        joypad.update_inputs(left_and_right_held());
+
+        assert_eq!(joypad.read() & 0x0F, 0b1100);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{io::IORegister, ppu::PpuMode};
+use crate::{
+    io::{IORegister, MaskedIORegister},
+    ppu::PpuMode,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TileMapArea {
@@ -78,6 +81,33 @@ impl From<u8> for ObjSize {
     }
 }
 
+/// How overlapping objects are prioritized, selected by OPRI (0xFF6C) on the CGB
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjPriority {
+    /// The object that comes first in OAM is drawn on top
+    OamIndex,
+    /// The object with the smallest x coordinate is drawn on top, like on the DMG
+    Coordinate,
+}
+
+impl From<ObjPriority> for u8 {
+    fn from(value: ObjPriority) -> Self {
+        match value {
+            ObjPriority::OamIndex => 0,
+            ObjPriority::Coordinate => 1,
+        }
+    }
+}
+
+impl From<u8> for ObjPriority {
+    fn from(value: u8) -> Self {
+        match value & 1 {
+            0 => ObjPriority::OamIndex,
+            _ => ObjPriority::Coordinate,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Palette {
     pub id0: Color,
@@ -149,90 +179,73 @@ impl From<Color> for u8 {
     }
 }
 
+/// STAT bit 7 is unused, and the LY=LYC flag and PPU mode in bits 0-2 can't be written
+const STAT_READ_MASK: u8 = 0b1000_0000;
+const STAT_WRITE_MASK: u8 = 0b0111_1000;
+
 #[derive(Debug, Clone, Copy)]
 pub struct LcdStatus {
-    lyc_interrupt_select: bool,
-    mode_2_interrupt_select: bool,
-    mode_1_interrupt_select: bool,
-    mode_0_interrupt_select: bool,
-    lyc_equals_ly: bool,
-    ppu_mode: PpuMode,
+    register: MaskedIORegister,
 }
 
 impl LcdStatus {
     pub fn new() -> Self {
         Self {
-            lyc_interrupt_select: false,
-            mode_2_interrupt_select: false,
-            mode_1_interrupt_select: false,
-            mode_0_interrupt_select: false,
-            lyc_equals_ly: false,
-            ppu_mode: PpuMode::HBlank,
+            register: MaskedIORegister::new(STAT_READ_MASK, STAT_WRITE_MASK),
         }
     }
 
     pub fn ppu_mode(&self) -> PpuMode {
-        self.ppu_mode
+        match self.register.value() & 0b11 {
+            0 => PpuMode::HBlank,
+            1 => PpuMode::VBlank,
+            2 => PpuMode::OAMScan,
+            _ => PpuMode::PixelDraw,
+        }
     }
 
     pub fn set_ppu_mode(&mut self, mode: PpuMode) {
-        self.ppu_mode = mode;
+        let value = (self.register.value() & !0b11) | u8::from(mode);
+        self.register.set(value);
     }
 
     pub fn lyc_interrupt_select(&self) -> bool {
-        self.lyc_interrupt_select
+        self.bit(6)
     }
 
     pub fn mode_2_interrupt_select(&self) -> bool {
-        self.mode_2_interrupt_select
+        self.bit(5)
     }
 
     pub fn mode_1_interrupt_select(&self) -> bool {
-        self.mode_1_interrupt_select
+        self.bit(4)
     }
 
     pub fn mode_0_interrupt_select(&self) -> bool {
-        self.mode_0_interrupt_select
+        self.bit(3)
     }
 
     pub fn lyc_equals_ly(&self) -> bool {
-        self.lyc_equals_ly
+        self.bit(2)
+    }
+
+    fn set_lyc_equals_ly(&mut self, equal: bool) {
+        let value = (self.register.value() & !(1 << 2)) | ((equal as u8) << 2);
+        self.register.set(value);
     }
 
     pub fn set_from_u8(&mut self, value: u8) {
-        self.lyc_interrupt_select = (value & (1 << 6)) != 0;
-        self.mode_2_interrupt_select = (value & (1 << 5)) != 0;
-        self.mode_1_interrupt_select = (value & (1 << 4)) != 0;
-        self.mode_0_interrupt_select = (value & (1 << 3)) != 0;
+        self.register.write(value);
+    }
+
+    fn bit(&self, bit: u8) -> bool {
+        (self.register.value() & (1 << bit)) != 0
     }
 }
 
 impl From<&LcdStatus> for u8 {
     fn from(value: &LcdStatus) -> Self {
-        let mut v = 0;
-        v |= if value.lyc_interrupt_select {
-            1 << 6
-        } else {
-            0
-        };
-        v |= if value.mode_2_interrupt_select {
-            1 << 5
-        } else {
-            0
-        };
-        v |= if value.mode_1_interrupt_select {
-            1 << 4
-        } else {
-            0
-        };
-        v |= if value.mode_0_interrupt_select {
-            1 << 3
-        } else {
-            0
-        };
-        v |= if value.lyc_equals_ly { 1 << 2 } else { 0 };
-        v |= u8::from(value.ppu_mode);
-        v
+        value.register.read()
     }
 }
 
@@ -334,6 +347,7 @@ pub struct Lcd {
     background_palette: Palette,
     obj_palette_0: Palette,
     obj_palette_1: Palette,
+    obj_priority: ObjPriority,
 }
 
 impl Lcd {
@@ -350,6 +364,7 @@ impl Lcd {
             background_palette: Palette::default(),
             obj_palette_0: Palette::default(),
             obj_palette_1: Palette::default(),
+            obj_priority: ObjPriority::Coordinate,
         }
     }
 
@@ -379,7 +394,8 @@ impl Lcd {
 
     pub fn update_lcd_y(&mut self, value: u8) {
         self.lcd_y.write(value);
-        self.status.lyc_equals_ly = self.lcd_y.read() == self.lcd_y_compare.read();
+        let equal = self.lcd_y.read() == self.lcd_y_compare.read();
+        self.status.set_lyc_equals_ly(equal);
     }
 
     pub fn read_lcd_y(&self) -> u8 {
@@ -473,4 +489,16 @@ impl Lcd {
     pub fn write_obj_palette_1(&mut self, value: u8) {
         self.obj_palette_1 = Palette::from(value);
     }
+    pub fn obj_priority(&self) -> ObjPriority {
+        self.obj_priority
+    }
+
+    /// Only bit 0 of OPRI is used, the rest read as 1
+    pub fn read_obj_priority(&self) -> u8 {
+        0b1111_1110 | u8::from(self.obj_priority)
+    }
+
+    pub fn write_obj_priority(&mut self, value: u8) {
+        self.obj_priority = ObjPriority::from(value);
+    }
 }
@@ -5,6 +5,7 @@ use joypad::JoypadInput;
 use lcd::Lcd;
 use serial::Serial;
 use timer::Timer;
+use vram_dma::VramDmaController;
 
 pub mod audio;
 pub mod dma;
@@ -13,6 +14,7 @@ pub mod joypad;
 pub mod lcd;
 pub mod serial;
 pub mod timer;
+pub mod vram_dma;
 
 #[derive(Debug, Clone, Copy)]
 pub struct IORegister(u8);
@@ -33,7 +35,53 @@ impl IORegister {
     }
 }
 
-#[derive(Clone, Copy)]
+/// An IO register with bits that can't be written, and bits that always read as 1 because they
+/// are unused or write-only
+///
+/// Bits outside the write mask keep their value when the CPU writes to the register, so they can
+/// only be changed by the hardware through `set`.
+#[derive(Debug, Clone, Copy)]
+pub struct MaskedIORegister {
+    value: u8,
+    read_mask: u8,
+    write_mask: u8,
+}
+
+impl MaskedIORegister {
+    pub fn new(read_mask: u8, write_mask: u8) -> Self {
+        Self {
+            value: 0,
+            read_mask,
+            write_mask,
+        }
+    }
+
+    /// A write from the CPU, which only changes the writable bits
+    #[inline(always)]
+    pub fn write(&mut self, value: u8) {
+        self.value = (self.value & !self.write_mask) | (value & self.write_mask);
+    }
+
+    /// A read from the CPU, with the unused bits set
+    #[inline(always)]
+    pub fn read(&self) -> u8 {
+        self.value | self.read_mask
+    }
+
+    /// The value the hardware has stored, without the unused bits set
+    #[inline(always)]
+    pub fn value(&self) -> u8 {
+        self.value
+    }
+
+    /// Changes any of the bits, as the hardware itself does
+    #[inline(always)]
+    pub fn set(&mut self, value: u8) {
+        self.value = value & !self.read_mask;
+    }
+}
+
+#[derive(Clone)]
 pub struct IO {
     joypad_input: JoypadInput,
     lcd: Lcd,
@@ -42,6 +90,7 @@ pub struct IO {
     timer: Timer,
     interrupts: Interrupts,
     dma: DMAController,
+    vram_dma: VramDmaController,
     boot_rom_enable: IORegister,
 }
 
@@ -55,6 +104,7 @@ impl IO {
             timer: Timer::new(),
             interrupts: Interrupts::new(),
             dma: DMAController::new(),
+            vram_dma: VramDmaController::new(),
             boot_rom_enable: IORegister::new(),
         }
     }
@@ -63,6 +113,10 @@ impl IO {
         self.boot_rom_enable.0
     }
 
+    pub fn set_boot_rom_enable(&mut self, value: u8) {
+        self.boot_rom_enable.write(value);
+    }
+
     pub fn interrupts(&self) -> &Interrupts {
         &self.interrupts
     }
@@ -71,6 +125,14 @@ impl IO {
         &mut self.interrupts
     }
 
+    pub fn audio(&self) -> &Audio {
+        &self.audio
+    }
+
+    pub fn audio_mut(&mut self) -> &mut Audio {
+        &mut self.audio
+    }
+
     pub fn dma(&self) -> &DMAController {
         &self.dma
     }
@@ -79,6 +141,14 @@ impl IO {
         &mut self.dma
     }
 
+    pub fn vram_dma(&self) -> &VramDmaController {
+        &self.vram_dma
+    }
+
+    pub fn vram_dma_mut(&mut self) -> &mut VramDmaController {
+        &mut self.vram_dma
+    }
+
     pub fn lcd(&self) -> &Lcd {
         &self.lcd
     }
@@ -158,6 +228,8 @@ impl IO {
             0xFF4A => self.lcd.read_window_y(),
             0xFF4B => self.lcd.read_window_x(),
             0xFF50 => self.boot_rom_enable.read(),
+            0xFF55 => self.vram_dma.read_control(),
+            0xFF6C => self.lcd.read_obj_priority(),
             0xFF0F => self.interrupts.read_interrupt_flag(),
             0xFFFF => self.interrupts.read_interrupt_enable(),
             _ => {
@@ -167,6 +239,15 @@ impl IO {
         })
     }
 
+    /// Reads the value stored in a register for debugging, without any of the side effects a read
+    /// by the CPU could have
+    ///
+    /// None of the registers currently have read side effects, so this matches `read_u8`, but the
+    /// debugger should always use this so that it stays correct once some do.
+    pub fn debug_read_u8(&self, address: u16) -> u8 {
+        self.read_u8(address).unwrap_or(0xFF)
+    }
+
     pub fn write_u8(&mut self, address: u16, data: u8) -> Result<(), crate::cpu::error::Error> {
         match address {
             0xFF00 => self.joypad_input.write(data),
@@ -176,6 +257,9 @@ impl IO {
             0xFF05 => self.timer.write_timer_counter(data),
             0xFF06 => self.timer.write_timer_modulo(data),
             0xFF07 => self.timer.write_timer_control(data),
+            0xFF10..=0xFF25 if !self.audio.powered_on() => {
+                self.audio.write_while_powered_off(address, data)
+            }
             0xFF10 => self.audio.channel_1_mut().write_sweep(data),
             0xFF11 => self
                 .audio
@@ -234,6 +318,12 @@ impl IO {
             0xFF4A => self.lcd.write_window_y(data),
             0xFF4B => self.lcd.write_window_x(data),
             0xFF50 => self.boot_rom_enable.write(data),
+            0xFF51 => self.vram_dma.write_source_high(data),
+            0xFF52 => self.vram_dma.write_source_low(data),
+            0xFF53 => self.vram_dma.write_destination_high(data),
+            0xFF54 => self.vram_dma.write_destination_low(data),
+            0xFF55 => self.vram_dma.write_control(data),
+            0xFF6C => self.lcd.write_obj_priority(data),
             0xFF0F => self.interrupts.write_interrupt_flag(data),
             0xFFFF => self.interrupts.write_interrupt_enable(data),
             _ => {
@@ -244,3 +334,32 @@ impl IO {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{MaskedIORegister, IO};
+
+    #[test]
+    fn unused_bits_read_as_one() {
+        // Like STAT, bit 7 is unused and the low 3 bits are read-only
+        let mut register = MaskedIORegister::new(0b1000_0000, 0b0111_1000);
+        assert_eq!(register.read(), 0b1000_0000);
+
+        register.write(0xFF);
+        assert_eq!(register.read(), 0b1111_1000);
+        assert_eq!(register.value(), 0b0111_1000);
+
+        register.set(0b0000_0110 | register.value());
+        register.write(0x00);
+        assert_eq!(register.read(), 0b1000_0110);
+    }
+
+    #[test]
+    fn stat_mode_bits_are_read_only() {
+        let mut io = IO::new();
+        assert_eq!(io.read_u8(0xFF41).unwrap(), 0x80);
+
+        io.write_u8(0xFF41, 0xFF).unwrap();
+        assert_eq!(io.read_u8(0xFF41).unwrap(), 0xF8);
+    }
+}
@@ -1,9 +1,14 @@
 use super::IORegister;
 
-#[derive(Debug, Clone, Copy)]
+/// M-cycles taken to shift out a whole byte using the internal 8192 Hz clock
+const TRANSFER_CYCLES: usize = 1024;
+
+#[derive(Debug, Clone)]
 pub struct Serial {
     data: IORegister,
     control: IORegister,
+    transfer_cycles: usize,
+    output: Vec<u8>,
 }
 
 impl Serial {
@@ -11,6 +16,8 @@ impl Serial {
         Self {
             data: IORegister::new(),
             control: IORegister::new(),
+            transfer_cycles: 0,
+            output: Vec::new(),
         }
     }
 
@@ -24,9 +31,80 @@ impl Serial {
 
     pub fn write_control(&mut self, value: u8) {
         self.control.write(value);
+        self.transfer_cycles = 0;
     }
 
     pub fn read_control(&self) -> u8 {
         self.control.read()
     }
+
+    /// A transfer is only ever in progress when this Game Boy provides the clock, as there is
+    /// never anything connected to the other end of the link cable
+    fn transfer_in_progress(&self) -> bool {
+        (self.control.read() & 0b1000_0001) == 0b1000_0001
+    }
+
+    /// Returns true if a transfer completed, which should request the serial interrupt
+    pub fn step(&mut self, cycles: usize) -> bool {
+        if !self.transfer_in_progress() {
+            return false;
+        }
+
+        self.transfer_cycles += cycles;
+
+        if self.transfer_cycles < TRANSFER_CYCLES {
+            return false;
+        }
+
+        self.output.push(self.data.read());
+
+        // With nothing connected, only 1s are shifted in
+        self.data.write(0xFF);
+        self.control.write(self.control.read() & 0b0111_1111);
+        self.transfer_cycles = 0;
+
+        true
+    }
+
+    /// All of the bytes transferred out since the output was last taken
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Serial, TRANSFER_CYCLES};
+
+    fn transfer(serial: &mut Serial, value: u8) {
+        serial.write_data(value);
+        serial.write_control(0x81);
+
+        assert!(!serial.step(TRANSFER_CYCLES - 1));
+        assert!(serial.step(1));
+        assert_eq!(serial.read_data(), 0xFF);
+        assert_eq!(serial.read_control(), 0x01);
+    }
+
+    #[test]
+    fn transfers_accumulate_in_order() {
+        let mut serial = Serial::new();
+
+        for byte in b"Passed" {
+            transfer(&mut serial, *byte);
+        }
+
+        assert_eq!(serial.take_output(), b"Passed");
+        assert!(serial.take_output().is_empty());
+    }
+
+    #[test]
+    fn external_clock_never_completes() {
+        let mut serial = Serial::new();
+        serial.write_data(b'A');
+        serial.write_control(0x80);
+
+        assert!(!serial.step(TRANSFER_CYCLES * 4));
+        assert!(serial.take_output().is_empty());
+    }
 }
@@ -0,0 +1,196 @@
+/// The bytes copied into VRAM at once, either a whole general purpose transfer or one HBlank block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VramDmaTransfer {
+    pub source: u16,
+    pub destination: u16,
+    pub length: u16,
+}
+
+/// CGB VRAM DMA, controlled by HDMA1-HDMA5 (0xFF51-0xFF55)
+///
+/// General purpose transfers copy everything as soon as HDMA5 is written, while HBlank transfers
+/// copy 16 bytes at the start of each HBlank.
+#[derive(Debug, Clone, Copy)]
+pub struct VramDmaController {
+    source: u16,
+    destination: u16,
+    remaining_blocks: u8,
+    hblank_active: bool,
+    general_pending: bool,
+}
+
+impl VramDmaController {
+    pub fn new() -> Self {
+        Self {
+            source: 0,
+            destination: 0,
+            remaining_blocks: 0,
+            hblank_active: false,
+            general_pending: false,
+        }
+    }
+
+    pub fn write_source_high(&mut self, value: u8) {
+        self.source = (self.source & 0x00FF) | ((value as u16) << 8);
+    }
+
+    /// The lower 4 bits are ignored, as transfers are always in blocks of 16 bytes
+    pub fn write_source_low(&mut self, value: u8) {
+        self.source = (self.source & 0xFF00) | (value & 0xF0) as u16;
+    }
+
+    /// Only the bottom 5 bits are used, as the destination is always within VRAM
+    pub fn write_destination_high(&mut self, value: u8) {
+        self.destination = (self.destination & 0x00FF) | (((value & 0x1F) as u16) << 8);
+    }
+
+    pub fn write_destination_low(&mut self, value: u8) {
+        self.destination = (self.destination & 0xFF00) | (value & 0xF0) as u16;
+    }
+
+    /// Bit 7 is clear while an HBlank transfer is active, and the rest hold the number of blocks
+    /// left minus one, so this reads 0xFF once a transfer has completed
+    pub fn read_control(&self) -> u8 {
+        let remaining = self.remaining_blocks.wrapping_sub(1) & 0b0111_1111;
+
+        if self.hblank_active {
+            remaining
+        } else {
+            0b1000_0000 | remaining
+        }
+    }
+
+    pub fn write_control(&mut self, value: u8) {
+        // Writing with bit 7 clear during an HBlank transfer stops it
+        if self.hblank_active && (value & 0b1000_0000) == 0 {
+            self.hblank_active = false;
+            return;
+        }
+
+        self.remaining_blocks = (value & 0b0111_1111) + 1;
+
+        if (value & 0b1000_0000) != 0 {
+            self.hblank_active = true;
+        } else {
+            self.general_pending = true;
+        }
+    }
+
+    pub fn hblank_active(&self) -> bool {
+        self.hblank_active
+    }
+
+    fn next_transfer(&mut self, blocks: u8) -> VramDmaTransfer {
+        let length = blocks as u16 * 16;
+        let transfer = VramDmaTransfer {
+            source: self.source,
+            destination: 0x8000 | self.destination,
+            length,
+        };
+
+        self.source = self.source.wrapping_add(length);
+        self.destination = self.destination.wrapping_add(length) & 0x1FF0;
+        self.remaining_blocks -= blocks;
+
+        transfer
+    }
+
+    /// The general purpose transfer started by the last write to HDMA5, if it hasn't been taken
+    pub fn take_general_transfer(&mut self) -> Option<VramDmaTransfer> {
+        if !self.general_pending {
+            return None;
+        }
+
+        self.general_pending = false;
+        Some(self.next_transfer(self.remaining_blocks))
+    }
+
+    /// Called as the PPU enters HBlank, returning the next block of an HBlank transfer
+    pub fn hblank(&mut self) -> Option<VramDmaTransfer> {
+        if !self.hblank_active {
+            return None;
+        }
+
+        let transfer = self.next_transfer(1);
+
+        if self.remaining_blocks == 0 {
+            self.hblank_active = false;
+        }
+
+        Some(transfer)
+    }
+}
+
+impl Default for VramDmaController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{VramDmaController, VramDmaTransfer};
+
+    fn controller(source: u16, destination: u16) -> VramDmaController {
+        let mut hdma = VramDmaController::new();
+        hdma.write_source_high((source >> 8) as u8);
+        hdma.write_source_low(source as u8);
+        hdma.write_destination_high((destination >> 8) as u8);
+        hdma.write_destination_low(destination as u8);
+        hdma
+    }
+
+    #[test]
+    fn general_transfer_all_at_once() {
+        let mut hdma = controller(0xC123, 0x9F05);
+        hdma.write_control(0x03);
+
+        assert_eq!(
+            hdma.take_general_transfer(),
+            Some(VramDmaTransfer {
+                source: 0xC120,
+                destination: 0x9F00,
+                length: 64,
+            })
+        );
+        assert_eq!(hdma.take_general_transfer(), None);
+        assert_eq!(hdma.hblank(), None);
+        assert_eq!(hdma.read_control(), 0xFF);
+    }
+
+    #[test]
+    fn hblank_transfer_in_blocks() {
+        let mut hdma = controller(0x4000, 0x8800);
+        hdma.write_control(0x81);
+
+        assert_eq!(hdma.take_general_transfer(), None);
+        assert_eq!(hdma.read_control(), 0x01);
+
+        let first = hdma.hblank().unwrap();
+        assert_eq!(
+            (first.source, first.destination, first.length),
+            (0x4000, 0x8800, 16)
+        );
+        assert_eq!(hdma.read_control(), 0x00);
+
+        let second = hdma.hblank().unwrap();
+        assert_eq!((second.source, second.destination), (0x4010, 0x8810));
+        assert_eq!(hdma.hblank(), None);
+        assert_eq!(hdma.read_control(), 0xFF);
+    }
+
+    #[test]
+    fn hblank_transfer_cancelled() {
+        let mut hdma = controller(0x4000, 0x8000);
+        hdma.write_control(0x83);
+        hdma.hblank().unwrap();
+
+        hdma.write_control(0x00);
+
+        assert!(!hdma.hblank_active());
+        assert_eq!(hdma.hblank(), None);
+        assert_eq!(hdma.take_general_transfer(), None);
+        // Reports the blocks which were left
+        assert_eq!(hdma.read_control(), 0x82);
+    }
+}
@@ -1,23 +1,169 @@
 use std::{ops::BitOr, path::Path};
 
-use boot::{BootRom, BootRomReader};
+use address_set::AddressSet;
+use boot::{BootRom, BootRomReader, DEFAULT_BOOT_ROM};
 use bus::Bus;
-use cartridge::Cartridge;
-use cpu::{error::Error, execution_state::ExecutionState, Cpu};
-use eframe::egui::Color32;
-use io::{interrupts::Interrupts, joypad::JoypadInput, timer::Timer};
+use cartridge::{header::CartridgeHeader, Cartridge};
+use clock::{Clock, SystemClock};
+use cpu::{
+    disassembler::disassemble, error::Error, execution_state::ExecutionState,
+    write_log::WriteLogEntry, Cpu,
+};
+use eframe::egui::{Color32, ColorImage};
+use error::EmulatorError;
+use io::{
+    interrupts::{Interrupt, InterruptFlags, Interrupts},
+    joypad::{JoypadInput, SGB_PACKET_LENGTH},
+    lcd::Lcd,
+    serial::Serial,
+    timer::Timer,
+};
+use ppu::{vram::ColorId, PpuMode, TOTAL_PIXELS};
+use symbols::SymbolTable;
 
+mod address_set;
 pub mod boot;
 pub mod bus;
 pub mod cartridge;
+pub mod clock;
 pub mod cpu;
+pub mod error;
 pub mod io;
 pub mod memory;
 pub mod ppu;
+pub mod symbols;
+
+/// The frequency of the main clock, in T-cycles per second
+pub const CPU_CLOCK_HZ: u32 = 4_194_304;
+/// The number of M-cycles it takes the PPU to draw a whole frame, including VBlank
+pub const CYCLES_PER_FRAME: usize = ppu::FRAME_CYCLES_LENGTH;
+/// Roughly 59.73 frames are drawn every second
+pub const FRAMES_PER_SECOND: f64 = CPU_CLOCK_HZ as f64 / (CYCLES_PER_FRAME * 4) as f64;
+
+/// IO register values left behind by the DMG boot ROM, applied when the boot ROM is skipped
+const POST_BOOT_IO_REGISTERS: [(u16, u8); 6] = [
+    (0xFF26, 0xF1), // NR52
+    (0xFF25, 0xF3), // NR51
+    (0xFF24, 0x77), // NR50
+    (0xFF47, 0xFC), // BGP
+    (0xFF40, 0x91), // LCDC
+    (0xFF50, 0x01), // Boot ROM disable
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccuracyMode {
+    /// Favors speed, rendering each scanline all at once
+    Fast,
+    /// Favors matching hardware timing more closely, at the cost of speed, drawing pixels as each
+    /// scanline progresses so that register writes part way through a scanline are visible
+    Accurate,
+}
+
+/// Whether the CPU is executing instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuStatus {
+    Running,
+    /// Real hardware locks up when it executes an invalid opcode, leaving only the rest of the
+    /// system running
+    Locked {
+        address: u16,
+        opcode: u8,
+    },
+}
+
+/// The reason execution stopped at a breakpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// The instruction pointer reached this address, or a breakpoint instruction was executed
+    Address(u16),
+    /// The cycle count reached this many M-cycles
+    Cycle(u64),
+    /// The instruction count reached this many instructions
+    Instruction(u64),
+}
+
+/// What happened during a call to [`Emulator::step`] or [`Emulator::run_frame`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StepOutcome {
+    /// The number of M-cycles that passed
+    pub cycles: usize,
+    /// Whether the PPU finished a frame
+    pub new_frame: bool,
+    /// The breakpoint execution stopped at afterwards, if any
+    pub breakpoint: Option<Breakpoint>,
+    /// The interrupts requested by the peripherals, in the layout of the IF register
+    requested_interrupts: u8,
+}
+
+impl StepOutcome {
+    /// Whether the interrupt was requested during the step, whether or not it is enabled
+    pub fn interrupt_requested(&self, interrupt: Interrupt) -> bool {
+        (self.requested_interrupts & interrupt.mask()) != 0
+    }
+
+    /// Adds on the outcome of stepping again straight afterwards
+    fn extend(&mut self, next: StepOutcome) {
+        self.cycles += next.cycles;
+        self.new_frame |= next.new_frame;
+        self.requested_interrupts |= next.requested_interrupts;
+    }
+}
+
+/// The interrupt registers and IME, for debugging views
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterruptState {
+    /// The interrupts enabled in IE
+    pub enabled: InterruptFlags,
+    /// The interrupts requested in IF
+    pub requested: InterruptFlags,
+    /// The interrupt master enable flag, without which no interrupt is serviced
+    pub master_enable: bool,
+}
+
+impl InterruptState {
+    /// The interrupts both enabled and requested
+    pub fn pending(&self) -> InterruptFlags {
+        InterruptFlags::new(self.enabled.bits() & self.requested.bits())
+    }
+
+    /// The interrupt the CPU will service next, if IME allows any to be serviced
+    pub fn next_interrupt(&self) -> Option<Interrupt> {
+        if self.master_enable {
+            self.pending().highest_priority()
+        } else {
+            None
+        }
+    }
+}
+
+/// Decides whether a conditional breakpoint fires, given the state at the breakpoint's address
+pub type BreakpointCondition = Box<dyn Fn(&ExecutionState) -> bool>;
+
+/// Called with the finished frame's pixels every time the PPU completes a frame
+pub type FrameCallback = Box<dyn FnMut(&[Color32])>;
 
 pub struct Emulator {
     cpu: Cpu,
+    boot_rom: BootRom,
+    skip_boot: bool,
     breakpoints: Vec<u16>,
+    breakpoint_addresses: AddressSet,
+    conditional_breakpoints: Vec<(u16, BreakpointCondition)>,
+    cycle_breakpoints: Vec<u64>,
+    instruction_breakpoints: Vec<u64>,
+    counter_breakpoint_hit: Option<Breakpoint>,
+    accuracy: AccuracyMode,
+    frame_callback: Option<FrameCallback>,
+    cycle_count: u64,
+    lock_on_invalid_opcode: bool,
+    debug_instructions: bool,
+    stack_checks: bool,
+    capture_sgb_packets: bool,
+    bypass_logo_check: bool,
+    fast_forward_halt: bool,
+    cpu_status: CpuStatus,
+    symbols: SymbolTable,
+    clock: Box<dyn Clock>,
 }
 
 impl Emulator {
@@ -26,47 +172,562 @@ impl Emulator {
 
         Self {
             cpu: Cpu::new(bus, false),
+            boot_rom,
+            skip_boot: false,
             breakpoints: Vec::new(),
+            breakpoint_addresses: AddressSet::new(),
+            conditional_breakpoints: Vec::new(),
+            cycle_breakpoints: Vec::new(),
+            instruction_breakpoints: Vec::new(),
+            counter_breakpoint_hit: None,
+            accuracy: AccuracyMode::Fast,
+            frame_callback: None,
+            cycle_count: 0,
+            lock_on_invalid_opcode: false,
+            debug_instructions: false,
+            stack_checks: false,
+            capture_sgb_packets: false,
+            bypass_logo_check: false,
+            fast_forward_halt: false,
+            cpu_status: CpuStatus::Running,
+            symbols: SymbolTable::new(),
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    pub fn builder() -> EmulatorBuilder {
+        EmulatorBuilder::new()
+    }
+
+    /// The source of wall clock time for anything timed in real time, such as a cartridge's real
+    /// time clock, rather than in emulated cycles
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Box::new(clock);
+    }
+
+    pub fn accuracy(&self) -> AccuracyMode {
+        self.accuracy
+    }
+
+    pub fn set_accuracy(&mut self, accuracy: AccuracyMode) {
+        self.accuracy = accuracy;
+        self.cpu
+            .bus_mut()
+            .ppu_mut()
+            .set_accurate_rendering(accuracy == AccuracyMode::Accurate);
+    }
+
+    /// Swaps in a new cartridge and resets the machine, as if it was powered on with it inserted
+    ///
+    /// The boot ROM, breakpoints and other options the emulator was configured with are kept,
+    /// while the symbols loaded for the old cartridge are cleared. Cycle and instruction
+    /// breakpoints that weren't reached yet count from the reset counters.
+    pub fn load_cartridge(&mut self, cartridge: Cartridge) {
+        let allow_opposing_directions = self.cpu.bus().io().joypad().allow_opposing_directions();
+        let lfsr_seed = self.cpu.bus().io().audio().channel_4().lfsr_seed();
+        let write_log_capacity = self.cpu.write_log_capacity();
+
+        self.cpu = Cpu::new(Bus::new(self.boot_rom, cartridge), self.debug_instructions);
+        self.cycle_count = 0;
+        self.counter_breakpoint_hit = None;
+        self.cpu_status = CpuStatus::Running;
+        self.symbols = SymbolTable::new();
+
+        self.joypad()
+            .set_allow_opposing_directions(allow_opposing_directions);
+        self.cpu
+            .bus_mut()
+            .io_mut()
+            .audio_mut()
+            .channel_4_mut()
+            .set_lfsr_seed(lfsr_seed);
+
+        if let Some(capacity) = write_log_capacity {
+            self.cpu.enable_write_log(capacity);
+        }
+
+        self.apply_configuration();
+    }
+
+    /// Changes the boot ROM, which is used from the next time a cartridge is loaded
+    pub fn set_boot_rom(&mut self, boot_rom: BootRom) {
+        self.boot_rom = boot_rom;
+    }
+
+    /// Changes whether the boot ROM is skipped, from the next time a cartridge is loaded
+    pub fn set_skip_boot(&mut self, skip_boot: bool) {
+        self.skip_boot = skip_boot;
+    }
+
+    /// Changes whether the boot ROM is shown the Nintendo logo in place of the cartridge's, see
+    /// [`EmulatorBuilder::bypass_logo_check`]
+    pub fn set_bypass_logo_check(&mut self, bypass: bool) {
+        self.bypass_logo_check = bypass;
+        self.cpu.bus_mut().set_bypass_logo_check(bypass);
+    }
+
+    /// Applies the configured options which live in the machine's components
+    fn apply_configuration(&mut self) {
+        self.cpu.set_debug_instructions(self.debug_instructions);
+        self.cpu.set_stack_checks(self.stack_checks);
+        let capture_sgb_packets = self.capture_sgb_packets;
+        self.joypad().set_capture_sgb_packets(capture_sgb_packets);
+        self.cpu.bus_mut().set_bypass_logo_check(self.bypass_logo_check);
+
+        self.set_accuracy(self.accuracy);
+
+        if self.skip_boot {
+            self.skip_boot()
+                .expect("Writing the post-boot IO registers should never fail");
+        }
+    }
+
+    /// Puts the machine in the state the DMG boot ROM leaves it in, with execution at 0x0100
+    fn skip_boot(&mut self) -> Result<(), EmulatorError> {
+        let state = self.cpu.execution_state_mut();
+        state.set_reg_af(0x01B0);
+        state.set_reg_bc(0x0013);
+        state.set_reg_de(0x00D8);
+        state.set_reg_hl(0x014D);
+        state.set_stack_pointer(0xFFFE);
+        state.set_instruction_pointer(0x0100);
+
+        for (address, value) in POST_BOOT_IO_REGISTERS {
+            self.cpu.bus_mut().write_u8(address, value)?;
         }
+
+        Ok(())
+    }
+
+    /// Seeds everything in the emulator that behaves randomly, so that runs can be reproduced
+    ///
+    /// Currently this is the initial value of the noise channel's LFSR.
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        let folded = seed ^ (seed >> 16) ^ (seed >> 32) ^ (seed >> 48);
+
+        self.cpu
+            .bus_mut()
+            .io_mut()
+            .audio_mut()
+            .channel_4_mut()
+            .set_lfsr_seed(folded as u16);
+    }
+
+    /// Installs a callback invoked from within `step` whenever a frame completes
+    pub fn set_frame_callback(&mut self, callback: FrameCallback) {
+        self.frame_callback = Some(callback);
+    }
+
+    pub fn clear_frame_callback(&mut self) {
+        self.frame_callback = None;
+    }
+
+    /// Lets games see opposing directions, such as left and right, pressed at the same time
+    ///
+    /// This can't happen on a real d-pad and some games misbehave when it does.
+    pub fn set_allow_opposing_directions(&mut self, allow: bool) {
+        self.joypad().set_allow_opposing_directions(allow);
     }
 
     pub fn add_breakpoint(&mut self, address: u16) {
-        self.breakpoints.push(address);
+        if self.breakpoint_addresses.insert(address) {
+            self.breakpoints.push(address);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        self.breakpoint_addresses.remove(address);
+        self.breakpoints.retain(|&breakpoint| breakpoint != address);
+    }
+
+    /// Removes every address breakpoint, leaving conditional and counter breakpoints in place
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoint_addresses.clear();
+        self.breakpoints.clear();
+    }
+
+    /// The address breakpoints, in the order they were added
+    pub fn breakpoints(&self) -> &[u16] {
+        &self.breakpoints
+    }
+
+    /// Breaks at `address` only when `predicate` returns true for the execution state there
+    pub fn add_conditional_breakpoint(&mut self, address: u16, predicate: BreakpointCondition) {
+        self.conditional_breakpoints.push((address, predicate));
+    }
+
+    /// Breaks once the cycle count reaches `cycle`, which may be part way through an instruction
+    pub fn add_cycle_breakpoint(&mut self, cycle: u64) {
+        self.cycle_breakpoints.push(cycle);
+    }
+
+    /// Breaks once the instruction count reaches `count`
+    pub fn add_instruction_breakpoint(&mut self, count: u64) {
+        self.instruction_breakpoints.push(count);
+    }
+
+    /// Starts recording the last `capacity` memory writes made by the CPU, for tracking down
+    /// where corrupted memory came from
+    pub fn enable_write_log(&mut self, capacity: usize) {
+        self.cpu.enable_write_log(capacity);
+    }
+
+    pub fn disable_write_log(&mut self) {
+        self.cpu.disable_write_log();
+    }
+
+    /// The most recent memory writes made by the CPU, oldest first, if the write log is enabled
+    pub fn recent_writes(&self) -> &[WriteLogEntry] {
+        self.cpu.recent_writes()
     }
 
     pub fn execution_state(&self) -> &ExecutionState {
         self.cpu.execution_state()
     }
 
-    pub fn step(&mut self, input_state: InputState) -> Result<(usize, bool), Error> {
-        let cycles = self.cpu.step()?;
+    /// Total instructions executed since the emulator was reset
+    pub fn instruction_count(&self) -> u64 {
+        self.cpu.instruction_count()
+    }
+
+    /// Total M-cycles elapsed since the emulator was reset
+    pub fn cycle_count(&self) -> u64 {
+        self.cycle_count
+    }
+
+    /// Reads memory for debugging purposes, without affecting the state of the emulator
+    pub fn peek_u8(&self, address: u16) -> u8 {
+        self.cpu.bus().debug_read_u8(address)
+    }
+
+    pub fn cpu_status(&self) -> CpuStatus {
+        self.cpu_status
+    }
+
+    /// The interrupt master enable flag, without which no interrupt is serviced
+    pub fn ime(&self) -> bool {
+        self.cpu.execution_state().interrupts_enabled()
+    }
+
+    /// Sets the interrupt master enable flag, taking effect before the next instruction rather
+    /// than after it as `EI` does
+    pub fn set_ime(&mut self, enabled: bool) {
+        self.cpu.set_ime(enabled);
+    }
+
+    /// The IE and IF registers and IME, see [`InterruptState`]
+    pub fn interrupt_state(&self) -> InterruptState {
+        let interrupts = self.cpu.bus().io().interrupts();
+
+        InterruptState {
+            enabled: interrupts.enabled(),
+            requested: interrupts.requested(),
+            master_enable: self.ime(),
+        }
+    }
+
+    /// Whether the boot ROM is mapped over the start of the cartridge at 0x0000-0x00FF
+    pub fn boot_rom_mapped(&self) -> bool {
+        self.cpu.bus().boot_rom_mapped()
+    }
+
+    /// Maps or unmaps the boot ROM directly, as writing to 0xFF50 does
+    pub fn set_boot_rom_mapped(&mut self, mapped: bool) {
+        self.cpu.bus_mut().set_boot_rom_mapped(mapped);
+    }
+
+    /// Whether the LCD is switched on (LCDC bit 7), without which no frames are finished
+    pub fn lcd_enabled(&self) -> bool {
+        self.cpu.bus().io().lcd().control().lcd_enabled()
+    }
+
+    pub fn ppu_mode(&self) -> PpuMode {
+        self.cpu.bus().io().lcd().status().ppu_mode()
+    }
+
+    /// The LCD registers, such as the scroll and window positions, for debugging views
+    pub fn lcd(&self) -> &Lcd {
+        self.cpu.bus().io().lcd()
+    }
+
+    /// Draws the whole of tile map 0 or 1 as the background would show it, see
+    /// [`Ppu::render_background_map`](ppu::Ppu::render_background_map)
+    pub fn render_background_map(&self, map: u8) -> ColorImage {
+        let bus = self.cpu.bus();
+        bus.ppu().render_background_map(bus.io().lcd(), map)
+    }
+
+    /// The scanline the PPU is on (LY), which is 144 or more during VBlank
+    pub fn scanline(&self) -> u8 {
+        self.cpu.bus().io().lcd().read_lcd_y()
+    }
+
+    /// Loads labels from an RGBDS or BGB `.sym` file, replacing any loaded before
+    pub fn load_symbols<P>(&mut self, path: P) -> Result<(), EmulatorError>
+    where
+        P: AsRef<Path>,
+    {
+        let text = std::fs::read_to_string(path)?;
+        self.symbols = SymbolTable::parse(&text);
+        Ok(())
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// The instruction at an address in RGBDS syntax, with labels for any known jump targets
+    pub fn disassemble(&self, address: u16) -> Result<String, EmulatorError> {
+        let instruction = self.cpu.decode_at(address)?;
+        Ok(disassemble(
+            &instruction,
+            address,
+            &self.symbols,
+            self.mapped_rom_bank(),
+        ))
+    }
+
+    /// The number of the ROM bank mapped at 0x4000-0x7FFF, which symbols there are looked up in
+    fn mapped_rom_bank(&self) -> usize {
+        self.cpu.bus().cartridge().rom_banks().1
+    }
+
+    /// A line describing the instruction about to be executed, for logging a trace
+    ///
+    /// It starts with the M-cycles run so far and the instruction's base cycle cost, so timing
+    /// problems show up in the log, followed by the instruction and the CPU state.
+    pub fn trace_line(&self) -> Result<String, EmulatorError> {
+        let address = self.cpu.execution_state().instruction_pointer();
+        let instruction = self.cpu.decode_at(address)?;
+
+        Ok(format!(
+            "{:>10} +{} {:04X}: {:<16} {}",
+            self.cycle_count,
+            instruction.base_num_cycles(),
+            address,
+            disassemble(&instruction, address, &self.symbols, self.mapped_rom_bank()),
+            self.cpu.execution_state()
+        ))
+    }
+
+    pub fn cartridge_header(&self) -> &CartridgeHeader {
+        self.cpu.bus().cartridge().header()
+    }
+
+    /// Whether the cartridge asked for CGB features, going by its header's CGB flag
+    pub fn cgb_mode(&self) -> bool {
+        self.cpu.bus().cgb_mode()
+    }
+
+    pub fn step(&mut self, input_state: InputState) -> Result<StepOutcome, EmulatorError> {
+        let mut outcome = StepOutcome::default();
 
+        // The joypad is updated before the CPU runs, so an instruction reading it sees this input
+        // rather than the previous step's
         if self.joypad().step(input_state) {
-            self.interrupts()
-                .set_interrupt_requested(io::interrupts::Interrupt::Joypad);
+            self.request_interrupt(Interrupt::Joypad, &mut outcome);
+        }
+
+        let cycles = self.step_cpu()?;
+        outcome.extend(self.step_peripherals(cycles)?);
+
+        // Nothing but the peripherals changes until a halted CPU is woken by an interrupt request,
+        // so the wait is run here rather than returning after every cycle of it. It is cut off
+        // after a frame's worth of cycles, in case nothing ever wakes the CPU
+        while self.fast_forward_halt
+            && self.idle()
+            && self.halt_can_end()
+            && !outcome.new_frame
+            && outcome.cycles < CYCLES_PER_FRAME
+        {
+            let halted_cycles = self.step_cpu()?;
+            outcome.extend(self.step_peripherals(halted_cycles)?);
         }
+
+        outcome.breakpoint = self.breakpoint_reached();
+
+        Ok(outcome)
+    }
+
+    fn step_cpu(&mut self) -> Result<usize, EmulatorError> {
+        let cycles = match self.cpu_status {
+            CpuStatus::Locked { .. } => 1,
+            CpuStatus::Running => match self.cpu.step() {
+                Err(Error::InvalidInstruction(address, opcode)) if self.lock_on_invalid_opcode => {
+                    self.cpu_status = CpuStatus::Locked { address, opcode };
+                    1
+                }
+                result => result?,
+            },
+        };
+        self.cycle_count += cycles as u64;
+        self.counter_breakpoint_hit = self.check_counter_breakpoints();
+
+        Ok(cycles)
+    }
+
+    /// Steps everything but the CPU by the cycles the CPU just took
+    fn step_peripherals(&mut self, cycles: usize) -> Result<StepOutcome, EmulatorError> {
+        let mut outcome = StepOutcome {
+            cycles,
+            ..Default::default()
+        };
+
         if self.timer().step(cycles) {
-            self.interrupts()
-                .set_interrupt_requested(io::interrupts::Interrupt::Timer);
+            self.request_interrupt(Interrupt::Timer, &mut outcome);
+        }
+        self.cpu.bus_mut().io_mut().audio_mut().step(cycles);
+        if self.serial().step(cycles) {
+            self.request_interrupt(Interrupt::Serial, &mut outcome);
         }
 
-        let (vblank, lcd, new_frame) = self.cpu.bus_mut().step_ppu(cycles);
+        let (vblank, lcd, new_frame) = self.cpu.bus_mut().step_ppu(cycles)?;
 
         if let Some(vblank) = vblank {
-            self.interrupts().set_interrupt_requested(vblank);
+            self.request_interrupt(vblank, &mut outcome);
         }
 
         if let Some(lcd) = lcd {
-            self.interrupts().set_interrupt_requested(lcd);
+            self.request_interrupt(lcd, &mut outcome);
+        }
+
+        if new_frame {
+            if let Some(callback) = &mut self.frame_callback {
+                callback(self.cpu.bus_mut().render());
+            }
+        }
+
+        outcome.new_frame = new_frame;
+
+        Ok(outcome)
+    }
+
+    fn request_interrupt(&mut self, interrupt: Interrupt, outcome: &mut StepOutcome) {
+        self.interrupts().set_interrupt_requested(interrupt);
+        outcome.requested_interrupts |= interrupt.mask();
+    }
+
+    /// Whether the CPU is halted with nothing to wake it, and no breakpoint to stop for
+    fn idle(&self) -> bool {
+        let interrupt_requested = self
+            .cpu
+            .bus()
+            .io()
+            .interrupts()
+            .highest_priority_triggered_interrupt()
+            .is_some();
+
+        self.cpu.halted() && !interrupt_requested && self.counter_breakpoint_hit.is_none()
+    }
+
+    /// Whether anything could end a halt, either an enabled interrupt or the LCD finishing a
+    /// frame, which a `DI` then `HALT` with the LCD off and IE cleared never has
+    fn halt_can_end(&self) -> bool {
+        let interrupts_enabled = !self.cpu.bus().io().interrupts().enabled().is_empty();
+
+        interrupts_enabled || self.lcd_enabled()
+    }
+
+    /// Runs until the PPU finishes a frame, returning the M-cycles it took and the interrupts
+    /// requested along the way
+    ///
+    /// While the LCD is off no frames are finished, so this instead stops after a frame's worth
+    /// of cycles. Stops early if a breakpoint is reached, which the outcome reports.
+    pub fn run_frame(&mut self, input_state: InputState) -> Result<StepOutcome, EmulatorError> {
+        let mut frame = StepOutcome::default();
+
+        loop {
+            let outcome = self.step(input_state)?;
+            frame.cycles += outcome.cycles;
+            frame.new_frame = outcome.new_frame;
+            frame.breakpoint = outcome.breakpoint;
+            frame.requested_interrupts |= outcome.requested_interrupts;
+
+            if outcome.new_frame || outcome.breakpoint.is_some() {
+                return Ok(frame);
+            }
+
+            if !self.lcd_enabled() && (frame.cycles >= CYCLES_PER_FRAME) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Runs until the instruction pointer reaches `address`, or `max_cycles` M-cycles have passed
+    ///
+    /// Returns whether the address was reached. Unless `max_cycles` is 0, which executes nothing,
+    /// at least one instruction is executed, so calling this again runs to the next time the
+    /// address is reached. Breakpoints are not checked.
+    pub fn run_until(&mut self, address: u16, max_cycles: u64) -> Result<bool, EmulatorError> {
+        let mut cycles_done = 0;
+
+        while cycles_done < max_cycles {
+            cycles_done += self.step(InputState::empty())?.cycles as u64;
+
+            if self.cpu.execution_state().instruction_pointer() == address {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Executes up to `count` instructions, returning the execution state after each one
+    ///
+    /// Stops early if a breakpoint is reached, in which case the last state is at the breakpoint.
+    pub fn run_instructions(
+        &mut self,
+        count: usize,
+        input_state: InputState,
+    ) -> Result<Vec<ExecutionState>, EmulatorError> {
+        let mut trace = Vec::new();
+
+        for _ in 0..count {
+            let outcome = self.step(input_state)?;
+            trace.push(*self.execution_state());
+
+            if outcome.breakpoint.is_some() {
+                break;
+            }
         }
 
-        Ok((cycles, new_frame))
+        Ok(trace)
     }
 
     fn timer(&mut self) -> &mut Timer {
         self.cpu.bus_mut().io_mut().timer_mut()
     }
 
+    fn serial(&mut self) -> &mut Serial {
+        self.cpu.bus_mut().io_mut().serial_mut()
+    }
+
+    /// Drains the bytes sent over the serial port so far, which test ROMs use to report results
+    pub fn take_serial_output(&mut self) -> String {
+        self.serial().take_output().into_iter().map(char::from).collect()
+    }
+
+    /// Drains the messages the game has logged with `LD D,D`, if debug instructions are enabled
+    pub fn take_debug_messages(&mut self) -> Vec<String> {
+        self.cpu.take_debug_messages()
+    }
+
+    /// Drains the warnings logged so far by pushes and pops that moved the stack pointer out of
+    /// RAM, if stack checks are enabled
+    pub fn take_stack_warnings(&mut self) -> Vec<String> {
+        self.cpu.take_stack_warnings()
+    }
+
+    /// Drains the Super Game Boy command packets the game has sent through the joypad register,
+    /// if capturing them is enabled
+    pub fn take_sgb_packets(&mut self) -> Vec<[u8; SGB_PACKET_LENGTH]> {
+        self.joypad().take_sgb_packets()
+    }
+
     fn joypad(&mut self) -> &mut JoypadInput {
         self.cpu.bus_mut().io_mut().joypad_mut()
     }
@@ -75,41 +736,231 @@ impl Emulator {
         self.cpu.bus_mut().io_mut().interrupts_mut()
     }
 
+    /// The last completed frame, which is safe to show even while the next one is being drawn
     pub fn get_pixels(&mut self) -> &[Color32] {
         self.cpu.bus_mut().render()
     }
 
-    pub fn breakpoint_reached(&self) -> Option<u16> {
+    /// The last completed frame as tightly packed RGBA bytes, for frontends that upload to a texture
+    pub fn frame_rgba(&self) -> &[u8] {
+        self.cpu.bus().frame_rgba()
+    }
+
+    /// The last completed frame's background and window color ids, before the palette is applied,
+    /// see [`Ppu::frame_color_ids`](ppu::Ppu::frame_color_ids)
+    pub fn frame_color_ids(&self) -> &[ColorId; TOTAL_PIXELS] {
+        self.cpu.bus().frame_color_ids()
+    }
+
+    /// The contents of work RAM, with each 4 KiB bank following the last
+    ///
+    /// This is 8 KiB on the DMG, and all 8 banks (32 KiB) in CGB mode.
+    pub fn dump_wram(&self) -> &[u8] {
+        self.cpu.bus().work_ram().banks()
+    }
+
+    /// Removes and returns any cycle or instruction breakpoint that the counters have reached
+    ///
+    /// Breakpoints are removed once hit. The counters only go backwards when
+    /// [`load_cartridge`](Self::load_cartridge) resets them, and breakpoints not reached by then
+    /// are kept and count from the reset.
+    fn check_counter_breakpoints(&mut self) -> Option<Breakpoint> {
+        let cycle_count = self.cycle_count;
+        let instruction_count = self.instruction_count();
+        let mut hit = None;
+
+        self.cycle_breakpoints.retain(|&cycle| {
+            let reached = cycle <= cycle_count;
+            if reached {
+                hit = Some(Breakpoint::Cycle(cycle));
+            }
+            !reached
+        });
+
+        self.instruction_breakpoints.retain(|&count| {
+            let reached = count <= instruction_count;
+            if reached {
+                hit = Some(Breakpoint::Instruction(count));
+            }
+            !reached
+        });
+
+        hit
+    }
+
+    pub fn breakpoint_reached(&self) -> Option<Breakpoint> {
+        if let Some(breakpoint) = self.counter_breakpoint_hit {
+            return Some(breakpoint);
+        }
+
         let pc = self.cpu.execution_state().instruction_pointer();
 
-        for breakpoint in &self.breakpoints {
-            if *breakpoint == pc {
-                return Some(*breakpoint);
+        if self.breakpoint_addresses.contains(pc) {
+            return Some(Breakpoint::Address(pc));
+        }
+
+        let state = self.cpu.execution_state();
+
+        for (address, predicate) in &self.conditional_breakpoints {
+            if *address == pc && predicate(state) {
+                return Some(Breakpoint::Address(*address));
             }
         }
 
         if self.cpu.hit_breakpoint_instruction() {
-            return Some(pc);
+            return Some(Breakpoint::Address(pc));
         }
 
         None
     }
 }
 
-pub fn read_cartridge<P>(path: P) -> Cartridge
+pub struct EmulatorBuilder {
+    boot_rom: BootRom,
+    cartridge: Option<Cartridge>,
+    skip_boot: bool,
+    accuracy: AccuracyMode,
+    lock_on_invalid_opcode: bool,
+    debug_instructions: bool,
+    stack_checks: bool,
+    capture_sgb_packets: bool,
+    bypass_logo_check: bool,
+    fast_forward_halt: bool,
+    clock: Option<Box<dyn Clock>>,
+}
+
+impl EmulatorBuilder {
+    pub fn new() -> Self {
+        Self {
+            boot_rom: DEFAULT_BOOT_ROM,
+            cartridge: None,
+            skip_boot: false,
+            accuracy: AccuracyMode::Fast,
+            lock_on_invalid_opcode: false,
+            debug_instructions: false,
+            stack_checks: false,
+            capture_sgb_packets: false,
+            bypass_logo_check: false,
+            fast_forward_halt: false,
+            clock: None,
+        }
+    }
+
+    pub fn with_boot_rom(mut self, boot_rom: BootRom) -> Self {
+        self.boot_rom = boot_rom;
+        self
+    }
+
+    pub fn with_cartridge(mut self, cartridge: Cartridge) -> Self {
+        self.cartridge = Some(cartridge);
+        self
+    }
+
+    /// Starts execution directly at 0x0100 instead of running the boot ROM
+    pub fn skip_boot(mut self, skip_boot: bool) -> Self {
+        self.skip_boot = skip_boot;
+        self
+    }
+
+    pub fn accuracy(mut self, accuracy: AccuracyMode) -> Self {
+        self.accuracy = accuracy;
+        self
+    }
+
+    /// Locks up the CPU on an invalid opcode like real hardware, reported through `cpu_status`,
+    /// instead of returning an error from `step`
+    pub fn lock_on_invalid_opcode(mut self, lock: bool) -> Self {
+        self.lock_on_invalid_opcode = lock;
+        self
+    }
+
+    /// Recognizes the debugging conventions used by test ROMs and homebrew: `LD B,B` is a
+    /// breakpoint, and `LD D,D` logs the message that follows it, read with `take_debug_messages`
+    pub fn debug_instructions(mut self, enabled: bool) -> Self {
+        self.debug_instructions = enabled;
+        self
+    }
+
+    /// Warns when a push or pop moves the stack pointer out of RAM, such as into ROM or the IO
+    /// registers, which usually means runaway recursion. Read with `take_stack_warnings`
+    pub fn stack_checks(mut self, enabled: bool) -> Self {
+        self.stack_checks = enabled;
+        self
+    }
+
+    /// Keeps the Super Game Boy command packets the game sends, read with `take_sgb_packets`
+    pub fn capture_sgb_packets(mut self, enabled: bool) -> Self {
+        self.capture_sgb_packets = enabled;
+        self
+    }
+
+    /// Shows the boot ROM the Nintendo logo instead of the cartridge's, so homebrew without it
+    /// still boots with the DMG boot ROM, which otherwise locks up on a logo mismatch
+    pub fn bypass_logo_check(mut self, bypass: bool) -> Self {
+        self.bypass_logo_check = bypass;
+        self
+    }
+
+    /// Runs the whole of a `HALT` in one call to `step`, instead of one call per cycle spent
+    /// halted, which speeds up games that wait for VBlank with it
+    ///
+    /// Emulation is unaffected, but `step` then only returns when the CPU is woken, a frame is
+    /// finished, a frame's worth of cycles has passed or a cycle or instruction breakpoint is
+    /// reached. A halt nothing can end, with the LCD off and no interrupts enabled, isn't
+    /// fast-forwarded.
+    pub fn fast_forward_halt(mut self, enabled: bool) -> Self {
+        self.fast_forward_halt = enabled;
+        self
+    }
+
+    /// Uses the given clock for wall clock time instead of the system's, such as a
+    /// [`MockClock`](clock::MockClock) to make tests deterministic
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Box::new(clock));
+        self
+    }
+
+    pub fn build(self) -> Emulator {
+        let cartridge = self.cartridge.unwrap_or_else(Cartridge::empty);
+
+        let mut emulator = Emulator::new(self.boot_rom, cartridge);
+        emulator.accuracy = self.accuracy;
+        emulator.lock_on_invalid_opcode = self.lock_on_invalid_opcode;
+        emulator.debug_instructions = self.debug_instructions;
+        emulator.stack_checks = self.stack_checks;
+        emulator.capture_sgb_packets = self.capture_sgb_packets;
+        emulator.bypass_logo_check = self.bypass_logo_check;
+        emulator.fast_forward_halt = self.fast_forward_halt;
+        emulator.skip_boot = self.skip_boot;
+        if let Some(clock) = self.clock {
+            emulator.clock = clock;
+        }
+        emulator.apply_configuration();
+
+        emulator
+    }
+}
+
+impl Default for EmulatorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn read_cartridge<P>(path: P) -> Result<Cartridge, EmulatorError>
 where
     P: AsRef<Path>,
 {
-    let mut cartridge_file = std::fs::File::open(path).unwrap();
-    Cartridge::read(&mut cartridge_file).unwrap()
+    let mut cartridge_file = std::fs::File::open(path)?;
+    Ok(Cartridge::read(&mut cartridge_file)?)
 }
 
-pub fn read_boot_rom<P>(path: P) -> BootRom
+pub fn read_boot_rom<P>(path: P) -> Result<BootRom, EmulatorError>
 where
     P: AsRef<Path>,
 {
-    let mut boot_rom_file = std::fs::File::open(path).unwrap();
-    BootRomReader::read(&mut boot_rom_file).unwrap()
+    let mut boot_rom_file = std::fs::File::open(path)?;
+    Ok(BootRomReader::read(&mut boot_rom_file)?)
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -219,7 +1070,9 @@ pub struct InputState {
     pub b_pressed: bool,
     pub start_pressed: bool,
     pub select_pressed: bool,
-    pub dpad_state: DPadState,
+    /// The directions held, including opposing ones, which only cancel out in
+    /// [`dpad_state`](Self::dpad_state)
+    pub dpad_buttons: DPadButtonState,
 }
 
 impl InputState {
@@ -229,7 +1082,1243 @@ impl InputState {
             b_pressed: false,
             start_pressed: false,
             select_pressed: false,
-            dpad_state: DPadState::None,
+            dpad_buttons: DPadButtonState::empty(),
         }
     }
+
+    /// The direction the D-pad is pressed in, with opposing directions cancelled out
+    pub fn dpad_state(&self) -> DPadState {
+        DPadState::from_buttons(self.dpad_buttons)
+    }
+
+    /// An input state with the given buttons and directions held
+    ///
+    /// Opposing directions cancel out in the D-pad state, the same way they do on hardware.
+    pub fn from_keys(
+        a: bool,
+        b: bool,
+        start: bool,
+        select: bool,
+        dpad_buttons: DPadButtonState,
+    ) -> Self {
+        Self::empty()
+            .with_a(a)
+            .with_b(b)
+            .with_start(start)
+            .with_select(select)
+            .with_dpad_buttons(dpad_buttons)
+    }
+
+    pub fn with_a(mut self, pressed: bool) -> Self {
+        self.a_pressed = pressed;
+        self
+    }
+
+    pub fn with_b(mut self, pressed: bool) -> Self {
+        self.b_pressed = pressed;
+        self
+    }
+
+    pub fn with_start(mut self, pressed: bool) -> Self {
+        self.start_pressed = pressed;
+        self
+    }
+
+    pub fn with_select(mut self, pressed: bool) -> Self {
+        self.select_pressed = pressed;
+        self
+    }
+
+    /// Sets the directions held to the ones that make up the D-pad state
+    pub fn with_dpad(mut self, state: DPadState) -> Self {
+        self.dpad_buttons = DPadButtonState::new(
+            state.is_up(),
+            state.is_down(),
+            state.is_left(),
+            state.is_right(),
+        );
+        self
+    }
+
+    /// Sets the directions held, which may include opposing ones
+    pub fn with_dpad_buttons(mut self, buttons: DPadButtonState) -> Self {
+        self.dpad_buttons = buttons;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, time::Duration};
+
+    use crate::{
+        boot::{BootRomReader, CGB_BOOT_ROM_SIZE, DEFAULT_BOOT_ROM, OPEN_SOURCE_BOOT_ROM},
+        cartridge::{
+            header::{CartridgeType, CgbFlag, RamSize, RomSize, NINTENDO_LOGO},
+            Cartridge,
+        },
+        clock::MockClock,
+        cpu::error::Error,
+        error::EmulatorError,
+        io::interrupts::{Interrupt, InterruptFlags},
+        ppu::{
+            oam::ObjectAttributeMemory, PpuMode, DARKEST_COLOR, DISPLAY_HEIGHT_PIXELS,
+            DISPLAY_WIDTH_PIXELS, FRAME_CYCLES_LENGTH,
+        },
+        AccuracyMode, Breakpoint, CpuStatus, DPadButtonState, DPadState, Emulator, EmulatorBuilder,
+        InputState, CPU_CLOCK_HZ, CYCLES_PER_FRAME, FRAMES_PER_SECOND,
+    };
+
+    /// Writes a program into memory and starts executing from its first instruction
+    fn load_program(emulator: &mut Emulator, address: u16, program: &[u8]) {
+        for (i, byte) in program.iter().enumerate() {
+            let bus = emulator.cpu.bus_mut();
+            bus.write_u8(address + i as u16, *byte).unwrap();
+        }
+
+        emulator
+            .cpu
+            .execution_state_mut()
+            .set_instruction_pointer(address);
+    }
+
+    /// Builds a 32 KiB ROM-only cartridge image with a valid header checksum
+    fn crafted_rom(title: &str) -> Vec<u8> {
+        let mut rom = vec![0u8; 32 * 1024];
+        rom[0x0134..0x0134 + title.len()].copy_from_slice(title.as_bytes());
+        rom[0x0143] = 0x80;
+        rom[0x014C] = 0x02;
+
+        let mut checksum: u8 = 0;
+        for b in &rom[0x0134..=0x014C] {
+            checksum = checksum.wrapping_sub(*b).wrapping_sub(1);
+        }
+        rom[0x014D] = checksum;
+
+        rom
+    }
+
+    fn cgb_emulator() -> Emulator {
+        let rom = crafted_rom("CGB");
+        let cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+
+        EmulatorBuilder::new()
+            .with_cartridge(cartridge)
+            .skip_boot(true)
+            .build()
+    }
+
+    /// Fills work RAM from 0xC000 with distinct bytes and points VRAM DMA from there to `dest`
+    fn setup_vram_dma(emulator: &mut Emulator, length: u16, destination: u16) {
+        let bus = emulator.cpu.bus_mut();
+
+        for i in 0..length {
+            bus.write_u8(0xC000 + i, (i as u8).wrapping_add(1)).unwrap();
+        }
+
+        bus.write_u8(0xFF51, 0xC0).unwrap();
+        bus.write_u8(0xFF52, 0x00).unwrap();
+        bus.write_u8(0xFF53, (destination >> 8) as u8).unwrap();
+        bus.write_u8(0xFF54, destination as u8).unwrap();
+    }
+
+    fn vram_matches_source(emulator: &Emulator, length: u16, destination: u16) -> bool {
+        (0..length).all(|i| emulator.peek_u8(destination + i) == (i as u8).wrapping_add(1))
+    }
+
+    #[test]
+    fn builder_skip_boot() {
+        let emulator = EmulatorBuilder::new()
+            .skip_boot(true)
+            .accuracy(AccuracyMode::Accurate)
+            .build();
+
+        let state = emulator.execution_state();
+        assert_eq!(state.instruction_pointer(), 0x0100);
+        assert_eq!(state.stack_pointer(), 0xFFFE);
+        assert_eq!(state.reg_af(), 0x01B0);
+        assert_eq!(emulator.accuracy(), AccuracyMode::Accurate);
+
+        // The boot ROM is unmapped, so the (empty) cartridge is visible at 0x0000
+        assert_eq!(emulator.cpu.bus().read_u8(0x0000).unwrap(), 0x00);
+        assert_eq!(emulator.cpu.bus().io().boot_rom_enable(), 0x01);
+    }
+
+    #[test]
+    fn unmapping_boot_rom_shows_cartridge() {
+        let mut rom = crafted_rom("MAPPED");
+        rom[0x0000] = 0xC3;
+        let cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+
+        let mut emulator = EmulatorBuilder::new().with_cartridge(cartridge).build();
+        let boot_rom_start = DEFAULT_BOOT_ROM.contents()[0x0000];
+
+        assert!(emulator.boot_rom_mapped());
+        assert_eq!(emulator.peek_u8(0x0000), boot_rom_start);
+
+        emulator.set_boot_rom_mapped(false);
+        assert!(!emulator.boot_rom_mapped());
+        assert_eq!(emulator.peek_u8(0x0000), 0xC3);
+        assert_eq!(emulator.peek_u8(0xFF50), 0x01);
+        // The boot ROM only ever covered the first 256 bytes
+        assert_eq!(emulator.peek_u8(0x0134), b'M');
+
+        emulator.set_boot_rom_mapped(true);
+        assert_eq!(emulator.peek_u8(0x0000), boot_rom_start);
+    }
+
+    #[test]
+    fn cgb_boot_rom_maps_around_cartridge_header() {
+        let mut rom = crafted_rom("MAPPED");
+        rom[0x0200] = 0x11;
+        rom[0x0900] = 0x22;
+        let cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+
+        let boot_bytes: Vec<u8> = (0..CGB_BOOT_ROM_SIZE).map(|i| (i % 251) as u8).collect();
+        let boot_rom = BootRomReader::read(&mut boot_bytes.as_slice()).unwrap();
+
+        let mut emulator = EmulatorBuilder::new()
+            .with_boot_rom(boot_rom)
+            .with_cartridge(cartridge)
+            .build();
+
+        assert_eq!(emulator.peek_u8(0x0000), boot_bytes[0x0000]);
+        assert_eq!(emulator.peek_u8(0x00FF), boot_bytes[0x00FF]);
+        assert_eq!(emulator.peek_u8(0x0200), boot_bytes[0x0200]);
+        assert_eq!(emulator.peek_u8(0x08FF), boot_bytes[0x08FF]);
+        // The header and everything past the boot ROM come from the cartridge
+        assert_eq!(emulator.peek_u8(0x0134), b'M');
+        assert_eq!(emulator.peek_u8(0x0900), 0x22);
+
+        emulator.set_boot_rom_mapped(false);
+        assert_eq!(emulator.peek_u8(0x0200), 0x11);
+    }
+
+    #[test]
+    fn bypassed_logo_check_boots_without_logo() {
+        // The crafted ROM has no logo, which the DMG boot ROM locks up on
+        let rom = crafted_rom("HOMEBREW");
+        let boot_cycles = CYCLES_PER_FRAME as u64 * 400;
+
+        let cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+        let mut emulator = EmulatorBuilder::new().with_cartridge(cartridge).build();
+        assert!(!emulator.run_until(0x0100, boot_cycles).unwrap());
+
+        let cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+        let mut emulator = EmulatorBuilder::new()
+            .with_cartridge(cartridge)
+            .bypass_logo_check(true)
+            .build();
+        assert!(emulator.run_until(0x0100, boot_cycles).unwrap());
+
+        // Past the boot ROM, the cartridge's own logo is visible again
+        assert_eq!(emulator.peek_u8(0x0104), 0x00);
+    }
+
+    #[test]
+    fn builder_defaults_run_boot_rom() {
+        let emulator = Emulator::builder().build();
+
+        assert_eq!(emulator.execution_state().instruction_pointer(), 0x0000);
+        assert_eq!(emulator.accuracy(), AccuracyMode::Fast);
+        assert_eq!(emulator.cpu.bus().read_u8(0x0000).unwrap(), 0x31);
+    }
+
+    #[test]
+    fn frame_callback_fires() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+
+        let frames = Rc::new(Cell::new(0));
+        let frame_len = Rc::new(Cell::new(0));
+        {
+            let frames = frames.clone();
+            let frame_len = frame_len.clone();
+            emulator.set_frame_callback(Box::new(move |pixels| {
+                frames.set(frames.get() + 1);
+                frame_len.set(pixels.len());
+            }));
+        }
+
+        loop {
+            if emulator.step(InputState::empty()).unwrap().new_frame {
+                break;
+            }
+        }
+
+        assert_eq!(frames.get(), 1);
+        assert_eq!(frame_len.get(), DISPLAY_WIDTH_PIXELS * DISPLAY_HEIGHT_PIXELS);
+    }
+
+    #[test]
+    fn run_instructions_trace() {
+        // An empty cartridge is a NOP sled starting at 0x0100
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+
+        let trace = emulator.run_instructions(5, InputState::empty()).unwrap();
+
+        let pcs: Vec<u16> = trace
+            .iter()
+            .map(|state| state.instruction_pointer())
+            .collect();
+        assert_eq!(pcs, vec![0x0101, 0x0102, 0x0103, 0x0104, 0x0105]);
+    }
+
+    #[test]
+    fn run_instructions_stops_at_breakpoint() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        emulator.add_breakpoint(0x0103);
+
+        let trace = emulator.run_instructions(5, InputState::empty()).unwrap();
+
+        assert_eq!(trace.len(), 3);
+        assert_eq!(trace[2].instruction_pointer(), 0x0103);
+
+        // The count is only a limit, so a huge one doesn't allocate room for that many states
+        emulator.add_breakpoint(0x0105);
+        let trace = emulator
+            .run_instructions(usize::MAX, InputState::empty())
+            .unwrap();
+        assert_eq!(trace.len(), 2);
+    }
+
+    #[test]
+    fn serial_output_accumulates() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+
+        for byte in b"ok\n" {
+            let bus = emulator.cpu.bus_mut();
+            bus.write_u8(0xFF01, *byte).unwrap();
+            bus.write_u8(0xFF02, 0x81).unwrap();
+
+            while emulator.cpu.bus().read_u8(0xFF02).unwrap() & 0x80 != 0 {
+                emulator.step(InputState::empty()).unwrap();
+            }
+        }
+
+        assert_eq!(emulator.take_serial_output(), "ok\n");
+        assert_eq!(emulator.take_serial_output(), "");
+        assert_ne!(emulator.cpu.bus().read_u8(0xFF0F).unwrap() & 0b0000_1000, 0);
+    }
+
+    #[test]
+    fn gbc_file_selects_cgb_mode() {
+        let mut rom = crafted_rom("GBC");
+        rom[0x0143] = 0xC0;
+        let checksum = rom[0x0134..=0x014C]
+            .iter()
+            .fold(0u8, |sum, b| sum.wrapping_sub(*b).wrapping_sub(1));
+        rom[0x014D] = checksum;
+
+        let path = std::env::temp_dir().join("gameboy-emulator-cgb-only.gbc");
+        std::fs::write(&path, &rom).unwrap();
+        let cartridge = crate::read_cartridge(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(cartridge.header().cgb_flag(), CgbFlag::CgbOnly);
+        assert!(cartridge.header().header_checksum_valid());
+
+        let emulator = EmulatorBuilder::new().with_cartridge(cartridge).build();
+        assert!(emulator.cgb_mode());
+
+        let emulator = EmulatorBuilder::new()
+            .with_cartridge(Cartridge::empty())
+            .build();
+        assert!(!emulator.cgb_mode());
+    }
+
+    #[test]
+    fn cartridge_header_matches_loaded_rom() {
+        let rom = crafted_rom("CRAFTED");
+        let cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+        let emulator = EmulatorBuilder::new().with_cartridge(cartridge).build();
+
+        let header = emulator.cartridge_header();
+        assert_eq!(header.title(), "CRAFTED");
+        assert_eq!(header.cgb_flag(), CgbFlag::BackwardsCompatible);
+        assert_eq!(header.cartridge_type(), CartridgeType::RomOnly);
+        assert_eq!(header.rom_size(), RomSize::Size32KiB);
+        assert_eq!(header.ram_size(), RamSize::NoRam);
+        assert_eq!(header.version_number(), 0x02);
+        assert!(header.header_checksum_valid());
+        assert_eq!(header.read_header_checksum(), rom[0x014D]);
+    }
+
+    #[test]
+    fn frame_timing_constants() {
+        assert_eq!(CYCLES_PER_FRAME, FRAME_CYCLES_LENGTH);
+        assert_eq!(CYCLES_PER_FRAME * 4, 70224);
+        assert!((FRAMES_PER_SECOND - 59.7275).abs() < 0.0001);
+        assert_eq!(CPU_CLOCK_HZ, 1 << 22);
+    }
+
+    fn noise_samples(seed: u64) -> Vec<u8> {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        emulator.set_rng_seed(seed);
+
+        // Triggers the noise channel at its fastest rate, then loops forever
+        let program = [
+            0x3E, 0x00, 0xE0, 0x22, 0x3E, 0xF0, 0xE0, 0x21, 0x3E, 0x80, 0xE0, 0x23, 0x18, 0xFE,
+        ];
+        load_program(&mut emulator, 0xC000, &program);
+
+        (0..500)
+            .map(|_| {
+                emulator.step(InputState::empty()).unwrap();
+                emulator.cpu.bus().io().audio().channel_4().sample()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rng_seed_reproducible() {
+        assert_eq!(noise_samples(0xDEAD_BEEF), noise_samples(0xDEAD_BEEF));
+        assert_ne!(noise_samples(0xDEAD_BEEF), noise_samples(42));
+    }
+
+    #[test]
+    fn peek_has_no_side_effects() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+
+        let io_before: Vec<u8> = (0xFF00..=0xFFFF).map(|a| emulator.peek_u8(a)).collect();
+        let io_again: Vec<u8> = (0xFF00..=0xFFFF).map(|a| emulator.peek_u8(a)).collect();
+        assert_eq!(io_before, io_again);
+        assert_eq!(emulator.peek_u8(0xFF40), 0x91);
+        assert_eq!(emulator.peek_u8(0xFFFF), 0x00);
+
+        // Unlike the CPU, peeking can see past an OAM DMA transfer
+        let bus = emulator.cpu.bus_mut();
+        bus.write_u8(0xC000, 0x42).unwrap();
+        bus.write_u8(0xFF46, 0xC0).unwrap();
+
+        assert_eq!(emulator.cpu.bus().read_u8(0xC000).unwrap(), 0xFF);
+        assert_eq!(emulator.peek_u8(0xC000), 0x42);
+        assert!(emulator.cpu.bus().io().dma().transferring());
+    }
+
+    #[test]
+    fn memory_fault_surfaces_as_cpu_error() {
+        fn read_past_oam(oam: &ObjectAttributeMemory) -> Result<u8, EmulatorError> {
+            Ok(oam.read_u8(0xFEA0)?)
+        }
+
+        let oam = ObjectAttributeMemory::zeroed();
+
+        assert!(matches!(
+            read_past_oam(&oam),
+            Err(EmulatorError::Cpu(Error::MemoryReadFault(0xFEA0)))
+        ));
+    }
+
+    #[test]
+    fn sgb_packet_capture_is_opt_in() {
+        let emulator = EmulatorBuilder::new().build();
+        assert!(!emulator.cpu.bus().io().joypad().capture_sgb_packets());
+
+        let mut emulator = EmulatorBuilder::new().capture_sgb_packets(true).build();
+        emulator.load_cartridge(Cartridge::empty());
+        assert!(emulator.cpu.bus().io().joypad().capture_sgb_packets());
+    }
+
+    #[test]
+    fn debug_instructions_log_and_break() {
+        let program = [
+            &[0x52, 0x18, 0x09, 0x64, 0x64, 0x00, 0x00][..], // ld d, d / jr .end / dw $6464, $0000
+            b"Hello",
+            &[0x40], // .end: ld b, b
+        ]
+        .concat();
+
+        let mut emulator = EmulatorBuilder::new()
+            .skip_boot(true)
+            .debug_instructions(true)
+            .build();
+        load_program(&mut emulator, 0xC000, &program);
+
+        let trace = emulator.run_instructions(5, InputState::empty()).unwrap();
+
+        assert_eq!(trace.len(), 3);
+        assert_eq!(emulator.take_debug_messages(), vec!["Hello".to_string()]);
+        assert!(emulator.take_debug_messages().is_empty());
+        assert_eq!(
+            emulator.breakpoint_reached(),
+            Some(Breakpoint::Address(0xC00D))
+        );
+
+        // Without the flag they're ordinary loads
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        load_program(&mut emulator, 0xC000, &program);
+
+        let trace = emulator.run_instructions(5, InputState::empty()).unwrap();
+
+        assert_eq!(trace.len(), 5);
+        assert!(emulator.take_debug_messages().is_empty());
+    }
+
+    #[test]
+    fn fast_forward_halt_until_vblank() {
+        let run_until_vblank = |fast_forward_halt| {
+            let mut emulator = EmulatorBuilder::new()
+                .skip_boot(true)
+                .fast_forward_halt(fast_forward_halt)
+                .build();
+            emulator.cpu.bus_mut().write_u8(0xFFFF, 0x01).unwrap();
+            emulator.cpu.bus_mut().write_u8(0xFF0F, 0x00).unwrap();
+            load_program(&mut emulator, 0xC000, &[0x76, 0x00]); // halt / nop
+
+            let mut steps = 0;
+            while emulator.peek_u8(0xFF0F) & 0x01 == 0 {
+                emulator.step(InputState::empty()).unwrap();
+                steps += 1;
+            }
+
+            let state = emulator.execution_state().to_string();
+            (steps, emulator.cycle_count(), state)
+        };
+
+        let (slow_steps, slow_cycles, slow_state) = run_until_vblank(false);
+        let (fast_steps, fast_cycles, fast_state) = run_until_vblank(true);
+
+        assert!(slow_steps > 1000);
+        assert_eq!(fast_steps, 1);
+        assert_eq!(fast_cycles, slow_cycles);
+        assert_eq!(fast_state, slow_state);
+    }
+
+    #[test]
+    fn fast_forward_halt_without_wakeup_returns() {
+        let halted_step = |enabled_interrupts: u8| {
+            let mut rom = crafted_rom("HALT");
+            // di; ld a, IE; ldh [$FF], a; xor a; ldh [$40], a (LCD off); halt
+            rom[0x0100..0x010A].copy_from_slice(&[
+                0xF3,
+                0x3E,
+                enabled_interrupts,
+                0xE0,
+                0xFF,
+                0xAF,
+                0xE0,
+                0x40,
+                0x76,
+                0x00,
+            ]);
+            let cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+            let mut emulator = EmulatorBuilder::new()
+                .skip_boot(true)
+                .fast_forward_halt(true)
+                .with_cartridge(cartridge)
+                .build();
+
+            for _ in 0..6 {
+                emulator.step(InputState::empty()).unwrap();
+            }
+            assert!(emulator.cpu.halted());
+
+            emulator.step(InputState::empty()).unwrap().cycles
+        };
+
+        // Nothing can wake the CPU, so the halt is stepped through one cycle at a time
+        assert_eq!(halted_step(0x00), 1);
+
+        // VBlank is enabled but never requested with the LCD off, so the wait is cut off
+        let cycles = halted_step(0x01);
+        assert!((CYCLES_PER_FRAME..CYCLES_PER_FRAME + 8).contains(&cycles));
+    }
+
+    #[test]
+    fn input_state_builder_matches_literal() {
+        let literal = InputState {
+            a_pressed: true,
+            b_pressed: false,
+            start_pressed: true,
+            select_pressed: false,
+            dpad_buttons: DPadButtonState::new(true, false, true, false),
+        };
+
+        let built = InputState::empty()
+            .with_a(true)
+            .with_start(true)
+            .with_dpad(DPadState::LeftUp);
+        assert!(built == literal);
+
+        let from_keys = InputState::from_keys(
+            true,
+            false,
+            true,
+            false,
+            DPadButtonState::new(true, false, true, false),
+        );
+        assert!(from_keys == literal);
+
+        // Opposing directions are kept in the buttons, but cancel out in the D-pad state
+        let opposing =
+            InputState::empty().with_dpad_buttons(DPadButtonState::new(true, false, true, true));
+        assert!(opposing.dpad_state() == DPadState::Up);
+        assert!(opposing.dpad_buttons.left && opposing.dpad_buttons.right);
+    }
+
+    #[test]
+    fn input_is_visible_to_joypad_read_in_same_step() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        // LD A, 0x10; LDH [0x00], A (select the buttons); LDH A, [0x00]
+        load_program(&mut emulator, 0xC000, &[0x3E, 0x10, 0xE0, 0x00, 0xF0, 0x00]);
+
+        emulator.step(InputState::empty()).unwrap();
+        emulator.step(InputState::empty()).unwrap();
+
+        let outcome = emulator.step(InputState::empty().with_a(true)).unwrap();
+
+        // A pressed reads as a low bit 0
+        assert_eq!(emulator.execution_state().reg_a() & 0x0F, 0x0E);
+        assert!(outcome.interrupt_requested(Interrupt::Joypad));
+    }
+
+    #[test]
+    fn write_log_records_writes_with_pc() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        // LD HL, 0xD000; LD [HL+], A; LD [HL], B; LDH [0x80], A; PUSH BC
+        let program = [0x21, 0x00, 0xD0, 0x22, 0x70, 0xE0, 0x80, 0xC5];
+        load_program(&mut emulator, 0xC000, &program);
+        emulator.cpu.execution_state_mut().set_reg_a(0x11);
+        emulator.cpu.execution_state_mut().set_reg_bc(0x2233);
+        emulator.cpu.execution_state_mut().set_stack_pointer(0xDFF0);
+
+        emulator.step(InputState::empty()).unwrap();
+        // Writes before the log is enabled aren't recorded
+        emulator.step(InputState::empty()).unwrap();
+        emulator.enable_write_log(16);
+
+        for _ in 0..3 {
+            emulator.step(InputState::empty()).unwrap();
+        }
+
+        let writes: Vec<(u16, u16, u8)> = emulator
+            .recent_writes()
+            .iter()
+            .map(|entry| (entry.pc, entry.address, entry.value))
+            .collect();
+
+        assert_eq!(
+            writes,
+            [
+                (0xC004, 0xD001, 0x22),
+                (0xC005, 0xFF80, 0x11),
+                (0xC007, 0xDFEF, 0x22),
+                (0xC007, 0xDFEE, 0x33),
+            ]
+        );
+
+        emulator.disable_write_log();
+        assert!(emulator.recent_writes().is_empty());
+    }
+
+    #[test]
+    fn step_outcome_reports_vblank() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        emulator.cpu.bus_mut().write_u8(0xFF0F, 0x00).unwrap();
+        load_program(&mut emulator, 0xC000, &[0x18, 0xFE]); // jr -2
+
+        let mut outcome = emulator.step(InputState::empty()).unwrap();
+        while emulator.scanline() < 144 {
+            assert!(!outcome.interrupt_requested(Interrupt::VBlank));
+            assert!(!outcome.new_frame);
+            outcome = emulator.step(InputState::empty()).unwrap();
+        }
+
+        assert!(outcome.interrupt_requested(Interrupt::VBlank));
+        assert!(outcome.new_frame);
+        assert_eq!(outcome.cycles, 3);
+        assert_eq!(outcome.breakpoint, None);
+        assert_eq!(emulator.peek_u8(0xFF0F) & 0x01, 0x01);
+
+        let outcome = emulator.step(InputState::empty()).unwrap();
+        assert!(!outcome.interrupt_requested(Interrupt::VBlank));
+
+        emulator.add_breakpoint(0xC000);
+        let outcome = emulator.step(InputState::empty()).unwrap();
+        assert_eq!(outcome.breakpoint, Some(Breakpoint::Address(0xC000)));
+    }
+
+    #[test]
+    fn trace_lines_show_cycles() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        // nop / ld a, [hl] / call $C010
+        load_program(&mut emulator, 0xC000, &[0x00, 0x7E, 0xCD, 0x10, 0xC0]);
+
+        let first_state = emulator.execution_state().to_string();
+        let mut lines = Vec::new();
+        for _ in 0..3 {
+            lines.push(emulator.trace_line().unwrap());
+            emulator.step(InputState::empty()).unwrap();
+        }
+        lines.push(emulator.trace_line().unwrap());
+
+        let prefixes: Vec<&str> = lines.iter().map(|line| &line[..30]).collect();
+        assert_eq!(
+            prefixes,
+            [
+                "         0 +1 C000: nop       ",
+                "         1 +2 C001: ld a, [hl]",
+                "         3 +6 C002: call $C010",
+                "         9 +1 C010: nop       ",
+            ]
+        );
+        assert!(lines[0].ends_with(&first_state));
+    }
+
+    #[test]
+    fn run_until_call_target() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        load_program(&mut emulator, 0xC010, &[0x18, 0xFE]); // jr -2
+        load_program(&mut emulator, 0xC000, &[0x00, 0xCD, 0x10, 0xC0]); // nop / call $C010
+
+        assert!(emulator.run_until(0xC010, 100).unwrap());
+        assert_eq!(emulator.execution_state().instruction_pointer(), 0xC010);
+        assert_eq!(emulator.cycle_count(), 7);
+
+        // The loop never leaves the CALL target
+        assert!(!emulator.run_until(0xC004, 100).unwrap());
+        assert_eq!(emulator.execution_state().instruction_pointer(), 0xC010);
+        assert!(emulator.cycle_count() >= 107);
+
+        assert!(emulator.run_until(0xC010, 100).unwrap());
+
+        // Without any cycles to run, nothing is executed, even when already at the address
+        let cycles = emulator.cycle_count();
+        assert!(!emulator.run_until(0xC010, 0).unwrap());
+        assert_eq!(emulator.cycle_count(), cycles);
+    }
+
+    #[test]
+    fn invalid_instruction_surfaces_from_step() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        load_program(&mut emulator, 0xC000, &[0xD3]);
+
+        assert!(matches!(
+            emulator.step(InputState::empty()),
+            Err(EmulatorError::Cpu(Error::InvalidInstruction(0xC000, 0xD3)))
+        ));
+    }
+
+    #[test]
+    fn odometer_counts_instructions_and_cycles() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        load_program(&mut emulator, 0xC000, &[0x00; 10]);
+
+        assert_eq!(emulator.instruction_count(), 0);
+        assert_eq!(emulator.cycle_count(), 0);
+
+        emulator.run_instructions(10, InputState::empty()).unwrap();
+
+        assert_eq!(emulator.instruction_count(), 10);
+        assert_eq!(emulator.cycle_count(), 10);
+
+        // HALT is counted, but the cycles spent halted afterwards are not instructions
+        load_program(&mut emulator, 0xC000, &[0x76]);
+        emulator.run_instructions(5, InputState::empty()).unwrap();
+
+        assert_eq!(emulator.instruction_count(), 11);
+        assert_eq!(emulator.cycle_count(), 15);
+    }
+
+    #[test]
+    fn cycle_breakpoint_stops_at_or_just_past_target() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        // LD BC,n16 takes 3 M-cycles, so cycle 4 is reached part way through the second one
+        let program = [0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00];
+        load_program(&mut emulator, 0xC000, &program);
+        emulator.add_cycle_breakpoint(4);
+
+        let trace = emulator.run_instructions(4, InputState::empty()).unwrap();
+
+        assert_eq!(trace.len(), 2);
+        assert_eq!(emulator.cycle_count(), 6);
+        assert_eq!(emulator.breakpoint_reached(), Some(Breakpoint::Cycle(4)));
+
+        // Only fires once
+        emulator.step(InputState::empty()).unwrap();
+        assert_eq!(emulator.breakpoint_reached(), None);
+    }
+
+    #[test]
+    fn instruction_breakpoint_stops_on_count() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        load_program(&mut emulator, 0xC000, &[0x00; 8]);
+        emulator.add_instruction_breakpoint(3);
+
+        let trace = emulator.run_instructions(8, InputState::empty()).unwrap();
+
+        assert_eq!(trace.len(), 3);
+        assert_eq!(emulator.instruction_count(), 3);
+        assert_eq!(
+            emulator.breakpoint_reached(),
+            Some(Breakpoint::Instruction(3))
+        );
+    }
+
+    #[test]
+    fn conditional_breakpoint_checks_registers() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        // loop: INC A; JR loop
+        load_program(&mut emulator, 0xC000, &[0x3C, 0x18, 0xFD]);
+        emulator.cpu.execution_state_mut().set_reg_a(0x3F);
+        emulator.add_conditional_breakpoint(0xC001, Box::new(|state| state.reg_a() == 0x42));
+
+        let trace = emulator.run_instructions(100, InputState::empty()).unwrap();
+
+        // Passes through 0xC001 twice with A = 0x40 and 0x41 before stopping
+        assert_eq!(trace.len(), 5);
+        assert_eq!(emulator.execution_state().reg_a(), 0x42);
+        assert_eq!(
+            emulator.breakpoint_reached(),
+            Some(Breakpoint::Address(0xC001))
+        );
+    }
+
+    #[test]
+    fn breakpoint_list_round_trip() {
+        let mut emulator = EmulatorBuilder::new().build();
+        assert!(emulator.breakpoints().is_empty());
+
+        emulator.add_breakpoint(0x0150);
+        emulator.add_breakpoint(0x0100);
+        emulator.add_breakpoint(0x0150);
+        assert_eq!(emulator.breakpoints(), &[0x0150, 0x0100]);
+
+        emulator.remove_breakpoint(0x0150);
+        assert_eq!(emulator.breakpoints(), &[0x0100]);
+
+        // Removing one that isn't set does nothing
+        emulator.remove_breakpoint(0x1234);
+        assert_eq!(emulator.breakpoints(), &[0x0100]);
+
+        emulator.add_breakpoint(0x0200);
+        emulator.clear_breakpoints();
+        assert!(emulator.breakpoints().is_empty());
+        assert_eq!(emulator.breakpoint_reached(), None);
+    }
+
+    #[test]
+    fn rgba_frame_matches_pixels() {
+        let mut emulator = EmulatorBuilder::new().build();
+
+        // Run until the boot ROM has drawn the logo
+        for _ in 0..CYCLES_PER_FRAME * 60 {
+            emulator.step(InputState::empty()).unwrap();
+        }
+
+        let rgba = emulator.frame_rgba().to_vec();
+        let pixels = emulator.get_pixels();
+
+        assert_eq!(rgba.len(), DISPLAY_WIDTH_PIXELS * DISPLAY_HEIGHT_PIXELS * 4);
+        assert!(pixels.iter().any(|pixel| *pixel != pixels[0]));
+
+        for (pixel, bytes) in pixels.iter().zip(rgba.chunks_exact(4)) {
+            assert_eq!(bytes, &[pixel.r(), pixel.g(), pixel.b(), pixel.a()]);
+        }
+    }
+
+    #[test]
+    fn general_purpose_vram_dma() {
+        let mut emulator = cgb_emulator();
+        setup_vram_dma(&mut emulator, 0x80, 0x8100);
+
+        emulator.cpu.bus_mut().write_u8(0xFF55, 0x07).unwrap();
+
+        assert!(vram_matches_source(&emulator, 0x80, 0x8100));
+        assert_eq!(emulator.peek_u8(0x8180), 0x00);
+        assert_eq!(emulator.peek_u8(0xFF55), 0xFF);
+    }
+
+    #[test]
+    fn hblank_vram_dma() {
+        let mut emulator = cgb_emulator();
+        setup_vram_dma(&mut emulator, 0x40, 0x8000);
+
+        emulator.cpu.bus_mut().write_u8(0xFF55, 0x83).unwrap();
+        assert_eq!(emulator.peek_u8(0x8000), 0x00);
+
+        let mut hblanks = 0;
+        let mut mode = emulator.cpu.bus().io().lcd().status().ppu_mode();
+
+        while hblanks < 4 {
+            emulator.step(InputState::empty()).unwrap();
+
+            let new_mode = emulator.cpu.bus().io().lcd().status().ppu_mode();
+            if (new_mode == PpuMode::HBlank) & (mode != PpuMode::HBlank) {
+                hblanks += 1;
+
+                let copied = hblanks * 16;
+                assert!(vram_matches_source(&emulator, copied, 0x8000));
+                assert_eq!(emulator.peek_u8(0x8000 + copied), 0x00);
+            }
+            mode = new_mode;
+        }
+
+        assert_eq!(emulator.peek_u8(0xFF55), 0xFF);
+    }
+
+    #[test]
+    fn no_vram_dma_on_dmg() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        setup_vram_dma(&mut emulator, 0x10, 0x8000);
+
+        emulator.cpu.bus_mut().write_u8(0xFF55, 0x00).unwrap();
+
+        assert_eq!(emulator.peek_u8(0x8000), 0x00);
+    }
+
+    #[test]
+    fn run_frame_takes_a_frame() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+
+        // The first frame starts part way through, as the boot ROM has already run
+        emulator.run_frame(InputState::empty()).unwrap();
+
+        for _ in 0..3 {
+            let outcome = emulator.run_frame(InputState::empty()).unwrap();
+            assert!(outcome.new_frame);
+            assert!(outcome.interrupt_requested(Interrupt::VBlank));
+            assert!(
+                outcome.cycles.abs_diff(CYCLES_PER_FRAME) < 8,
+                "{}",
+                outcome.cycles
+            );
+        }
+
+        // With the LCD off, a frame's worth of cycles are run instead
+        emulator.cpu.bus_mut().write_u8(0xFF40, 0x00).unwrap();
+        let outcome = emulator.run_frame(InputState::empty()).unwrap();
+        assert!(!outcome.new_frame);
+        assert!((CYCLES_PER_FRAME..CYCLES_PER_FRAME + 8).contains(&outcome.cycles));
+    }
+
+    #[test]
+    fn run_frame_stops_at_breakpoint() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        emulator.run_frame(InputState::empty()).unwrap();
+
+        let cycle = emulator.cycle_count() + (CYCLES_PER_FRAME / 2) as u64;
+        emulator.add_cycle_breakpoint(cycle);
+        let outcome = emulator.run_frame(InputState::empty()).unwrap();
+
+        assert!(!outcome.new_frame);
+        assert_eq!(outcome.breakpoint, Some(Breakpoint::Cycle(cycle)));
+        assert!(emulator.cycle_count() >= cycle);
+        assert!(outcome.cycles < CYCLES_PER_FRAME);
+
+        // The rest of the frame runs on the next call
+        let outcome = emulator.run_frame(InputState::empty()).unwrap();
+        assert!(outcome.new_frame);
+        assert_eq!(outcome.breakpoint, None);
+    }
+
+    #[test]
+    fn disassembly_uses_loaded_symbols() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        // call $C010; jr -2
+        load_program(&mut emulator, 0xC000, &[0xCD, 0x10, 0xC0, 0x18, 0xFE]);
+
+        assert_eq!(emulator.disassemble(0xC000).unwrap(), "call $C010");
+
+        let path = std::env::temp_dir().join("gameboy-emulator-disassembly.sym");
+        std::fs::write(&path, "; Symbols\n00:C003 Hang\n00:C010 Helper\n").unwrap();
+        emulator.load_symbols(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(emulator.disassemble(0xC000).unwrap(), "call Helper");
+        assert_eq!(emulator.disassemble(0xC003).unwrap(), "jr Hang");
+    }
+
+    #[test]
+    fn breakpoints_survive_load_cartridge() {
+        let mut emulator = EmulatorBuilder::new()
+            .skip_boot(true)
+            .accuracy(AccuracyMode::Accurate)
+            .build();
+        emulator.add_breakpoint(0x0150);
+        emulator.run_instructions(10, InputState::empty()).unwrap();
+
+        let rom = crafted_rom("SECOND");
+        let cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+        emulator.load_cartridge(cartridge);
+
+        assert_eq!(emulator.cartridge_header().title(), "SECOND");
+        assert_eq!(emulator.breakpoints(), [0x0150]);
+        assert_eq!(emulator.accuracy(), AccuracyMode::Accurate);
+        assert_eq!(emulator.cycle_count(), 0);
+        assert_eq!(emulator.execution_state().instruction_pointer(), 0x0100);
+
+        // The crafted ROM is all NOPs, so execution reaches the breakpoint
+        while emulator.breakpoint_reached().is_none() {
+            emulator.step(InputState::empty()).unwrap();
+        }
+        assert_eq!(emulator.execution_state().instruction_pointer(), 0x0150);
+    }
+
+    #[test]
+    fn pending_counter_breakpoints_count_from_load_cartridge() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        emulator.add_instruction_breakpoint(20);
+        emulator.add_cycle_breakpoint(40);
+        emulator.run_instructions(10, InputState::empty()).unwrap();
+        assert_eq!(emulator.breakpoint_reached(), None);
+
+        let rom = crafted_rom("SECOND");
+        let cartridge = Cartridge::read(&mut rom.as_slice()).unwrap();
+        emulator.load_cartridge(cartridge);
+        assert_eq!(emulator.instruction_count(), 0);
+
+        // The NOPs before the header take a cycle each, so both are reached from the reload
+        let mut hits = Vec::new();
+        while hits.len() < 2 {
+            emulator.step(InputState::empty()).unwrap();
+            if let Some(breakpoint) = emulator.breakpoint_reached() {
+                hits.push((breakpoint, emulator.instruction_count()));
+            }
+        }
+        let expected = [
+            (Breakpoint::Instruction(20), 20),
+            (Breakpoint::Cycle(40), 40),
+        ];
+        assert_eq!(hits, expected);
+
+        // Both were removed when hit
+        emulator.load_cartridge(Cartridge::read(&mut rom.as_slice()).unwrap());
+        emulator.run_instructions(50, InputState::empty()).unwrap();
+        assert_eq!(emulator.breakpoint_reached(), None);
+    }
+
+    #[test]
+    fn boot_rom_changes_apply_on_load_cartridge() {
+        let mut emulator = EmulatorBuilder::new().build();
+        emulator.set_skip_boot(true);
+        assert_eq!(emulator.execution_state().instruction_pointer(), 0x0000);
+
+        emulator.load_cartridge(Cartridge::empty());
+        assert_eq!(emulator.execution_state().instruction_pointer(), 0x0100);
+
+        emulator.set_skip_boot(false);
+        emulator.set_boot_rom(OPEN_SOURCE_BOOT_ROM);
+        emulator.load_cartridge(Cartridge::empty());
+        assert_eq!(emulator.execution_state().instruction_pointer(), 0x0000);
+        let boot_rom_start = OPEN_SOURCE_BOOT_ROM.contents()[0x0000];
+        assert_eq!(emulator.peek_u8(0x0000), boot_rom_start);
+    }
+
+    #[test]
+    fn dump_wram_reflects_bus_writes() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        let bus = emulator.cpu.bus_mut();
+        bus.write_u8(0xC000, 0x12).unwrap();
+        bus.write_u8(0xD123, 0x34).unwrap();
+        // Echo RAM writes through to work RAM
+        bus.write_u8(0xFDFF, 0x56).unwrap();
+
+        let wram = emulator.dump_wram();
+        assert_eq!(wram.len(), 0x2000);
+        assert_eq!(wram[0x0000], 0x12);
+        assert_eq!(wram[0x1123], 0x34);
+        assert_eq!(wram[0x1DFF], 0x56);
+
+        let mut emulator = cgb_emulator();
+        let bus = emulator.cpu.bus_mut();
+        bus.write_u8(0xFF70, 3).unwrap();
+        bus.write_u8(0xD010, 0x78).unwrap();
+
+        let wram = emulator.dump_wram();
+        assert_eq!(wram.len(), 0x8000);
+        assert_eq!(wram[0x3010], 0x78);
+        assert_eq!(wram[0x1010], 0x00);
+    }
+
+    #[test]
+    fn ppu_mode_and_scanline_reach_vblank() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        assert!(emulator.scanline() < 144);
+
+        while emulator.ppu_mode() != PpuMode::VBlank {
+            emulator.step(InputState::empty()).unwrap();
+        }
+
+        assert!(emulator.scanline() >= 144);
+        assert_eq!(emulator.scanline(), emulator.peek_u8(0xFF44));
+    }
+
+    #[test]
+    fn invalid_opcode_locks_cpu() {
+        let mut emulator = EmulatorBuilder::new()
+            .skip_boot(true)
+            .lock_on_invalid_opcode(true)
+            .build();
+        load_program(&mut emulator, 0xC000, &[0x00, 0xDD, 0x00]);
+        assert_eq!(emulator.cpu_status(), CpuStatus::Running);
+
+        emulator.step(InputState::empty()).unwrap();
+        let outcome = emulator.step(InputState::empty()).unwrap();
+        assert_eq!((outcome.cycles, outcome.new_frame), (1, false));
+
+        let locked = CpuStatus::Locked {
+            address: 0xC001,
+            opcode: 0xDD,
+        };
+        assert_eq!(emulator.cpu_status(), locked);
+
+        // The rest of the system keeps running
+        let scanline = emulator.scanline();
+        for _ in 0..CYCLES_PER_FRAME / 2 {
+            emulator.step(InputState::empty()).unwrap();
+        }
+
+        assert_eq!(emulator.cpu_status(), locked);
+        assert_eq!(emulator.execution_state().instruction_pointer(), 0xC001);
+        assert_ne!(emulator.scanline(), scanline);
+    }
+
+    #[test]
+    fn interrupt_state_matches_registers() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        emulator.interrupts().write_interrupt_enable(0b0000_0101);
+        emulator.interrupts().write_interrupt_flag(0b0000_0110);
+        emulator.set_ime(false);
+
+        let state = emulator.interrupt_state();
+        assert_eq!(state.enabled, InterruptFlags::new(0b0000_0101));
+        assert_eq!(state.requested, InterruptFlags::new(0b0000_0110));
+        assert!(!state.master_enable);
+        assert_eq!(state.pending(), InterruptFlags::new(0b0000_0100));
+        // Nothing is serviced until IME is set
+        assert_eq!(state.next_interrupt(), None);
+
+        emulator.set_ime(true);
+
+        let state = emulator.interrupt_state();
+        assert!(state.master_enable);
+        assert_eq!(state.next_interrupt(), Some(Interrupt::Timer));
+    }
+
+    #[test]
+    fn ime_controls_interrupt_dispatch() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        load_program(&mut emulator, 0xC000, &[0x00; 4]);
+        let interrupts = emulator.interrupts();
+        interrupts.write_interrupt_enable(Interrupt::Timer.mask());
+        interrupts.set_interrupt_requested(Interrupt::Timer);
+        let stack_pointer = emulator.execution_state().stack_pointer();
+
+        emulator.set_ime(false);
+        assert!(!emulator.ime());
+
+        emulator.step(InputState::empty()).unwrap();
+        assert_eq!(emulator.execution_state().instruction_pointer(), 0xC001);
+        assert_eq!(emulator.interrupt_state().next_interrupt(), None);
+
+        emulator.set_ime(true);
+        assert_eq!(
+            emulator.interrupt_state().next_interrupt(),
+            Some(Interrupt::Timer)
+        );
+
+        // The interrupt is serviced in the very next step, which pushes the return address
+        emulator.step(InputState::empty()).unwrap();
+        let state = emulator.execution_state();
+        assert_eq!(state.stack_pointer(), stack_pointer.wrapping_sub(2));
+        let return_address = u16::from_le_bytes([
+            emulator.peek_u8(state.stack_pointer()),
+            emulator.peek_u8(state.stack_pointer() + 1),
+        ]);
+        assert_eq!(return_address, 0xC001);
+        assert!(!emulator.ime());
+        assert!(emulator.interrupt_state().pending().is_empty());
+    }
+
+    #[test]
+    fn bus_reads_back_every_writable_region() {
+        let mut emulator = EmulatorBuilder::new().skip_boot(true).build();
+        let bus = emulator.cpu.bus_mut();
+
+        // VRAM, work RAM, OAM, high RAM at both ends, and IE
+        for address in [
+            0x8000, 0x9FFF, 0xC000, 0xDFFF, 0xFE00, 0xFF80, 0xFFFE, 0xFFFF,
+        ] {
+            bus.write_u8(address, 0x15).unwrap();
+            assert_eq!(bus.read_u8(address).unwrap(), 0x15, "{:04X}", address);
+        }
+
+        // Echo RAM mirrors work RAM both ways
+        bus.write_u8(0xE123, 0x42).unwrap();
+        assert_eq!(bus.read_u8(0xC123).unwrap(), 0x42);
+        bus.write_u8(0xD456, 0x24).unwrap();
+        assert_eq!(bus.read_u8(0xF456).unwrap(), 0x24);
+
+        // The unusable area and the unmapped register after the IO range read as open bus
+        for address in [0xFEA0, 0xFEFF, 0xFF7F] {
+            bus.write_u8(address, 0x00).unwrap();
+            assert_eq!(bus.read_u8(address).unwrap(), 0xFF, "{:04X}", address);
+        }
+    }
+
+    #[test]
+    fn emulator_reads_time_from_injected_clock() {
+        let clock = MockClock::new(Duration::from_secs(1_000));
+        let mut emulator = EmulatorBuilder::new()
+            .skip_boot(true)
+            .clock(clock.clone())
+            .build();
+        assert_eq!(emulator.clock().now(), Duration::from_secs(1_000));
+
+        // Time only moves when the test says so, however much is emulated
+        emulator.run_frame(InputState::empty()).unwrap();
+        assert_eq!(emulator.clock().now(), Duration::from_secs(1_000));
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(emulator.clock().now(), Duration::from_secs(1_090));
+
+        // Loading a cartridge keeps the clock
+        emulator.load_cartridge(Cartridge::empty());
+        assert_eq!(emulator.clock().now(), Duration::from_secs(1_090));
+    }
+
+    #[test]
+    fn empty_cartridge_runs_boot_rom() {
+        let dark_pixels_after_boot = |bypass_logo_check: bool| {
+            let mut emulator = EmulatorBuilder::new()
+                .with_cartridge(Cartridge::empty())
+                .bypass_logo_check(bypass_logo_check)
+                .build();
+
+            for _ in 0..300 {
+                emulator.run_frame(InputState::empty()).unwrap();
+            }
+
+            // The boot ROM stops at the logo check, or the header checksum if it passes
+            assert!(emulator.boot_rom_mapped());
+
+            emulator
+                .get_pixels()
+                .iter()
+                .filter(|pixel| **pixel == DARKEST_COLOR)
+                .count()
+        };
+
+        // Only the boot ROM's own (R) symbol is shown for the blank logo, but bypassing the check
+        // shows the Nintendo logo too
+        assert!(dark_pixels_after_boot(true) > dark_pixels_after_boot(false));
+    }
+
+    #[test]
+    fn bypass_can_be_turned_off_for_the_next_cartridge() {
+        let mut emulator = EmulatorBuilder::new()
+            .with_cartridge(Cartridge::empty())
+            .bypass_logo_check(true)
+            .build();
+        assert_eq!(emulator.peek_u8(0x0104), NINTENDO_LOGO[0]);
+
+        let mut rom = crafted_rom("OWN LOGO");
+        rom[0x0104] = 0x12;
+        emulator.set_bypass_logo_check(false);
+        emulator.load_cartridge(Cartridge::read(&mut rom.as_slice()).unwrap());
+
+        assert!(emulator.boot_rom_mapped());
+        assert_eq!(emulator.peek_u8(0x0104), 0x12);
+    }
 }
@@ -23,27 +23,131 @@ impl HighRam {
     }
 }
 
-#[derive(Clone, Copy)]
+const WORK_RAM_BANK_SIZE: usize = 4 * 1024;
+const WORK_RAM_BANKS: usize = 8;
+
+/// Work RAM, which on the CGB is split into 8 banks of 4 KiB
+///
+/// Bank 0 is always at 0xC000-0xCFFF, and the bank at 0xD000-0xDFFF is selected by SVBK (0xFF70).
+/// Outside of CGB mode SVBK does nothing and bank 1 is always used, giving the DMG's 8 KiB.
+#[derive(Clone)]
 pub struct WorkRam {
-    contents: [u8; 8192],
+    contents: Box<[u8; WORK_RAM_BANK_SIZE * WORK_RAM_BANKS]>,
+    bank_select: u8,
+    cgb_mode: bool,
 }
 
 impl WorkRam {
-    pub fn new() -> Self {
+    pub fn new(cgb_mode: bool) -> Self {
         Self {
-            contents: [0u8; 8192],
+            contents: Box::new([0u8; WORK_RAM_BANK_SIZE * WORK_RAM_BANKS]),
+            bank_select: 0,
+            cgb_mode,
         }
     }
 
-    pub fn contents(&self) -> &[u8; 8192] {
-        &self.contents
+    /// The currently mapped 8 KiB, bank 0 followed by the switchable bank
+    pub fn contents(&self) -> [u8; 8192] {
+        let mut contents = [0u8; 8192];
+        contents[..WORK_RAM_BANK_SIZE].copy_from_slice(&self.contents[..WORK_RAM_BANK_SIZE]);
+
+        let bank_start = self.switchable_bank() * WORK_RAM_BANK_SIZE;
+        contents[WORK_RAM_BANK_SIZE..]
+            .copy_from_slice(&self.contents[bank_start..bank_start + WORK_RAM_BANK_SIZE]);
+
+        contents
+    }
+
+    /// Every bank of work RAM in order, which is just banks 0 and 1 outside of CGB mode
+    pub fn banks(&self) -> &[u8] {
+        if self.cgb_mode {
+            &self.contents[..]
+        } else {
+            &self.contents[..WORK_RAM_BANK_SIZE * 2]
+        }
+    }
+
+    /// The bank mapped at 0xD000-0xDFFF, where selecting bank 0 maps bank 1
+    pub fn switchable_bank(&self) -> usize {
+        match self.bank_select & 0b0000_0111 {
+            0 => 1,
+            bank => bank as usize,
+        }
+    }
+
+    pub fn read_bank_select(&self) -> u8 {
+        if self.cgb_mode {
+            0b1111_1000 | self.bank_select
+        } else {
+            0xFF
+        }
+    }
+
+    pub fn write_bank_select(&mut self, value: u8) {
+        if self.cgb_mode {
+            self.bank_select = value & 0b0000_0111;
+        }
+    }
+
+    fn index(&self, address: u16) -> usize {
+        let offset = (address - 0xC000) as usize;
+
+        if offset < WORK_RAM_BANK_SIZE {
+            offset
+        } else {
+            (self.switchable_bank() * WORK_RAM_BANK_SIZE) + (offset - WORK_RAM_BANK_SIZE)
+        }
     }
 
     pub fn read_u8(&self, address: u16) -> u8 {
-        self.contents[(address - 0xC000) as usize]
+        self.contents[self.index(address)]
     }
 
     pub fn write_u8(&mut self, address: u16, data: u8) {
-        self.contents[(address - 0xC000) as usize] = data;
+        let index = self.index(address);
+        self.contents[index] = data;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WorkRam;
+
+    #[test]
+    fn banks_switch_at_0xd000() {
+        let mut work_ram = WorkRam::new(true);
+
+        for bank in 1..8 {
+            work_ram.write_bank_select(bank);
+            work_ram.write_u8(0xD000, bank * 0x11);
+            work_ram.write_u8(0xDFFF, bank);
+        }
+        work_ram.write_u8(0xC000, 0xAB);
+
+        for bank in 1..8 {
+            work_ram.write_bank_select(bank);
+            assert_eq!(work_ram.read_u8(0xD000), bank * 0x11);
+            assert_eq!(work_ram.read_u8(0xDFFF), bank);
+            assert_eq!(work_ram.read_u8(0xC000), 0xAB);
+            assert_eq!(work_ram.read_bank_select(), 0xF8 | bank);
+        }
+
+        // Bank 0 selects bank 1
+        work_ram.write_bank_select(0);
+        assert_eq!(work_ram.read_u8(0xD000), 0x11);
+        assert_eq!(work_ram.read_bank_select(), 0xF8);
+        assert_eq!(work_ram.contents()[0x1000], 0x11);
+    }
+
+    #[test]
+    fn dmg_always_uses_bank_1() {
+        let mut work_ram = WorkRam::new(false);
+        work_ram.write_u8(0xD000, 0x42);
+
+        work_ram.write_bank_select(3);
+
+        assert_eq!(work_ram.switchable_bank(), 1);
+        assert_eq!(work_ram.read_u8(0xD000), 0x42);
+        assert_eq!(work_ram.read_bank_select(), 0xFF);
     }
 }
@@ -1,10 +1,10 @@
-use eframe::egui::Color32;
+use eframe::egui::{Color32, ColorImage};
 use oam::{ObjectAttributeMemory, ObjectAttributes, PaletteSelection};
-use vram::{ColorId, Vram};
+use vram::{ColorId, Tile, TileId, Vram};
 
 use crate::io::{
     interrupts::Interrupt,
-    lcd::{Color, Lcd, ObjSize, Palette, TileMapArea},
+    lcd::{Color, Lcd, ObjPriority, ObjSize, Palette, TileMapArea},
 };
 
 pub mod oam;
@@ -14,6 +14,9 @@ pub const DISPLAY_HEIGHT_PIXELS: usize = 144;
 pub const DISPLAY_WIDTH_PIXELS: usize = 160;
 pub const DISPLAY_SIZE_PIXELS: &'static [usize; 2] = &[DISPLAY_WIDTH_PIXELS, DISPLAY_HEIGHT_PIXELS];
 pub const TOTAL_PIXELS: usize = DISPLAY_HEIGHT_PIXELS * DISPLAY_WIDTH_PIXELS;
+pub const RGBA_FRAME_LENGTH: usize = TOTAL_PIXELS * 4;
+/// The width and height of a whole background tile map, which the display scrolls around
+pub const BACKGROUND_MAP_SIZE_PIXELS: usize = 256;
 
 pub const DARKEST_COLOR: Color32 = Color32::from_rgb(8, 24, 32);
 pub const DARKER_COLOR: Color32 = Color32::from_rgb(52, 104, 86);
@@ -54,12 +57,30 @@ impl From<PpuMode> for u8 {
 pub struct Ppu {
     vram: Vram,
     oam: ObjectAttributeMemory,
+    /// The frame being drawn, which is swapped with `front_pixel_buffer` when it is finished
     pixel_buffer: Box<[Color32; TOTAL_PIXELS]>,
+    /// The last finished frame
+    front_pixel_buffer: Box<[Color32; TOTAL_PIXELS]>,
     bg_priority: [bool; TOTAL_PIXELS],
     off_display: Box<[Color32; TOTAL_PIXELS]>,
+    /// The same pixels as `pixel_buffer` packed as RGBA bytes, updated as each scanline is drawn
+    rgba_buffer: Box<[u8; RGBA_FRAME_LENGTH]>,
+    front_rgba_buffer: Box<[u8; RGBA_FRAME_LENGTH]>,
+    off_display_rgba: Box<[u8; RGBA_FRAME_LENGTH]>,
+    /// The background and window color ids of `pixel_buffer`, before the palette is applied
+    color_id_buffer: Box<[ColorId; TOTAL_PIXELS]>,
+    front_color_id_buffer: Box<[ColorId; TOTAL_PIXELS]>,
     current_cycles: usize,
     current_scanline: usize,
     window_scanline: usize,
+    /// The objects drawn on the current scanline, kept between scanlines to reuse the allocation
+    line_objects: Vec<ObjectAttributes>,
+    /// The next pixel of the current scanline to have its background drawn
+    line_x: usize,
+    window_drawn_on_line: bool,
+    /// Draws pixels as the scanline progresses rather than all at once, so that register writes
+    /// part way through a scanline take effect
+    accurate_rendering: bool,
 }
 
 impl Ppu {
@@ -68,14 +89,28 @@ impl Ppu {
             vram: Vram::zeroed(),
             oam: ObjectAttributeMemory::zeroed(),
             pixel_buffer: Self::empty_pixel_buffer(),
+            front_pixel_buffer: Self::empty_pixel_buffer(),
             bg_priority: [false; TOTAL_PIXELS],
             off_display: Self::off_display(),
+            rgba_buffer: Self::filled_rgba(LIGHTEST_COLOR),
+            front_rgba_buffer: Self::filled_rgba(LIGHTEST_COLOR),
+            off_display_rgba: Self::filled_rgba(OFF_COLOR),
+            color_id_buffer: Box::new([ColorId::Zero; TOTAL_PIXELS]),
+            front_color_id_buffer: Box::new([ColorId::Zero; TOTAL_PIXELS]),
             current_cycles: 0,
             current_scanline: 0,
             window_scanline: 0,
+            line_objects: Vec::with_capacity(40),
+            line_x: 0,
+            window_drawn_on_line: false,
+            accurate_rendering: false,
         }
     }
 
+    pub fn set_accurate_rendering(&mut self, accurate: bool) {
+        self.accurate_rendering = accurate;
+    }
+
     pub fn vram(&self) -> &Vram {
         &self.vram
     }
@@ -92,6 +127,73 @@ impl Ppu {
         &mut self.oam
     }
 
+    /// Loads a VRAM dump, decoding the tiles in it like writes from the bus would
+    pub fn load_vram(&mut self, bytes: &[u8]) {
+        self.vram.load(bytes);
+    }
+
+    pub fn dump_vram(&self) -> Vec<u8> {
+        self.vram.dump()
+    }
+
+    /// Places a tile directly in VRAM, without going through the bus
+    ///
+    /// The tile carries its decoded colors with it, so it draws exactly as if its bytes had been
+    /// written to VRAM.
+    pub fn set_tile(&mut self, index: usize, tile: Tile) {
+        self.vram.set_tile(index, tile);
+    }
+
+    /// Sets an entry of tile map 0 or 1 directly, without going through the bus
+    pub fn set_tilemap_entry(&mut self, map: u8, index: usize, id: TileId) {
+        self.vram.set_map_entry(map, index, id);
+    }
+
+    /// Tile map 0 (0x9800) or tile map 1 (0x9C00)
+    pub fn tilemap(&self, map: u8) -> &[TileId; 1024] {
+        self.vram.get_tilemap(map)
+    }
+
+    /// Replaces the whole of tile map 0 or 1 directly, without going through the bus
+    pub fn set_tilemap(&mut self, map: u8, ids: [TileId; 1024]) {
+        self.vram.set_tilemap(map, ids);
+    }
+
+    /// Draws the whole of tile map 0 or 1, as the background would show it if scrolled over it
+    ///
+    /// The tile data area and palette are the background's current ones from the LCD registers.
+    pub fn render_background_map(&self, lcd: &Lcd, map: u8) -> ColorImage {
+        let tilemap = self.vram.get_tilemap(map);
+        let data_mode = lcd.control().bg_and_window_tile_data_area();
+        let bg_palette = lcd.background_palette();
+
+        let mut pixels =
+            Vec::with_capacity(BACKGROUND_MAP_SIZE_PIXELS * BACKGROUND_MAP_SIZE_PIXELS);
+
+        for y in 0..BACKGROUND_MAP_SIZE_PIXELS {
+            for x in 0..BACKGROUND_MAP_SIZE_PIXELS {
+                let tile_id = tilemap[(y / 8) * 32 + (x / 8)];
+                let color_id = self.vram.get_tile(data_mode, tile_id).color_data()[y % 8][x % 8];
+
+                pixels.push(self.color_id_to_color(bg_palette, color_id));
+            }
+        }
+
+        ColorImage {
+            size: [BACKGROUND_MAP_SIZE_PIXELS, BACKGROUND_MAP_SIZE_PIXELS],
+            pixels,
+        }
+    }
+
+    /// Loads an OAM dump, such as one taken with [`Ppu::dump_oam`]
+    pub fn load_oam(&mut self, bytes: &[u8]) {
+        self.oam.load(bytes);
+    }
+
+    pub fn dump_oam(&self) -> Vec<u8> {
+        self.oam.dump()
+    }
+
     pub fn step(
         &mut self,
         lcd: &mut Lcd,
@@ -151,6 +253,12 @@ impl Ppu {
             status.set_ppu_mode(new_mode);
         }
 
+        if self.accurate_rendering & (new_mode == PpuMode::PixelDraw) {
+            // Four pixels are pushed out every M-cycle after the OAM scan
+            let drawn_to = ((within_scanline - 20) * 4).min(DISPLAY_WIDTH_PIXELS);
+            self.draw_background(lcd, scanline, drawn_to);
+        }
+
         if (new_mode == PpuMode::HBlank) & (old_mode != PpuMode::HBlank) {
             self.write_scanline(lcd);
         }
@@ -163,11 +271,17 @@ impl Ppu {
             }
 
             self.current_scanline = scanline;
+            self.line_x = 0;
+            self.window_drawn_on_line = false;
 
             if new_mode == PpuMode::VBlank {
                 if old_mode != PpuMode::VBlank {
                     new_frame = true;
                     self.window_scanline = 0;
+
+                    std::mem::swap(&mut self.pixel_buffer, &mut self.front_pixel_buffer);
+                    std::mem::swap(&mut self.rgba_buffer, &mut self.front_rgba_buffer);
+                    std::mem::swap(&mut self.color_id_buffer, &mut self.front_color_id_buffer);
                 }
             }
         }
@@ -180,6 +294,53 @@ impl Ppu {
     }
 
     fn write_scanline(&mut self, lcd: &mut Lcd) {
+        let y = self.current_scanline;
+
+        self.draw_background(lcd, y, DISPLAY_WIDTH_PIXELS);
+
+        if self.window_drawn_on_line {
+            self.window_scanline += 1;
+        }
+
+        self.line_x = 0;
+        self.window_drawn_on_line = false;
+
+        self.select_line_objects(lcd, y);
+
+        // Taken so the objects can be drawn while borrowing self mutably, and put back afterwards
+        let line_objects = std::mem::take(&mut self.line_objects);
+
+        match lcd.control().obj_size() {
+            ObjSize::Single => {
+                for obj in line_objects.iter().rev() {
+                    self.draw_object_8(lcd, *obj, y);
+                }
+            }
+            ObjSize::Double => {
+                for obj in line_objects.iter().rev() {
+                    self.draw_object_16(lcd, *obj, y);
+                }
+            }
+        }
+
+        self.line_objects = line_objects;
+
+        self.update_rgba_row(y);
+    }
+
+    fn update_rgba_row(&mut self, y: usize) {
+        let start = y * DISPLAY_WIDTH_PIXELS;
+        let row = &self.pixel_buffer[start..start + DISPLAY_WIDTH_PIXELS];
+        let rgba_row = &mut self.rgba_buffer[start * 4..(start + DISPLAY_WIDTH_PIXELS) * 4];
+
+        for (pixel, rgba) in row.iter().zip(rgba_row.chunks_exact_mut(4)) {
+            rgba.copy_from_slice(&pixel.to_array());
+        }
+    }
+
+    /// Draws the background and window of scanline `y` from where drawing last stopped up to, but
+    /// not including, pixel `to_x`, using the current register values
+    fn draw_background(&mut self, lcd: &Lcd, y: usize, to_x: usize) {
         let scroll_y = lcd.read_scroll_y();
         let scroll_x = lcd.read_scroll_x();
 
@@ -196,13 +357,13 @@ impl Ppu {
         let bg_palette = lcd.background_palette();
         let data_mode = lcd.control().bg_and_window_tile_data_area();
 
-        let y = self.current_scanline;
-
         let view_y = ((top as usize) + y) % 256;
 
         let bg_enabled = lcd.control().bg_and_window_enabled();
 
-        for x in 0..DISPLAY_WIDTH_PIXELS {
+        let from_x = self.line_x;
+
+        for x in from_x..to_x {
             let view_x = ((left as usize) + x) % 256;
 
             let tile_location = ((view_y / 8) * 32) + (view_x / 8);
@@ -218,13 +379,11 @@ impl Ppu {
             let pixel_index = (y * DISPLAY_WIDTH_PIXELS) + x;
             let color_id = color_ids[tile_y][tile_x];
 
-            if bg_enabled {
-                self.bg_priority[pixel_index] = color_id != ColorId::Zero;
-                self.pixel_buffer[pixel_index] = self.color_id_to_color(bg_palette, color_id);
-            } else {
-                self.bg_priority[pixel_index] = false;
-                self.pixel_buffer[pixel_index] = self.color_id_to_color(bg_palette, ColorId::Zero);
-            }
+            let color_id = if bg_enabled { color_id } else { ColorId::Zero };
+
+            self.bg_priority[pixel_index] = color_id != ColorId::Zero;
+            self.color_id_buffer[pixel_index] = color_id;
+            self.pixel_buffer[pixel_index] = self.color_id_to_color(bg_palette, color_id);
         }
 
         if lcd.control().window_enabled() {
@@ -239,14 +398,12 @@ impl Ppu {
             if y >= window_y {
                 let inside_y = self.window_scanline;
 
-                let mut rendered = false;
-
-                for x in 0..DISPLAY_WIDTH_PIXELS {
+                for x in from_x..to_x {
                     if x < (window_x - 7) {
                         continue;
                     }
 
-                    rendered = true;
+                    self.window_drawn_on_line = true;
 
                     let inside_x = x - (window_x - 7);
                     let tile_location = ((inside_y / 8) * 32) + (inside_x / 8);
@@ -263,21 +420,25 @@ impl Ppu {
                     let color_id = color_ids[tile_y][tile_x];
 
                     self.bg_priority[pixel_index] = color_id != ColorId::Zero;
+                    self.color_id_buffer[pixel_index] = color_id;
                     self.pixel_buffer[pixel_index] = self.color_id_to_color(bg_palette, color_id);
                 }
-
-                if rendered {
-                    self.window_scanline += 1;
-                }
             }
         }
 
-        let obj_size = lcd.control().obj_size();
-        let height = match obj_size {
+        self.line_x = self.line_x.max(to_x);
+    }
+
+    /// Fills `line_objects` with up to 10 objects on scanline `y`, highest priority first
+    ///
+    /// Objects are ordered by x position, unless OPRI selects OAM order.
+    fn select_line_objects(&mut self, lcd: &Lcd, y: usize) {
+        let height = match lcd.control().obj_size() {
             ObjSize::Single => 8,
             ObjSize::Double => 16,
         };
-        let mut line_objects = Vec::new();
+
+        self.line_objects.clear();
 
         if lcd.control().obj_enabled() {
             for obj in self.oam.objects() {
@@ -288,25 +449,16 @@ impl Ppu {
                 let obj_y = (obj.y_pos() - 16) as usize;
 
                 if (y >= obj_y) & (y < (obj_y + height)) {
-                    line_objects.push(*obj);
+                    self.line_objects.push(*obj);
                 }
             }
         }
 
-        line_objects.sort_by(|a, b| a.x_pos().cmp(&b.x_pos()));
-
-        match obj_size {
-            ObjSize::Single => {
-                for obj in line_objects.iter().take(10).rev() {
-                    self.draw_object_8(lcd, *obj, y);
-                }
-            }
-            ObjSize::Double => {
-                for obj in line_objects.iter().take(10).rev() {
-                    self.draw_object_16(lcd, *obj, y);
-                }
-            }
+        if lcd.obj_priority() == ObjPriority::Coordinate {
+            self.line_objects.sort_by(|a, b| a.x_pos().cmp(&b.x_pos()));
         }
+
+        self.line_objects.truncate(10);
     }
 
     fn draw_object_8(&mut self, lcd: &mut Lcd, obj: ObjectAttributes, y: usize) {
@@ -385,12 +537,33 @@ impl Ppu {
         }
     }
 
+    /// The last finished frame, so a frame still being drawn is never shown
     pub fn render(&mut self, lcd: &mut Lcd) -> &[Color32; TOTAL_PIXELS] {
         if !lcd.control().lcd_enabled() {
             return self.off_display.as_ref();
         }
 
-        &self.pixel_buffer
+        &self.front_pixel_buffer
+    }
+
+    /// The last finished frame as tightly packed RGBA bytes, row by row
+    pub fn frame_rgba(&self, lcd: &Lcd) -> &[u8; RGBA_FRAME_LENGTH] {
+        if !lcd.control().lcd_enabled() {
+            return self.off_display_rgba.as_ref();
+        }
+
+        &self.front_rgba_buffer
+    }
+
+    /// The background and window color ids of the last finished frame, before BGP turns them into
+    /// shades, for applying a custom palette
+    ///
+    /// Objects aren't included, as they have palettes of their own: where an object covers the
+    /// background this still holds the background's color id. With the background disabled every
+    /// id is zero, which is the shade that is shown. While the LCD is off this is the last frame
+    /// drawn before it was turned off.
+    pub fn frame_color_ids(&self) -> &[ColorId; TOTAL_PIXELS] {
+        &self.front_color_id_buffer
     }
 
     fn color_id_to_color(&self, palette: Palette, color_id: ColorId) -> Color32 {
@@ -418,4 +591,332 @@ impl Ppu {
     fn off_display() -> Box<[Color32; TOTAL_PIXELS]> {
         Box::new([OFF_COLOR; TOTAL_PIXELS])
     }
+
+    fn filled_rgba(color: Color32) -> Box<[u8; RGBA_FRAME_LENGTH]> {
+        let mut buffer = Box::new([0u8; RGBA_FRAME_LENGTH]);
+
+        for rgba in buffer.chunks_exact_mut(4) {
+            rgba.copy_from_slice(&color.to_array());
+        }
+
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io::lcd::Lcd;
+
+    use super::{
+        oam::OAM_SIZE,
+        vram::{ColorId, Tile, TileId, VRAM_SIZE},
+        Color32, Ppu, DARKER_COLOR, DARKEST_COLOR, LIGHTER_COLOR, LIGHTEST_COLOR,
+    };
+
+    /// Places an object in OAM at the given screen position, in OAM coordinates
+    fn place_object(ppu: &mut Ppu, index: u16, y: u8, x: u8) {
+        let address = 0xFE00 + index * 4;
+        ppu.oam_mut().write_u8(address, y).unwrap();
+        ppu.oam_mut().write_u8(address + 1, x).unwrap();
+        ppu.oam_mut().write_u8(address + 2, index as u8).unwrap();
+    }
+
+    /// Steps the PPU until the frame it is drawing is finished and can be rendered
+    fn finish_frame(ppu: &mut Ppu, lcd: &mut Lcd) {
+        while !ppu.step(lcd, 1).2 {}
+    }
+
+    fn selected_x_positions(ppu: &Ppu) -> Vec<u8> {
+        ppu.line_objects.iter().map(|obj| obj.x_pos()).collect()
+    }
+
+    #[test]
+    fn line_object_selection_across_scanlines() {
+        let mut ppu = Ppu::new();
+        let mut lcd = Lcd::new();
+        // LCD on, objects on, 8x8 objects
+        lcd.write_control(0b1000_0010);
+
+        // 12 objects on screen line 0, in reverse x order
+        for i in 0..12 {
+            place_object(&mut ppu, i, 16, 120 - i as u8 * 8);
+        }
+        // One object on screen lines 8 to 15, and one hidden above the screen
+        place_object(&mut ppu, 12, 24, 50);
+        place_object(&mut ppu, 13, 8, 60);
+
+        ppu.select_line_objects(&lcd, 0);
+        assert_eq!(
+            selected_x_positions(&ppu),
+            vec![32, 40, 48, 56, 64, 72, 80, 88, 96, 104]
+        );
+
+        ppu.select_line_objects(&lcd, 8);
+        assert_eq!(selected_x_positions(&ppu), vec![50]);
+
+        ppu.select_line_objects(&lcd, 16);
+        assert!(selected_x_positions(&ppu).is_empty());
+
+        // Tall objects reach the next 8 lines too
+        lcd.write_control(0b1000_0110);
+        ppu.select_line_objects(&lcd, 8);
+        assert_eq!(selected_x_positions(&ppu).len(), 10);
+        assert_eq!(selected_x_positions(&ppu)[0], 32);
+
+        lcd.write_control(0b1000_0000);
+        ppu.select_line_objects(&lcd, 0);
+        assert!(selected_x_positions(&ppu).is_empty());
+
+        assert!(ppu.line_objects.capacity() >= 40);
+    }
+
+    /// Draws the first scanline, changing SCX from 0 to 128 part way through drawing it
+    ///
+    /// The left half of the background map is blank and the right half solid black.
+    fn draw_with_mid_scanline_scroll(accurate: bool) -> (Ppu, Lcd) {
+        let mut ppu = Ppu::new();
+        ppu.set_accurate_rendering(accurate);
+
+        for i in 0..16 {
+            ppu.vram_mut().write_u8(0x8010 + i, 0xFF).unwrap();
+        }
+        for i in 16..32 {
+            ppu.vram_mut().write_u8(0x9800 + i, 0x01).unwrap();
+        }
+
+        let mut lcd = Lcd::new();
+        lcd.write_background_palette(0b1110_0100);
+        lcd.write_control(0b1001_0001);
+
+        // 10 M-cycles into drawing pixels, so the first 40 pixels have been drawn
+        for _ in 0..30 {
+            ppu.step(&mut lcd, 1);
+        }
+        lcd.write_scroll_x(128);
+
+        finish_frame(&mut ppu, &mut lcd);
+
+        (ppu, lcd)
+    }
+
+    #[test]
+    fn accurate_renderer_shows_mid_scanline_scroll() {
+        let (mut ppu, mut lcd) = draw_with_mid_scanline_scroll(true);
+        let line = &ppu.render(&mut lcd)[..160];
+
+        assert!(line[..40].iter().all(|pixel| *pixel == LIGHTEST_COLOR));
+        assert!(line[40..128].iter().all(|pixel| *pixel == DARKEST_COLOR));
+        assert!(line[128..].iter().all(|pixel| *pixel == LIGHTEST_COLOR));
+    }
+
+    #[test]
+    fn fast_renderer_uses_scroll_at_end_of_scanline() {
+        let (mut ppu, mut lcd) = draw_with_mid_scanline_scroll(false);
+        let line = &ppu.render(&mut lcd)[..160];
+
+        assert!(line[..128].iter().all(|pixel| *pixel == DARKEST_COLOR));
+        assert!(line[128..].iter().all(|pixel| *pixel == LIGHTEST_COLOR));
+    }
+
+    #[test]
+    fn render_scanline_from_vram_dump() {
+        let mut vram = vec![0; VRAM_SIZE];
+        // Tile 1, from the Pan Docs tile data example
+        vram[0x10..0x20].copy_from_slice(&[
+            0x3C, 0x7E, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x7E, 0x5E, 0x7E, 0x0A, 0x7C, 0x56,
+            0x38, 0x7C,
+        ]);
+        // The first row of the background map is filled with it
+        vram[0x1800..0x1820].fill(0x01);
+
+        let mut ppu = Ppu::new();
+        ppu.load_vram(&vram);
+        assert_eq!(ppu.dump_vram(), vram);
+
+        let mut lcd = Lcd::new();
+        lcd.write_background_palette(0b1110_0100);
+        lcd.write_control(0b1001_0001);
+
+        finish_frame(&mut ppu, &mut lcd);
+
+        let (l, d, k) = (LIGHTEST_COLOR, DARKER_COLOR, DARKEST_COLOR);
+        let tile_row = [l, d, k, k, k, k, d, l];
+        let line = &ppu.render(&mut lcd)[..160];
+
+        assert!(line.chunks(8).all(|pixels| pixels == tile_row));
+    }
+
+    #[test]
+    fn render_scanline_from_placed_tile() {
+        let mut ppu = Ppu::new();
+        ppu.set_tile(
+            1,
+            Tile::from_bytes(&[
+                0x3C, 0x7E, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x7E, 0x5E, 0x7E, 0x0A, 0x7C, 0x56,
+                0x38, 0x7C,
+            ]),
+        );
+        ppu.set_tilemap_entry(0, 1, TileId::new(1));
+
+        let mut lcd = Lcd::new();
+        lcd.write_background_palette(0b1110_0100);
+        lcd.write_control(0b1001_0001);
+
+        finish_frame(&mut ppu, &mut lcd);
+
+        let (l, d, k) = (LIGHTEST_COLOR, DARKER_COLOR, DARKEST_COLOR);
+        let line = &ppu.render(&mut lcd)[..160];
+
+        assert!(line[..8].iter().all(|pixel| *pixel == LIGHTEST_COLOR));
+        assert_eq!(line[8..16], [l, d, k, k, k, k, d, l]);
+        assert!(line[16..].iter().all(|pixel| *pixel == LIGHTEST_COLOR));
+        assert_eq!(ppu.vram().read_u8(0x8010).unwrap(), 0x3C);
+        assert_eq!(ppu.vram().read_u8(0x9801).unwrap(), 0x01);
+    }
+
+    #[test]
+    fn render_background_map_from_filled_tilemap() {
+        let mut ppu = Ppu::new();
+        // A checkered tile: the first row is color 1 and 2 alternating, the rest color 3
+        let mut bytes = [0xFF; 16];
+        bytes[0] = 0xAA;
+        bytes[1] = 0x55;
+        ppu.set_tile(1, Tile::from_bytes(&bytes));
+        ppu.set_tilemap(0, [TileId::new(1); 1024]);
+
+        let mut lcd = Lcd::new();
+        lcd.write_background_palette(0b1110_0100);
+        lcd.write_control(0b1001_0001);
+
+        let image = ppu.render_background_map(&lcd, 0);
+
+        assert_eq!(image.size, [256, 256]);
+        assert_eq!(image.pixels.len(), 256 * 256);
+        assert_eq!(image.pixels[0], LIGHTER_COLOR);
+        assert_eq!(image.pixels[1], DARKER_COLOR);
+        // The bottom right corner of the last tile, and the top left of the one at (1, 1)
+        assert_eq!(image.pixels[256 * 256 - 1], DARKEST_COLOR);
+        assert_eq!(image.pixels[(8 * 256) + 8], LIGHTER_COLOR);
+
+        // The other map is still all tile 0, which is blank
+        let other = ppu.render_background_map(&lcd, 1);
+        assert!(other.pixels.iter().all(|pixel| *pixel == LIGHTEST_COLOR));
+    }
+
+    #[test]
+    fn color_ids_map_to_rendered_colors() {
+        let mut ppu = Ppu::new();
+        // Every row of the tile is 0, 0, 1, 1, 2, 2, 3, 3
+        let mut bytes = [0x33; 16];
+        for high in bytes.iter_mut().skip(1).step_by(2) {
+            *high = 0x0F;
+        }
+        ppu.set_tile(1, Tile::from_bytes(&bytes));
+        let mut tilemap = [TileId::new(0); 1024];
+        for (index, id) in tilemap.iter_mut().enumerate() {
+            if index % 3 != 0 {
+                *id = TileId::new(1);
+            }
+        }
+        ppu.set_tilemap(0, tilemap);
+
+        let mut lcd = Lcd::new();
+        // A palette that isn't the identity, so ids and shades can't be confused
+        lcd.write_background_palette(0b0010_0111);
+        lcd.write_control(0b1001_0001);
+
+        finish_frame(&mut ppu, &mut lcd);
+
+        let palette = lcd.background_palette();
+        let ids = *ppu.frame_color_ids();
+        let pixels = *ppu.render(&mut lcd);
+
+        for (id, pixel) in ids.iter().zip(pixels.iter()) {
+            assert_eq!(ppu.color_id_to_color(palette, *id), *pixel);
+        }
+        for id in [ColorId::Zero, ColorId::One, ColorId::Two, ColorId::Three] {
+            assert!(ids.contains(&id), "{:?}", id);
+        }
+    }
+
+    #[test]
+    fn render_shows_last_finished_frame() {
+        let mut ppu = Ppu::new();
+        for i in 0..16 {
+            ppu.vram_mut().write_u8(0x8010 + i, 0xFF).unwrap();
+        }
+        for i in 0..32 {
+            ppu.vram_mut().write_u8(0x9800 + i, 0x01).unwrap();
+        }
+
+        let mut lcd = Lcd::new();
+        lcd.write_background_palette(0b1110_0100);
+        lcd.write_control(0b1001_0001);
+
+        // The first scanline has been drawn, but the frame isn't finished
+        while ppu.current_scanline < 2 {
+            assert!(!ppu.step(&mut lcd, 1).2);
+        }
+        assert_eq!(ppu.render(&mut lcd)[0], LIGHTEST_COLOR);
+        assert_eq!(ppu.frame_rgba(&lcd)[..4], LIGHTEST_COLOR.to_array());
+
+        finish_frame(&mut ppu, &mut lcd);
+        assert_eq!(ppu.render(&mut lcd)[0], DARKEST_COLOR);
+        assert_eq!(ppu.frame_rgba(&lcd)[..4], DARKEST_COLOR.to_array());
+
+        // Drawing the next frame leaves the finished one alone
+        lcd.write_background_palette(0b0000_0000);
+        while ppu.current_scanline != 2 {
+            ppu.step(&mut lcd, 1);
+        }
+        assert_eq!(ppu.render(&mut lcd)[0], DARKEST_COLOR);
+    }
+
+    #[test]
+    fn oam_dump_round_trip() {
+        // Only the upper nibble of the attribute flags is used on the DMG
+        let oam: Vec<u8> = (0..OAM_SIZE as u8)
+            .map(|i| if i % 4 == 3 { i << 4 } else { i })
+            .collect();
+
+        let mut ppu = Ppu::new();
+        ppu.load_oam(&oam);
+
+        assert_eq!(ppu.dump_oam(), oam);
+        assert_eq!(ppu.oam().objects()[1].x_pos(), 5);
+    }
+
+    /// Draws the first scanline with two overlapping objects, returning the color where they overlap
+    ///
+    /// Object 0 is drawn in color 3 at x = 16, and object 1 in color 1 at x = 12.
+    fn overlapping_object_color(obj_priority: u8) -> Color32 {
+        let mut ppu = Ppu::new();
+
+        for i in 0..16 {
+            ppu.vram_mut().write_u8(0x8000 + i, 0xFF).unwrap();
+            ppu.vram_mut()
+                .write_u8(0x8010 + i, if i % 2 == 0 { 0xFF } else { 0x00 })
+                .unwrap();
+        }
+
+        place_object(&mut ppu, 0, 16, 24);
+        place_object(&mut ppu, 1, 16, 20);
+
+        let mut lcd = Lcd::new();
+        lcd.write_obj_palette_0(0b1110_0100);
+        lcd.write_obj_priority(obj_priority);
+        lcd.write_control(0b1000_0010);
+
+        finish_frame(&mut ppu, &mut lcd);
+
+        ppu.render(&mut lcd)[16]
+    }
+
+    #[test]
+    fn object_priority_follows_opri() {
+        // Coordinate priority, the leftmost object is on top
+        assert_eq!(overlapping_object_color(0x01), LIGHTER_COLOR);
+        // OAM priority, the first object is on top
+        assert_eq!(overlapping_object_color(0x00), DARKEST_COLOR);
+    }
 }
@@ -1,5 +1,10 @@
+use crate::cpu::error::Error;
+
 use super::vram::TileId;
 
+/// The number of bytes of object attribute memory
+pub const OAM_SIZE: usize = 0xA0;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PaletteSelection {
     Pallete0,
@@ -122,33 +127,88 @@ impl ObjectAttributeMemory {
         &self.objects
     }
 
-    pub fn read_u8(&self, address: u16) -> u8 {
-        let oam_addr = address - 0xFE00;
+    /// Writes the bytes to the start of OAM, as if they were written through the bus
+    ///
+    /// Anything past the end of OAM is ignored.
+    pub fn load(&mut self, bytes: &[u8]) {
+        for (offset, byte) in bytes.iter().take(OAM_SIZE).enumerate() {
+            self.write_u8(0xFE00 + offset as u16, *byte)
+                .expect("Writes within OAM should never fail");
+        }
+    }
+
+    /// A copy of all of OAM, in the layout it appears on the bus
+    pub fn dump(&self) -> Vec<u8> {
+        (0..OAM_SIZE)
+            .map(|offset| {
+                self.read_u8(0xFE00 + offset as u16)
+                    .expect("Reads within OAM should never fail")
+            })
+            .collect()
+    }
+
+    pub fn read_u8(&self, address: u16) -> Result<u8, Error> {
+        let oam_addr = address.wrapping_sub(0xFE00);
         let object_index = (oam_addr / 4) as usize;
         let attribute_index = oam_addr % 4;
-        let object = &self.objects[object_index];
+        let object = self
+            .objects
+            .get(object_index)
+            .ok_or(Error::MemoryReadFault(address))?;
 
-        match attribute_index {
+        Ok(match attribute_index {
             0 => object.y_position,
             1 => object.x_position,
             2 => u8::from(object.tile_index),
-            3 => u8::from(&object.attributes),
-            _ => panic!(),
-        }
+            _ => u8::from(&object.attributes),
+        })
     }
 
-    pub fn write_u8(&mut self, address: u16, data: u8) {
-        let oam_addr = address - 0xFE00;
+    pub fn write_u8(&mut self, address: u16, data: u8) -> Result<(), Error> {
+        let oam_addr = address.wrapping_sub(0xFE00);
         let object_index = (oam_addr / 4) as usize;
         let attribute_index = oam_addr % 4;
-        let object = &mut self.objects[object_index];
+        let object = self
+            .objects
+            .get_mut(object_index)
+            .ok_or(Error::MemoryWriteFault(address, data))?;
 
         match attribute_index {
             0 => object.y_position = data,
             1 => object.x_position = data,
             2 => object.tile_index = TileId::new(data),
-            3 => object.attributes = Flags::from(data),
-            _ => panic!(),
+            _ => object.attributes = Flags::from(data),
         }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ObjectAttributeMemory, PaletteSelection};
+
+    #[test]
+    fn boundary_addresses() {
+        let mut oam = ObjectAttributeMemory::zeroed();
+
+        oam.write_u8(0xFE00, 0x10).unwrap();
+        oam.write_u8(0xFE9F, 0b1011_0000).unwrap();
+
+        assert_eq!(oam.read_u8(0xFE00).unwrap(), 0x10);
+        assert_eq!(oam.read_u8(0xFE9F).unwrap(), 0b1011_0000);
+        let attributes = oam.objects()[39].attributes();
+        assert_eq!(attributes.palette(), PaletteSelection::Pallete1);
+    }
+
+    #[test]
+    fn out_of_range_addresses() {
+        let mut oam = ObjectAttributeMemory::zeroed();
+
+        assert!(oam.read_u8(0xFEA0).is_err());
+        assert!(oam.read_u8(0xFDFF).is_err());
+        assert!(oam.read_u8(0x0000).is_err());
+        assert!(oam.write_u8(0xFEA0, 0).is_err());
+        assert!(oam.write_u8(0xFDFF, 0).is_err());
     }
 }
@@ -1,5 +1,8 @@
 use crate::io::lcd::TileDataArea;
 
+/// The number of bytes of VRAM in a single bank
+pub const VRAM_SIZE: usize = 0x2000;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ColorId {
     Zero,
@@ -26,6 +29,10 @@ impl Tile {
         &self.data
     }
 
+    /// The color of each pixel, indexed by row then column
+    ///
+    /// This is decoded when the tile is written rather than when it is drawn, as the renderer
+    /// reads every pixel of a visible tile on each scanline while tiles are rarely written.
     pub fn color_data(&self) -> &[[ColorId; 8]; 8] {
         &self.colors
     }
@@ -34,15 +41,32 @@ impl Tile {
         self.data[index]
     }
 
-    pub fn write(&mut self, index: usize, data: u8) {
-        self.data[index] = data;
+    /// Decodes a tile from its 16 bytes in the 2bpp format used in VRAM
+    pub fn from_bytes(bytes: &[u8; 16]) -> Self {
+        let mut tile = Self {
+            data: *bytes,
+            colors: [[ColorId::Zero; 8]; 8],
+        };
 
-        let row_start = index & 0xFFFE;
+        for row_idx in 0..8 {
+            tile.decode_row(row_idx);
+        }
 
-        let lo_bits = self.data[row_start];
-        let hi_bits = self.data[row_start + 1];
+        tile
+    }
 
-        let row_idx = index / 2;
+    pub fn to_bytes(&self) -> [u8; 16] {
+        self.data
+    }
+
+    pub fn write(&mut self, index: usize, data: u8) {
+        self.data[index] = data;
+        self.decode_row(index / 2);
+    }
+
+    fn decode_row(&mut self, row_idx: usize) {
+        let lo_bits = self.data[row_idx * 2];
+        let hi_bits = self.data[row_idx * 2 + 1];
 
         for col_idx in 0..8 {
             let mask = 1 << (7 - col_idx);
@@ -143,8 +167,58 @@ impl Vram {
         &self.map1
     }
 
+    /// Replaces one of the 384 tiles, indexed as they are laid out from 0x8000
+    pub fn set_tile(&mut self, index: usize, tile: Tile) {
+        self.tiles[index] = tile;
+    }
+
+    /// Tile map 0 (0x9800) or tile map 1 (0x9C00)
+    pub fn get_tilemap(&self, map: u8) -> &[TileId; 1024] {
+        match map {
+            0 => &self.map0,
+            _ => &self.map1,
+        }
+    }
+
+    pub fn get_tilemap_mut(&mut self, map: u8) -> &mut [TileId; 1024] {
+        match map {
+            0 => &mut self.map0,
+            _ => &mut self.map1,
+        }
+    }
+
+    /// Replaces the whole of tile map 0 (0x9800) or tile map 1 (0x9C00)
+    pub fn set_tilemap(&mut self, map: u8, ids: [TileId; 1024]) {
+        *self.get_tilemap_mut(map) = ids;
+    }
+
+    /// Sets an entry of tile map 0 (0x9800) or tile map 1 (0x9C00)
+    pub fn set_map_entry(&mut self, map: u8, index: usize, id: TileId) {
+        self.get_tilemap_mut(map)[index] = id;
+    }
+
+    /// Writes the bytes to the start of VRAM, as if they were written through the bus
+    ///
+    /// Anything past the end of VRAM is ignored.
+    pub fn load(&mut self, bytes: &[u8]) {
+        for (offset, byte) in bytes.iter().take(VRAM_SIZE).enumerate() {
+            self.write_u8(0x8000 + offset as u16, *byte)
+                .expect("Writes within VRAM should never fail");
+        }
+    }
+
+    /// A copy of all of VRAM, in the layout it appears on the bus
+    pub fn dump(&self) -> Vec<u8> {
+        (0..VRAM_SIZE)
+            .map(|offset| {
+                self.read_u8(0x8000 + offset as u16)
+                    .expect("Reads within VRAM should never fail")
+            })
+            .collect()
+    }
+
     pub fn read_u8(&self, address: u16) -> Result<u8, crate::cpu::error::Error> {
-        let vram_addr = address - 0x8000;
+        let vram_addr = address.wrapping_sub(0x8000);
 
         Ok(match vram_addr {
             0x0000..=0x17FF => {
@@ -164,7 +238,7 @@ impl Vram {
     }
 
     pub fn write_u8(&mut self, address: u16, data: u8) -> Result<(), crate::cpu::error::Error> {
-        let vram_addr = address - 0x8000;
+        let vram_addr = address.wrapping_sub(0x8000);
 
         match vram_addr {
             0x0000..=0x17FF => {
@@ -188,3 +262,89 @@ impl Vram {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ColorId::{self, One as I, Three as T, Two as W, Zero as O},
+        Tile, Vram,
+    };
+
+    /// The example tile from the Pan Docs description of the 2bpp tile format
+    const EXAMPLE_TILE: [u8; 16] = [
+        0x3C, 0x7E, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x7E, 0x5E, 0x7E, 0x0A, 0x7C, 0x56, 0x38,
+        0x7C,
+    ];
+
+    const EXAMPLE_COLORS: [[ColorId; 8]; 8] = [
+        [O, W, T, T, T, T, W, O],
+        [O, T, O, O, O, O, T, O],
+        [O, T, O, O, O, O, T, O],
+        [O, T, O, O, O, O, T, O],
+        [O, T, I, T, T, T, T, O],
+        [O, I, I, I, T, I, T, O],
+        [O, T, I, T, I, T, W, O],
+        [O, W, T, T, T, W, O, O],
+    ];
+
+    #[test]
+    fn tile_colors_use_2bpp_interleaving() {
+        let mut tile = Tile::zeroed();
+        for (i, byte) in EXAMPLE_TILE.iter().enumerate() {
+            tile.write(i, *byte);
+        }
+
+        assert_eq!(tile.color_data(), &EXAMPLE_COLORS);
+        assert_eq!(tile.data(), &EXAMPLE_TILE);
+    }
+
+    #[test]
+    fn tile_colors_independent_of_write_order() {
+        let mut tile = Tile::zeroed();
+        for (i, byte) in EXAMPLE_TILE.iter().enumerate().rev() {
+            tile.write(i, *byte);
+        }
+
+        assert_eq!(tile.color_data(), &EXAMPLE_COLORS);
+    }
+
+    #[test]
+    fn tile_bytes_round_trip() {
+        let tile = Tile::from_bytes(&EXAMPLE_TILE);
+
+        assert_eq!(tile.color_data(), &EXAMPLE_COLORS);
+        assert_eq!(tile.to_bytes(), EXAMPLE_TILE);
+
+        let mut written = Tile::zeroed();
+        for (i, byte) in EXAMPLE_TILE.iter().enumerate() {
+            written.write(i, *byte);
+        }
+        assert_eq!(written.color_data(), tile.color_data());
+    }
+
+    #[test]
+    fn boundary_addresses() {
+        let mut vram = Vram::zeroed();
+
+        vram.write_u8(0x8000, 0xAB).unwrap();
+        vram.write_u8(0x97FF, 0xCD).unwrap();
+        vram.write_u8(0x9800, 0x01).unwrap();
+        vram.write_u8(0x9FFF, 0x02).unwrap();
+
+        assert_eq!(vram.read_u8(0x8000).unwrap(), 0xAB);
+        assert_eq!(vram.read_u8(0x97FF).unwrap(), 0xCD);
+        assert_eq!(vram.read_u8(0x9800).unwrap(), 0x01);
+        assert_eq!(vram.read_u8(0x9FFF).unwrap(), 0x02);
+    }
+
+    #[test]
+    fn out_of_range_addresses() {
+        let mut vram = Vram::zeroed();
+
+        assert!(vram.read_u8(0xA000).is_err());
+        assert!(vram.read_u8(0x7FFF).is_err());
+        assert!(vram.read_u8(0x0000).is_err());
+        assert!(vram.write_u8(0xA000, 0).is_err());
+        assert!(vram.write_u8(0x7FFF, 0).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+/// Labels for addresses, as found in the `.sym` files written by RGBDS and read by BGB
+///
+/// Each line is `BB:AAAA name`, giving the bank and address of a label in hexadecimal. Anything
+/// after a `;` is a comment, and lines that can't be parsed are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    labels: HashMap<(u8, u16), String>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self {
+            labels: HashMap::new(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut table = Self::new();
+
+        for line in text.lines() {
+            let line = match line.split_once(';') {
+                Some((before_comment, _)) => before_comment,
+                None => line,
+            };
+
+            let mut parts = line.split_whitespace();
+
+            let (Some(location), Some(name)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+
+            let Some((bank, address)) = location.split_once(':') else {
+                continue;
+            };
+
+            if let (Ok(bank), Ok(address)) = (
+                u8::from_str_radix(bank, 16),
+                u16::from_str_radix(address, 16),
+            ) {
+                table.insert(bank, address, name);
+            }
+        }
+
+        table
+    }
+
+    pub fn insert(&mut self, bank: u8, address: u16, name: &str) {
+        self.labels.insert((bank, address), name.to_string());
+    }
+
+    pub fn label(&self, bank: u8, address: u16) -> Option<&str> {
+        self.labels.get(&(bank, address)).map(String::as_str)
+    }
+
+    /// The label for an address as the CPU sees it, with `rom_bank` mapped at 0x4000-0x7FFF
+    ///
+    /// Everything outside 0x4000-0x7FFF is looked up in bank 0, which is how RGBDS numbers it,
+    /// even when a mapper like MMM01 has another bank at 0x0000-0x3FFF. Banks above 0xFF have no
+    /// labels, since `.sym` banks are read as a byte.
+    pub fn label_at(&self, address: u16, rom_bank: usize) -> Option<&str> {
+        let bank = match address {
+            0x4000..=0x7FFF => u8::try_from(rom_bank).ok()?,
+            _ => 0,
+        };
+
+        self.label(bank, address)
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SymbolTable;
+
+    const SYM_FILE: &str = "\
+; File generated by rgblink
+00:0100 EntryPoint
+00:0150 Main
+01:4000 BankedRoutine ; a comment
+not a symbol
+00:zzzz Broken
+";
+
+    #[test]
+    fn parse_sym_file() {
+        let symbols = SymbolTable::parse(SYM_FILE);
+
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols.label(0, 0x0100), Some("EntryPoint"));
+        assert_eq!(symbols.label(1, 0x4000), Some("BankedRoutine"));
+        assert_eq!(symbols.label(0, 0x4000), None);
+
+        assert_eq!(symbols.label_at(0x4000, 1), Some("BankedRoutine"));
+        assert_eq!(symbols.label_at(0x0101, 1), None);
+    }
+
+    #[test]
+    fn label_at_uses_the_mapped_bank() {
+        let mut symbols = SymbolTable::parse(SYM_FILE);
+        symbols.insert(2, 0x4000, "OtherBank");
+
+        assert_eq!(symbols.label_at(0x4000, 2), Some("OtherBank"));
+        assert_eq!(symbols.label_at(0x4000, 3), None);
+        assert_eq!(symbols.label_at(0x4000, 0x101), None);
+
+        // Bank 0 is always used below 0x4000
+        assert_eq!(symbols.label_at(0x0150, 2), Some("Main"));
+    }
+}